@@ -0,0 +1,155 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use super::interpolation::Interpolation;
+
+/// Camera pose at a tick, in mesh space. `zoom` sets how far the camera is from `target`, so
+/// `eye` is only the direction it looks from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraKey {
+    pub frame: u32,
+    pub eye: Vec3,
+    pub target: Vec3,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    pub zoom: f32,
+    /// Perspective at 0, orthographic at 1. Older projects don't store it and load as
+    /// perspective.
+    #[serde(default)]
+    pub orthographic: f32,
+}
+
+impl Default for CameraKey {
+    /// The view the viewport starts with
+    fn default() -> Self {
+        Self {
+            frame: 0,
+            eye: Vec3::Z,
+            target: Vec3::ZERO,
+            fov: 45.0,
+            zoom: -5.0,
+            orthographic: 0.0,
+        }
+    }
+}
+
+impl CameraKey {
+    /// Swings the eye around the target rather than cutting straight across, so orbits keep
+    /// their distance
+    pub fn lerp(&self, other: &CameraKey, t: f32) -> CameraKey {
+        let target = self.target.lerp(other.target, t);
+        let (a, b) = (self.eye - self.target, other.eye - other.target);
+        let distance = a.length() + (b.length() - a.length()) * t;
+        let direction = slerp(a.normalize_or_zero(), b.normalize_or_zero(), t);
+
+        CameraKey {
+            frame: self.frame,
+            eye: target + direction * distance,
+            target,
+            fov: self.fov + (other.fov - self.fov) * t,
+            zoom: self.zoom + (other.zoom - self.zoom) * t,
+            orthographic: self.orthographic + (other.orthographic - self.orthographic) * t,
+        }
+    }
+}
+
+/// Keyframed camera for renders, sorted by frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraTrack {
+    pub keys: Vec<CameraKey>,
+    pub interpolation: Interpolation,
+    /// Views saved to the number keys, kept apart from the keys so they never animate
+    #[serde(default)]
+    pub bookmarks: [Option<CameraKey>; 9],
+}
+
+impl Default for CameraTrack {
+    fn default() -> Self {
+        Self {
+            keys: vec![],
+            interpolation: Interpolation::EaseInOut,
+            bookmarks: [None; 9],
+        }
+    }
+}
+
+impl CameraTrack {
+    /// Full orbits around `target` over `duration` ticks, starting from `eye`. Orbits too short
+    /// to give every key its own tick take a tick per key instead.
+    pub fn turntable(eye: Vec3, target: Vec3, fov: f32, zoom: f32, duration: u32) -> Self {
+        // Enough keys that every segment is well under half a turn
+        const KEYS: u32 = 8;
+        let offset = eye - target;
+
+        Self {
+            keys: (0..=KEYS)
+                .map(|i| {
+                    let angle = std::f32::consts::TAU * i as f32 / KEYS as f32;
+                    CameraKey {
+                        frame: (duration * i / KEYS).max(i),
+                        eye: target + glam::Quat::from_rotation_y(angle) * offset,
+                        target,
+                        fov,
+                        zoom,
+                        orthographic: 0.0,
+                    }
+                })
+                .collect(),
+            interpolation: Interpolation::Linear,
+            bookmarks: [None; 9],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Adds a key, replacing any key already on its frame
+    pub fn set_key(&mut self, key: CameraKey) {
+        match self.keys.binary_search_by_key(&key.frame, |k| k.frame) {
+            Ok(i) => self.keys[i] = key,
+            Err(i) => self.keys.insert(i, key),
+        }
+    }
+
+    pub fn remove_key(&mut self, frame: u32) -> bool {
+        let len = self.keys.len();
+        self.keys.retain(|k| k.frame != frame);
+        self.keys.len() != len
+    }
+
+    /// Pose at `tick`, holding the first and last keys outside of them
+    pub fn sample(&self, tick: f32) -> Option<CameraKey> {
+        let next = self.keys.iter().position(|k| k.frame as f32 > tick);
+        let (a, b) = match next {
+            None => return self.keys.last().copied(),
+            Some(0) => return self.keys.first().copied(),
+            Some(i) => (&self.keys[i - 1], &self.keys[i]),
+        };
+
+        let t = (tick - a.frame as f32) / (b.frame - a.frame) as f32;
+        Some(CameraKey {
+            frame: tick.max(0.0) as u32,
+            ..a.lerp(b, self.interpolation.apply(t))
+        })
+    }
+}
+
+/// Spherical interpolation between unit vectors
+fn slerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    let angle = a.dot(b).clamp(-1.0, 1.0).acos();
+    if angle < 1e-4 {
+        return a.lerp(b, t).normalize_or_zero();
+    }
+
+    // Opposite directions could turn either way, so swing around the vertical like an orbit,
+    // or any way at all when looking straight up or down
+    if angle > std::f32::consts::PI - 1e-3 {
+        let axis = (Vec3::Y - a * a.y)
+            .try_normalize()
+            .unwrap_or_else(|| a.any_orthonormal_vector());
+        return glam::Quat::from_axis_angle(axis, angle * t) * a;
+    }
+
+    (a * ((1.0 - t) * angle).sin() + b * (t * angle).sin()) / angle.sin()
+}
@@ -0,0 +1,423 @@
+use glam::{IVec3, Quat, Vec3};
+
+use crate::{
+    models::{material::Material, model::Model, voxel::Voxel},
+    utils::{consts::*, rng::Rng},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractureMode {
+    /// Splits around `cells` random seed voxels, each voxel going to its closest seed
+    Voronoi { cells: usize, seed: u64 },
+    /// Splits along the model's layers, which are its parts
+    Layers,
+}
+
+/// Connected group of voxels broken off a model, in the model's voxel coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    pub voxels: Vec<(IVec3, Material)>,
+}
+
+impl Fragment {
+    /// Center of mass, every voxel weighing the same
+    pub fn center(&self) -> Vec3 {
+        let sum = self
+            .voxels
+            .iter()
+            .fold(Vec3::ZERO, |acc, (p, _)| acc + p.as_vec3() + 0.5);
+        sum / self.voxels.len().max(1) as f32
+    }
+}
+
+/// Breaks a model into fragments that are each connected through voxel faces
+pub fn fracture(model: &Model, mode: FractureMode) -> Vec<Fragment> {
+    let voxels = model.filled_voxels();
+    let pos = |p: (usize, usize, usize)| IVec3::new(p.0 as i32, p.1 as i32, p.2 as i32);
+
+    let regions = match mode {
+        FractureMode::Voronoi { cells, seed } => {
+            let mut rng = Rng::new(seed);
+            let mut seeds = voxels.iter().map(|(p, _)| pos(*p)).collect::<Vec<_>>();
+            rng.shuffle(&mut seeds);
+            seeds.truncate(cells.max(1));
+
+            voxels
+                .iter()
+                .map(|(p, _)| {
+                    let p = pos(*p);
+                    (0..seeds.len())
+                        .min_by_key(|i| (seeds[*i] - p).length_squared())
+                        .unwrap_or(0)
+                })
+                .collect::<Vec<_>>()
+        }
+        FractureMode::Layers => voxels.iter().map(|((_, y, _), _)| *y).collect(),
+    };
+
+    // Flood fill each region into its connected pieces
+    let lookup = voxels
+        .iter()
+        .enumerate()
+        .map(|(i, (p, _))| (pos(*p), i))
+        .collect::<std::collections::HashMap<_, _>>();
+    let mut visited = vec![false; voxels.len()];
+    let mut out = vec![];
+
+    for start in 0..voxels.len() {
+        if visited[start] {
+            continue;
+        }
+
+        visited[start] = true;
+        let mut stack = vec![start];
+        let mut fragment = Fragment { voxels: vec![] };
+        while let Some(i) = stack.pop() {
+            let p = pos(voxels[i].0);
+            fragment.voxels.push((p, voxels[i].1.material));
+
+            for offset in [
+                IVec3::X,
+                IVec3::NEG_X,
+                IVec3::Y,
+                IVec3::NEG_Y,
+                IVec3::Z,
+                IVec3::NEG_Z,
+            ] {
+                if let Some(&n) = lookup.get(&(p + offset)) {
+                    if !visited[n] && regions[n] == regions[i] {
+                        visited[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        out.push(fragment);
+    }
+
+    out
+}
+
+/// Fragment moving as a single rigid body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    /// Voxel centers relative to the center of mass
+    voxels: Vec<(Vec3, Material)>,
+    inertia: f32,
+}
+
+impl Body {
+    pub fn new(fragment: &Fragment) -> Self {
+        let center = fragment.center();
+        let voxels = fragment
+            .voxels
+            .iter()
+            .map(|(p, m)| (p.as_vec3() + 0.5 - center, *m))
+            .collect::<Vec<_>>();
+
+        // Treats every voxel as a unit cube, averaged over the axes
+        let inertia = voxels
+            .iter()
+            .map(|(r, _)| r.length_squared() * 2.0 / 3.0 + 1.0 / 6.0)
+            .sum::<f32>()
+            .max(1.0 / 6.0);
+
+        Self {
+            position: center,
+            rotation: Quat::IDENTITY,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            voxels,
+            inertia,
+        }
+    }
+
+    pub fn mass(&self) -> f32 {
+        self.voxels.len() as f32
+    }
+
+    /// World space centers of every voxel
+    pub fn voxel_positions(&self) -> impl Iterator<Item = (Vec3, Material)> + '_ {
+        self.voxels
+            .iter()
+            .map(|(r, m)| (self.position + self.rotation * *r, *m))
+    }
+
+    /// Pushes the body at a point, in world space
+    pub fn apply_impulse(&mut self, impulse: Vec3, point: Vec3) {
+        self.velocity += impulse / self.mass().max(1.0);
+        self.angular_velocity += (point - self.position).cross(impulse) / self.inertia;
+    }
+
+    /// Farthest any voxel's surface reaches from the center of mass
+    fn radius(&self) -> f32 {
+        self.voxels
+            .iter()
+            .map(|(r, _)| r.length())
+            .fold(0.0, f32::max)
+            + 0.5
+    }
+
+    /// How hard a push at `r` from the center of mass along `n` is to make
+    fn resistance(&self, r: Vec3, n: Vec3) -> f32 {
+        1.0 / self.mass().max(1.0) + r.cross(n).length_squared() / self.inertia
+    }
+
+    /// Lifts the body out of the ground, returning the deepest point that was under it
+    fn push_out_of_ground(&mut self) -> Option<Vec3> {
+        // Deepest voxel below the ground, treating voxels as spheres for contact
+        let contact = self
+            .voxel_positions()
+            .map(|(p, _)| p)
+            .min_by(|a, b| a.y.total_cmp(&b.y))
+            .filter(|p| p.y < 0.5)?;
+
+        self.position.y += 0.5 - contact.y;
+        Some(contact)
+    }
+}
+
+/// Where two bodies' voxels overlap, each voxel taken as a cube lined up with the world axes.
+/// Voxels that don't overlap that way never end up in the same cell when voxelized.
+struct Contact {
+    /// From the second body towards the first
+    normal: Vec3,
+    depth: f32,
+    point: Vec3,
+}
+
+impl Contact {
+    /// Averages every overlapping pair of voxels, so flat faces resting on each other push
+    /// through their middle rather than tipping over at a corner
+    fn between(a: &Body, b: &Body) -> Option<Self> {
+        if a.position.distance(b.position) >= a.radius() + b.radius() {
+            return None;
+        }
+
+        let (mut normal, mut point, mut depth, mut count) = (Vec3::ZERO, Vec3::ZERO, 0.0, 0);
+        for (pa, _) in a.voxel_positions() {
+            for (pb, _) in b.voxel_positions() {
+                let offset = pa - pb;
+                let overlap = 1.0 - offset.abs();
+                if overlap.min_element() <= 0.0 {
+                    continue;
+                }
+
+                // Out along whichever axis is the shortest way apart
+                let axis = match overlap.min_element() {
+                    m if m == overlap.y => Vec3::Y,
+                    m if m == overlap.x => Vec3::X,
+                    _ => Vec3::Z,
+                };
+                let sign = match offset.dot(axis) {
+                    d if d.abs() > 1e-6 => d.signum(),
+                    _ => 1.0,
+                };
+                normal += axis * sign;
+                point += (pa + pb) / 2.0;
+                depth = f32::max(depth, overlap.min_element());
+                count += 1;
+            }
+        }
+
+        let normal = normal.try_normalize()?;
+        Some(Self {
+            normal,
+            depth,
+            point: point / count as f32,
+        })
+    }
+}
+
+/// Fragments falling under gravity onto a ground plane at `y = 0`, the bottom of the model, and
+/// landing on each other
+#[derive(Debug, Clone, PartialEq)]
+pub struct RigidWorld {
+    pub bodies: Vec<Body>,
+    pub gravity: Vec3,
+    /// Fraction of speed kept when bouncing off the ground
+    pub restitution: f32,
+    /// Fraction of sliding and spinning speed lost on every step touching the ground
+    pub friction: f32,
+    label: &'static str,
+    size: (usize, usize, usize),
+}
+
+impl RigidWorld {
+    pub fn new(model: &Model, fragments: &[Fragment]) -> Self {
+        Self {
+            bodies: fragments.iter().map(Body::new).collect(),
+            gravity: Vec3::new(0.0, PHYSICS_GRAVITY, 0.0),
+            restitution: PHYSICS_RESTITUTION,
+            friction: PHYSICS_FRICTION,
+            label: model.label,
+            size: model.dimensions(),
+        }
+    }
+
+    /// Blasts every body away from `origin`, falling off with distance, with a random spin
+    pub fn explode(&mut self, origin: Vec3, strength: f32, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for body in &mut self.bodies {
+            let offset = body.position - origin;
+            let direction = match offset.length_squared() > 1e-6 {
+                true => offset.normalize(),
+                false => Vec3::Y,
+            };
+            let falloff = 1.0 / (1.0 + offset.length_squared() * 0.1);
+
+            let spin = Vec3::new(
+                rng.range_f32(-1.0, 1.0),
+                rng.range_f32(-1.0, 1.0),
+                rng.range_f32(-1.0, 1.0),
+            );
+            body.velocity += direction * strength * falloff;
+            body.angular_velocity += spin * strength * falloff * 0.25;
+        }
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        for body in &mut self.bodies {
+            body.velocity += self.gravity * dt;
+            body.position += body.velocity * dt;
+            body.rotation =
+                (Quat::from_scaled_axis(body.angular_velocity * dt) * body.rotation).normalize();
+        }
+
+        // Contacts are solved a few times over so stacks settle. Bodies on the ground don't give
+        // way to the ones landing on them, which would push them into it.
+        let mut touching = vec![false; self.bodies.len()];
+        let mut grounded = vec![None; self.bodies.len()];
+        for _ in 0..PHYSICS_CONTACT_ITERATIONS {
+            for (body, grounded) in self.bodies.iter_mut().zip(&mut grounded) {
+                if let Some(p) = body.push_out_of_ground() {
+                    *grounded = Some(p);
+                }
+            }
+            for i in 0..self.bodies.len() {
+                for j in (i + 1)..self.bodies.len() {
+                    let pinned = (grounded[i].is_some(), grounded[j].is_some());
+                    if self.collide(i, j, pinned) {
+                        touching[i] = true;
+                        touching[j] = true;
+                    }
+                }
+            }
+        }
+
+        for ((body, touching), grounded) in self.bodies.iter_mut().zip(touching).zip(grounded) {
+            let contact = match grounded {
+                Some(p) => Vec3::new(p.x, 0.0, p.z),
+                None if touching => {
+                    Self::settle(body, self.friction);
+                    continue;
+                }
+                None => continue,
+            };
+
+            // Bounce off the ground, the impulse at the contact point tipping the body over
+            let r = contact - body.position;
+            let contact_velocity = body.velocity + body.angular_velocity.cross(r);
+            if contact_velocity.y < 0.0 {
+                let j =
+                    -(1.0 + self.restitution) * contact_velocity.y / body.resistance(r, Vec3::Y);
+                body.apply_impulse(Vec3::Y * j, contact);
+            }
+
+            Self::settle(body, self.friction);
+        }
+    }
+
+    /// Slows a body resting on something down, and stops it instead of jittering there forever
+    fn settle(body: &mut Body, friction: f32) {
+        body.velocity.x *= 1.0 - friction;
+        body.velocity.z *= 1.0 - friction;
+        body.angular_velocity *= 1.0 - friction;
+
+        if body.velocity.length_squared() < PHYSICS_SLEEP_SPEED.powi(2)
+            && body.angular_velocity.length_squared() < PHYSICS_SLEEP_SPEED.powi(2)
+        {
+            body.velocity = Vec3::ZERO;
+            body.angular_velocity = Vec3::ZERO;
+        }
+    }
+
+    /// Pushes two bodies out of each other, the lighter one further and a pinned one not at all,
+    /// and bounces them apart if they're moving together. Returns whether they touched.
+    fn collide(&mut self, i: usize, j: usize, pinned: (bool, bool)) -> bool {
+        let (head, tail) = self.bodies.split_at_mut(j);
+        let (a, b) = (&mut head[i], &mut tail[0]);
+        let Some(contact) = Contact::between(a, b) else {
+            return false;
+        };
+
+        // Two pinned bodies side by side on the ground still push each other apart
+        let n = contact.normal;
+        let both = pinned.0 && pinned.1;
+        let weight = |body: &Body, pinned: bool| match pinned && !both {
+            true => 0.0,
+            false => 1.0 / body.mass().max(1.0),
+        };
+        let (wa, wb) = (weight(a, pinned.0), weight(b, pinned.1));
+        a.position += n * contact.depth * wa / (wa + wb);
+        b.position -= n * contact.depth * wb / (wa + wb);
+
+        let (ra, rb) = (contact.point - a.position, contact.point - b.position);
+        let relative = (a.velocity + a.angular_velocity.cross(ra))
+            - (b.velocity + b.angular_velocity.cross(rb));
+        let approach = relative.dot(n);
+        if approach < 0.0 {
+            let j =
+                -(1.0 + self.restitution) * approach / (a.resistance(ra, n) + b.resistance(rb, n));
+            a.apply_impulse(n * j, contact.point);
+            b.apply_impulse(-n * j, contact.point);
+        }
+
+        true
+    }
+
+    /// Voxelizes every body into a model the size of the original with `padding` voxels of room
+    /// on each side and above, for fragments that fly out of it
+    pub fn to_model(&self, padding: usize) -> Model {
+        let (w, h, d) = self.size;
+        let mut out = Model::empty(self.label, w + padding * 2, h + padding, d + padding * 2);
+        let offset = Vec3::new(padding as f32, 0.0, padding as f32);
+
+        for body in &self.bodies {
+            for (p, material) in body.voxel_positions() {
+                let p = (p + offset).floor();
+                if p.cmpge(Vec3::ZERO).all() {
+                    out.set(
+                        p.x as usize,
+                        p.y as usize,
+                        p.z as usize,
+                        Voxel::new(true, material),
+                    );
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Bakes `frames` frames starting with the current state, at the given frame rate
+    pub fn bake(&mut self, frames: usize, fps: f32, padding: usize) -> Vec<Model> {
+        let dt = 1.0 / (fps.max(1.0) * PHYSICS_SUBSTEPS as f32);
+        let mut out = vec![];
+        for i in 0..frames {
+            if i > 0 {
+                for _ in 0..PHYSICS_SUBSTEPS {
+                    self.step(dt);
+                }
+            }
+            out.push(self.to_model(padding));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Hold each key until the next one starts
+    #[default]
+    Step,
+    Linear,
+    EaseInOut,
+}
+
+impl Interpolation {
+    /// Maps progress between two keys to how far to blend from the first to the second
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => t,
+            Interpolation::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::ops::Range;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::Animation;
+
+/// Named point on the timeline that engines can fire an event at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Marker {
+    pub name: String,
+    /// Tick the marker sits on
+    pub frame: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+}
+
+impl Marker {
+    pub fn new<S>(name: S, frame: u32, payload: Option<String>) -> Self
+    where
+        S: ToString,
+    {
+        Self {
+            name: name.to_string(),
+            frame,
+            payload,
+        }
+    }
+}
+
+impl Animation {
+    /// Adds a marker, keeping markers sorted by frame
+    pub fn add_marker(&mut self, marker: Marker) {
+        let index = self.markers.partition_point(|m| m.frame <= marker.frame);
+        self.markers.insert(index, marker);
+    }
+
+    /// Removes every marker on a frame, returning how many were removed
+    pub fn remove_markers_at(&mut self, frame: u32) -> usize {
+        let len = self.markers.len();
+        self.markers.retain(|m| m.frame != frame);
+        len - self.markers.len()
+    }
+
+    /// Markers passed over while playing through `ticks`
+    pub fn markers_in(&self, ticks: Range<u32>) -> impl Iterator<Item = &Marker> {
+        self.markers
+            .iter()
+            .filter(move |m| ticks.contains(&m.frame))
+    }
+}
+
+/// Markers as a list of events with their time in seconds, as engines expect
+pub fn events(animation: &Animation) -> serde_json::Value {
+    let events = animation
+        .markers
+        .iter()
+        .map(|m| {
+            json!({
+                "name": m.name,
+                "frame": m.frame,
+                "time": match animation.fps > 0.0 {
+                    true => m.frame as f32 / animation.fps,
+                    false => 0.0,
+                },
+                "payload": m.payload,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::Value::Array(events)
+}
+
+/// Sidecar written next to exported sequences, videos and animated images, since those formats
+/// have nowhere to keep events. Sprite sheet atlases carry the same events themselves. Nothing
+/// exports glTF yet, so there are no glTF extras to put markers in.
+pub fn sidecar(animation: &Animation) -> serde_json::Value {
+    json!({
+        "animation": animation.label,
+        "fps": animation.fps,
+        "frames": animation.duration(),
+        "markers": events(animation),
+    })
+}
+
+pub fn write_sidecar<P>(animation: &Animation, path: P) -> anyhow::Result<()>
+where
+    P: AsRef<std::path::Path>,
+{
+    let text = serde_json::to_string_pretty(&sidecar(animation))?;
+    std::fs::write(&path, text)
+        .with_context(|| format!("Couldn't write {}", path.as_ref().display()))
+}
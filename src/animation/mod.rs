@@ -0,0 +1,85 @@
+pub mod camera;
+pub mod destruction;
+pub mod interpolation;
+pub mod marker;
+pub mod playback;
+pub mod project;
+pub mod retime;
+pub mod sim;
+pub mod storage;
+pub mod track;
+pub mod tween;
+
+use crate::{models::model::Model, utils::consts::*};
+
+use camera::CameraTrack;
+use marker::Marker;
+use track::{BlendMode, Track, TrackTarget};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub label: &'static str,
+    pub fps: f32,
+    /// Evaluated in order, each track blending over the result of the ones before it
+    pub tracks: Vec<Track>,
+    /// Sorted by frame
+    pub markers: Vec<Marker>,
+    /// Camera for renders and previews, unused while it has no keys
+    pub camera: CameraTrack,
+}
+
+impl Animation {
+    pub fn new(label: &'static str, frames: Vec<Model>) -> Self {
+        Self {
+            label,
+            fps: DEFAULT_FPS,
+            tracks: vec![Track::new(label, frames)],
+            markers: vec![],
+            camera: CameraTrack::default(),
+        }
+    }
+
+    /// Length of the longest track in ticks, shorter tracks hold their last frame
+    pub fn duration(&self) -> u32 {
+        self.tracks.iter().map(|t| t.duration()).max().unwrap_or(0)
+    }
+
+    /// Combines every track at `tick` into a single model
+    pub fn sample(&self, tick: f32) -> Option<Model> {
+        let frames = self
+            .tracks
+            .iter()
+            .filter_map(|t| t.sample(tick).map(|f| (t, f)))
+            .collect::<Vec<_>>();
+
+        // A lone track covering everything needs no blending
+        if let [(track, frame)] = frames.as_slice() {
+            if track.blend == BlendMode::Override
+                && track.target == TrackTarget::All
+                && track.weight >= 1.0
+            {
+                return Some(frame.clone());
+            }
+        }
+
+        let (_, first) = frames.first()?;
+        let mut dims = (0, 0, 0);
+        for (_, f) in &frames {
+            let (w, h, d) = f.dimensions();
+            dims = (dims.0.max(w), dims.1.max(h), dims.2.max(d));
+        }
+
+        let mut out = Model::empty(first.label, dims.0, dims.1, dims.2);
+        for (y, layer) in out.value.iter_mut().enumerate() {
+            if let Some(l) = frames.iter().find_map(|(_, f)| f.value.get(y)) {
+                layer.label = l.label;
+            }
+        }
+
+        for (track, frame) in &frames {
+            track::blend(&mut out, frame, track);
+        }
+
+        Some(out)
+    }
+}
@@ -0,0 +1,179 @@
+use crate::utils::consts::*;
+
+use super::Animation;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    pub playing: bool,
+    pub frame: usize,
+    pub speed: f32,
+    /// Inclusive range of frames to loop over instead of the whole animation
+    pub loop_range: Option<(usize, usize)>,
+    /// Plays back and forth across the loop instead of jumping back to its start
+    pub ping_pong: bool,
+    // Whether ping-pong playback is on its way back
+    reversing: bool,
+    // Time spent on the current frame, in seconds of animation time
+    elapsed: f32,
+    // Frame and direction the last update started from, and how many frames it stepped
+    stepped: (usize, bool, usize),
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            frame: 0,
+            speed: 1.0,
+            loop_range: None,
+            ping_pong: false,
+            reversing: false,
+            elapsed: 0.0,
+            stepped: (0, false, 0),
+        }
+    }
+}
+
+impl Playback {
+    /// Advances playback by `delta_time` seconds, returning whether the frame changed
+    pub fn update(&mut self, delta_time: f32, animation: &Animation) -> bool {
+        let previous = self.frame;
+        self.frame = self
+            .frame
+            .min((animation.duration() as usize).saturating_sub(1));
+        self.stepped = (self.frame, self.reversing, 0);
+
+        let (start, end) = self.loop_bounds(animation);
+        if self.playing && end > start && animation.fps > 0.0 {
+            let frame_time = 1.0 / animation.fps;
+            self.elapsed += delta_time * self.speed;
+
+            // Skip whole loops at once after long stalls, there and back again for ping-pong
+            let cycle = match self.ping_pong {
+                true => (end - start) * 2,
+                false => end - start + 1,
+            };
+            self.elapsed %= frame_time * cycle as f32;
+
+            while self.elapsed >= frame_time {
+                self.elapsed -= frame_time;
+                self.frame = self.next_frame(start, end);
+                self.stepped.2 += 1;
+            }
+        }
+
+        self.frame != previous
+    }
+
+    /// Every frame the last `update` played, in order, which can be several at high speeds or
+    /// across the end of the loop
+    pub fn played(&self, animation: &Animation) -> Vec<usize> {
+        let (start, end) = self.loop_bounds(animation);
+        let (frame, reversing, steps) = self.stepped;
+        let mut replay = Self {
+            frame,
+            reversing,
+            ..*self
+        };
+
+        (0..steps)
+            .map(|_| {
+                replay.frame = replay.next_frame(start, end);
+                replay.frame
+            })
+            .collect()
+    }
+
+    /// Frame after the current one within the loop
+    fn next_frame(&mut self, start: usize, end: usize) -> usize {
+        if self.frame < start || self.frame > end {
+            self.reversing = false;
+            return start;
+        }
+        if !self.ping_pong {
+            return match self.frame >= end {
+                true => start,
+                false => self.frame + 1,
+            };
+        }
+
+        // Turns around on reaching either end
+        if self.frame >= end {
+            self.reversing = true;
+        } else if self.frame <= start {
+            self.reversing = false;
+        }
+        match self.reversing {
+            true => self.frame - 1,
+            false => self.frame + 1,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+        self.elapsed = 0.0;
+    }
+
+    /// Moves by `offset` frames, wrapping around either end
+    pub fn step(&mut self, offset: i64, animation: &Animation) {
+        if animation.duration() == 0 {
+            return;
+        }
+
+        let len = animation.duration() as i64;
+        self.seek(
+            (self.frame as i64 + offset).rem_euclid(len) as usize,
+            animation,
+        );
+    }
+
+    pub fn seek(&mut self, frame: usize, animation: &Animation) {
+        self.frame = frame.min((animation.duration() as usize).saturating_sub(1));
+        self.elapsed = 0.0;
+        self.stepped.2 = 0;
+    }
+
+    pub fn jump_to_start(&mut self, animation: &Animation) {
+        self.seek(0, animation);
+    }
+
+    pub fn jump_to_end(&mut self, animation: &Animation) {
+        self.seek((animation.duration() as usize).saturating_sub(1), animation);
+    }
+
+    /// First and last frame played, clamped to the animation
+    pub fn loop_bounds(&self, animation: &Animation) -> (usize, usize) {
+        let last = (animation.duration() as usize).saturating_sub(1);
+        match self.loop_range {
+            Some((start, end)) => (start.min(last), end.min(last)),
+            None => (0, last),
+        }
+    }
+
+    pub fn set_loop_start(&mut self, frame: usize, animation: &Animation) {
+        let (_, end) = self.loop_bounds(animation);
+        self.loop_range = Some((frame.min(end), frame.max(end)));
+    }
+
+    pub fn set_loop_end(&mut self, frame: usize, animation: &Animation) {
+        let (start, _) = self.loop_bounds(animation);
+        self.loop_range = Some((frame.min(start), frame.max(start)));
+    }
+
+    pub fn clear_loop(&mut self) {
+        self.loop_range = None;
+    }
+
+    pub fn toggle_ping_pong(&mut self) {
+        self.ping_pong = !self.ping_pong;
+        self.reversing = false;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(PLAYBACK_SPEED_MAX);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(PLAYBACK_SPEED_MIN);
+    }
+}
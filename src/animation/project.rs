@@ -0,0 +1,256 @@
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{layer::Layer, material::Material, model::Model, voxel::Voxel},
+    utils::{self, consts::*},
+};
+
+use super::{
+    camera::CameraTrack,
+    interpolation::Interpolation,
+    marker::Marker,
+    storage::FrameStore,
+    track::{BlendMode, Track, TrackTarget},
+    tween::TweenStrategy,
+    Animation,
+};
+
+// Voxels are written as `material index * 2 + filled` into a palette shared by the whole track,
+// and frames keep the keyframe and diff layout they have in memory
+
+#[derive(Serialize, Deserialize)]
+struct ProjectFile {
+    version: u32,
+    label: String,
+    fps: f32,
+    tracks: Vec<TrackFile>,
+    #[serde(default)]
+    markers: Vec<Marker>,
+    #[serde(default)]
+    camera: CameraTrack,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrackFile {
+    label: String,
+    keyframe_interval: usize,
+    durations: Vec<u32>,
+    interpolation: Interpolation,
+    tween: TweenStrategy,
+    target: TrackTarget,
+    blend: BlendMode,
+    weight: f32,
+    palette: Vec<[f32; 4]>,
+    /// Emission, roughness and metalness of each palette entry, missing from older files
+    #[serde(default)]
+    surfaces: Vec<[f32; 3]>,
+    frames: Vec<FrameFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum FrameFile {
+    Key(ModelFile),
+    /// (x, y, z, voxel)
+    Delta(Vec<(usize, usize, usize, u32)>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ModelFile {
+    label: String,
+    layers: Vec<LayerFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LayerFile {
+    label: String,
+    rows: Vec<Vec<u32>>,
+}
+
+pub fn save<P>(animation: &Animation, path: P) -> anyhow::Result<()>
+where
+    P: AsRef<std::path::Path>,
+{
+    std::fs::write(&path, to_string(animation)?)
+        .with_context(|| format!("Couldn't write {}", path.as_ref().display()))
+}
+
+pub fn load<P>(path: P) -> anyhow::Result<Animation>
+where
+    P: AsRef<std::path::Path>,
+{
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Couldn't read {}", path.as_ref().display()))?;
+    from_str(&text)
+}
+
+pub fn to_string(animation: &Animation) -> anyhow::Result<String> {
+    let file = ProjectFile {
+        version: PROJECT_VERSION,
+        label: animation.label.to_string(),
+        fps: animation.fps,
+        tracks: animation.tracks.iter().map(track_to_file).collect(),
+        markers: animation.markers.clone(),
+        camera: animation.camera.clone(),
+    };
+
+    Ok(serde_json::to_string(&file)?)
+}
+
+pub fn from_str(text: &str) -> anyhow::Result<Animation> {
+    let file: ProjectFile = serde_json::from_str(text)?;
+    if file.version > PROJECT_VERSION {
+        bail!(
+            "Project version {} is newer than the supported version {}",
+            file.version,
+            PROJECT_VERSION
+        );
+    }
+
+    let mut markers = file.markers;
+    markers.sort_by_key(|m| m.frame);
+
+    let animation = Animation {
+        label: utils::leak_str(file.label),
+        fps: file.fps,
+        tracks: file
+            .tracks
+            .into_iter()
+            .map(track_from_file)
+            .collect::<anyhow::Result<_>>()?,
+        markers,
+        camera: file.camera,
+    };
+
+    // Everything showing an animation needs a frame to show
+    if animation.duration() == 0 {
+        bail!("Project {} has no frames to play", animation.label);
+    }
+
+    Ok(animation)
+}
+
+fn track_to_file(track: &Track) -> TrackFile {
+    let mut palette = vec![];
+    let mut encode = |voxel: &Voxel| {
+        utils::push_if_absent(&mut palette, voxel.material);
+        let index = palette
+            .iter()
+            .position(|m| *m == voxel.material)
+            .unwrap_or(0);
+        index as u32 * 2 + voxel.filled as u32
+    };
+
+    let mut frames = vec![];
+    for (i, model) in track.frames.iter().enumerate() {
+        frames.push(match track.frames.changes(i) {
+            Some(changes) => FrameFile::Delta(
+                changes
+                    .iter()
+                    .map(|c| (c.x, c.y, c.z, encode(&c.voxel)))
+                    .collect(),
+            ),
+            None => FrameFile::Key(ModelFile {
+                label: model.label.to_string(),
+                layers: model
+                    .value
+                    .iter()
+                    .map(|l| LayerFile {
+                        label: l.label.to_string(),
+                        rows: l
+                            .value
+                            .iter()
+                            .map(|r| r.iter().map(&mut encode).collect())
+                            .collect(),
+                    })
+                    .collect(),
+            }),
+        });
+    }
+
+    TrackFile {
+        label: track.label.to_string(),
+        keyframe_interval: track.frames.keyframe_interval(),
+        durations: track.durations.clone(),
+        interpolation: track.interpolation,
+        tween: track.tween,
+        target: track.target.clone(),
+        blend: track.blend,
+        weight: track.weight,
+        palette: palette.iter().map(|m| m.color.to_array()).collect(),
+        surfaces: palette
+            .iter()
+            .map(|m| [m.emission, m.roughness, m.metalness])
+            .collect(),
+        frames,
+    }
+}
+
+fn track_from_file(file: TrackFile) -> anyhow::Result<Track> {
+    let label = utils::leak_str(file.label);
+    if file.durations.len() != file.frames.len() {
+        bail!(
+            "Track {} has a different number of frames and durations",
+            label
+        );
+    }
+
+    let decode = |v: u32| -> anyhow::Result<Voxel> {
+        let color = file
+            .palette
+            .get(v as usize / 2)
+            .with_context(|| format!("Track {} uses a material outside its palette", label))?;
+        let mut material = Material::new(glam::Vec4::from_array(*color));
+        if let Some([emission, roughness, metalness]) = file.surfaces.get(v as usize / 2) {
+            material.emission = *emission;
+            material.roughness = *roughness;
+            material.metalness = *metalness;
+        }
+        Ok(Voxel::new(v % 2 == 1, material))
+    };
+
+    let mut frames = FrameStore::new(file.keyframe_interval);
+    let mut previous: Option<Model> = None;
+    for frame in file.frames {
+        let model = match (frame, previous.take()) {
+            (FrameFile::Key(m), _) => Model {
+                label: utils::leak_str(m.label),
+                value: m
+                    .layers
+                    .into_iter()
+                    .map(|l| {
+                        Ok(Layer {
+                            label: utils::leak_str(l.label),
+                            value: l
+                                .rows
+                                .into_iter()
+                                .map(|r| r.into_iter().map(&decode).collect::<anyhow::Result<_>>())
+                                .collect::<anyhow::Result<_>>()?,
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            },
+            (FrameFile::Delta(changes), Some(mut m)) => {
+                for (x, y, z, v) in changes {
+                    m.set(x, y, z, decode(v)?);
+                }
+                m
+            }
+            (FrameFile::Delta(_), None) => bail!("Track {} starts with a diff", label),
+        };
+
+        frames.push(model.clone());
+        previous = Some(model);
+    }
+
+    Ok(Track {
+        label,
+        frames,
+        durations: file.durations,
+        interpolation: file.interpolation,
+        tween: file.tween,
+        target: file.target,
+        blend: file.blend,
+        weight: file.weight,
+    })
+}
@@ -0,0 +1,110 @@
+use std::ops::Range;
+
+use crate::models::model::Model;
+
+use super::{storage::FrameStore, track::Track, Animation};
+
+/// Changes the frame rate while keeping every frame of every track, every marker and every camera
+/// key at the same wall-clock time
+pub fn set_fps(animation: &mut Animation, fps: f32) {
+    if fps <= 0.0 || animation.fps <= 0.0 {
+        return;
+    }
+
+    let factor = fps / animation.fps;
+    for track in animation.tracks.iter_mut() {
+        let len = track.len();
+        scale_range(track, 0..len, factor);
+    }
+    for marker in animation.markers.iter_mut() {
+        marker.frame = (marker.frame as f32 * factor).round() as u32;
+    }
+    for key in animation.camera.keys.iter_mut() {
+        key.frame = (key.frame as f32 * factor).round() as u32;
+    }
+    animation.camera.keys.dedup_by_key(|k| k.frame);
+    animation.fps = fps;
+}
+
+/// Stretches a range of frames in time, so a `factor` of 2 plays them at half speed
+pub fn scale_range(track: &mut Track, range: Range<usize>, factor: f32) {
+    let range = clamp_range(track, range);
+    let factor = factor.max(0.0);
+
+    // Round frame boundaries rather than lengths so rounding errors don't add up over the range
+    let mut elapsed = 0;
+    let mut rounded = 0;
+    for d in &mut track.durations[range.clone()] {
+        elapsed += *d;
+        let end = (elapsed as f32 * factor).round() as u32;
+        *d = end - rounded;
+        rounded = end;
+    }
+
+    // Frames without any ticks are never shown, and interpolating towards one would tween to it
+    // and then jump straight past it, so they're dropped whatever the interpolation
+    if track.duration() == 0 {
+        if let Some(d) = track.durations.get_mut(range.start) {
+            *d = 1;
+        }
+    }
+
+    let dropped = range
+        .filter(|i| track.durations[*i] == 0)
+        .collect::<Vec<_>>();
+    if !dropped.is_empty() {
+        edit_frames(track, |frames, durations| {
+            for i in dropped.iter().rev() {
+                frames.remove(*i);
+                durations.remove(*i);
+            }
+        });
+    }
+}
+
+/// Plays a range of frames backwards, keeping each frame's duration with it
+pub fn reverse_range(track: &mut Track, range: Range<usize>) {
+    let range = clamp_range(track, range);
+    edit_frames(track, |frames, durations| {
+        frames[range.clone()].reverse();
+        durations[range].reverse();
+    });
+}
+
+/// Holds a frame on screen for extra ticks
+pub fn hold_frame(track: &mut Track, index: usize, ticks: u32) {
+    if let Some(d) = track.durations.get_mut(index) {
+        *d += ticks;
+    }
+}
+
+/// Inserts copies of a frame after it, each as long as the original
+pub fn duplicate_frame(track: &mut Track, index: usize, copies: usize) {
+    if index >= track.len() {
+        return;
+    }
+
+    edit_frames(track, |frames, durations| {
+        for _ in 0..copies {
+            frames.insert(index + 1, frames[index].clone());
+            durations.insert(index + 1, durations[index]);
+        }
+    });
+}
+
+/// Inside the track, and the right way around for ranges selected backwards
+fn clamp_range(track: &Track, range: Range<usize>) -> Range<usize> {
+    let len = track.len();
+    let (start, end) = (range.start.min(range.end), range.start.max(range.end));
+    start.min(len)..end.min(len)
+}
+
+/// Materializes every frame for edits that move many frames at once, then stores them again
+fn edit_frames<F>(track: &mut Track, edit: F)
+where
+    F: FnOnce(&mut Vec<Model>, &mut Vec<u32>),
+{
+    let mut frames = track.frames.iter().collect::<Vec<_>>();
+    edit(&mut frames, &mut track.durations);
+    track.frames = FrameStore::from_frames(frames, track.frames.keyframe_interval());
+}
@@ -0,0 +1,356 @@
+use crate::{
+    models::{material::Material, model::Model, voxel::Voxel},
+    utils::{consts::*, rng::Rng},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element {
+    /// Never moves, everything else piles up on or flows around it
+    Solid,
+    /// Falls, sliding off slopes into piles
+    Sand,
+    /// Falls and spreads out sideways until it is level
+    Water,
+    /// Rises and drifts, fading away after a while
+    Smoke,
+    /// Flickers and flares upwards, burning out into smoke
+    Fire,
+}
+
+impl Element {
+    /// Heavier elements sink through lighter ones they aren't fixed to
+    fn density(self) -> u8 {
+        match self {
+            Element::Smoke | Element::Fire => 0,
+            Element::Water => 1,
+            Element::Sand => 2,
+            Element::Solid => 3,
+        }
+    }
+}
+
+/// Materials picked from at random for cells of each element
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimPalette {
+    pub sand: Vec<Material>,
+    pub water: Vec<Material>,
+    pub smoke: Vec<Material>,
+    pub fire: Vec<Material>,
+}
+
+impl Default for SimPalette {
+    fn default() -> Self {
+        let mat = |r, g, b, a| Material::new(glam::vec4(r, g, b, a));
+
+        Self {
+            sand: vec![mat(0.86, 0.74, 0.48, 1.0), mat(0.8, 0.67, 0.42, 1.0)],
+            water: vec![mat(0.2, 0.42, 0.85, 1.0)],
+            smoke: vec![mat(0.35, 0.35, 0.38, 1.0), mat(0.45, 0.45, 0.47, 1.0)],
+            fire: vec![
+                mat(1.0, 0.85, 0.3, 1.0),
+                mat(1.0, 0.55, 0.1, 1.0),
+                mat(0.9, 0.25, 0.05, 1.0),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    element: Element,
+    material: Material,
+    /// Steps left before smoke fades or fire burns out
+    life: u32,
+}
+
+/// Cellular automaton stepped over a dense voxel grid, with `y` pointing up
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub palette: SimPalette,
+    pub smoke_life: u32,
+    pub fire_life: u32,
+    label: &'static str,
+    layer_labels: Vec<&'static str>,
+    size: (usize, usize, usize),
+    cells: Vec<Option<Cell>>,
+    rng: Rng,
+}
+
+impl Simulation {
+    /// Starts from a model whose filled voxels are all solid
+    pub fn new(model: &Model, seed: u64) -> Self {
+        let size = model.dimensions();
+        let mut out = Self {
+            palette: SimPalette::default(),
+            smoke_life: SIM_SMOKE_LIFE,
+            fire_life: SIM_FIRE_LIFE,
+            label: model.label,
+            layer_labels: model.value.iter().map(|l| l.label).collect(),
+            size,
+            cells: vec![None; size.0 * size.1 * size.2],
+            rng: Rng::new(seed),
+        };
+
+        for ((x, y, z), voxel) in model.filled_voxels() {
+            let i = out.index(x, y, z);
+            out.cells[i] = Some(Cell {
+                element: Element::Solid,
+                material: voxel.material,
+                life: 0,
+            });
+        }
+
+        out
+    }
+
+    pub fn size(&self) -> (usize, usize, usize) {
+        self.size
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<Element> {
+        self.in_bounds(x, y, z)
+            .then(|| self.cells[self.index(x, y, z)])
+            .flatten()
+            .map(|c| c.element)
+    }
+
+    /// Turns solid voxels of a material into another element, keeping their material
+    pub fn convert(&mut self, material: Material, element: Element) {
+        let life = self.life(element);
+        for cell in self.cells.iter_mut().flatten() {
+            if cell.element == Element::Solid && cell.material == material {
+                cell.element = element;
+                cell.life = life;
+            }
+        }
+    }
+
+    /// Places a cell of an element, replacing whatever was there. Returns false if the position is
+    /// outside of the grid.
+    pub fn spawn(&mut self, x: usize, y: usize, z: usize, element: Element) -> bool {
+        if !self.in_bounds(x, y, z) {
+            return false;
+        }
+
+        let i = self.index(x, y, z);
+        self.cells[i] = Some(self.new_cell(element));
+        true
+    }
+
+    pub fn remove(&mut self, x: usize, y: usize, z: usize) {
+        if self.in_bounds(x, y, z) {
+            let i = self.index(x, y, z);
+            self.cells[i] = None;
+        }
+    }
+
+    /// Advances every cell by one step
+    pub fn step(&mut self) {
+        let (w, h, d) = self.size;
+        let mut moved = vec![false; self.cells.len()];
+        let mut columns = (0..w)
+            .flat_map(|x| (0..d).map(move |z| (x, z)))
+            .collect::<Vec<_>>();
+
+        // Bottom up, so falling cells land in layers that are already done, while rising cells
+        // are marked as moved so they aren't stepped again in the layer above
+        for y in 0..h {
+            // A fresh order every layer keeps flows from drifting towards one side
+            self.rng.shuffle(&mut columns);
+
+            for &(x, z) in &columns {
+                let i = self.index(x, y, z);
+                if moved[i] {
+                    continue;
+                }
+
+                let cell = match self.cells[i] {
+                    Some(c) => c,
+                    None => continue,
+                };
+
+                match cell.element {
+                    Element::Solid => {}
+                    Element::Sand => {
+                        self.try_moves(&mut moved, (x, y, z), &[&DOWN, &DIAGONALS_DOWN]);
+                    }
+                    Element::Water => {
+                        self.try_moves(&mut moved, (x, y, z), &[&DOWN, &DIAGONALS_DOWN, &SIDEWAYS]);
+                    }
+                    Element::Smoke => {
+                        if cell.life <= 1 {
+                            self.cells[i] = None;
+                            continue;
+                        }
+                        self.cells[i] = Some(Cell {
+                            life: cell.life - 1,
+                            ..cell
+                        });
+
+                        // Drifts sideways half of the time it can't rise
+                        let drift: &[(i64, i64, i64)] = match self.rng.chance(0.5) {
+                            true => &SIDEWAYS,
+                            false => &[],
+                        };
+                        self.try_moves(&mut moved, (x, y, z), &[&UP, &DIAGONALS_UP, drift]);
+                    }
+                    Element::Fire => {
+                        if cell.life <= 1 {
+                            self.cells[i] = Some(self.new_cell(Element::Smoke));
+                            moved[i] = true;
+                            continue;
+                        }
+                        self.cells[i] = Some(Cell {
+                            material: self.pick(Element::Fire),
+                            life: cell.life - 1,
+                            ..cell
+                        });
+
+                        if self.rng.chance(0.6) {
+                            self.try_moves(&mut moved, (x, y, z), &[&RISING]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn to_model(&self) -> Model {
+        let (w, h, d) = self.size;
+        let mut out = Model::empty(self.label, w, h, d);
+        for (layer, label) in out.value.iter_mut().zip(&self.layer_labels) {
+            layer.label = label;
+        }
+
+        for y in 0..h {
+            for x in 0..w {
+                for z in 0..d {
+                    if let Some(cell) = self.cells[self.index(x, y, z)] {
+                        out.set(x, y, z, Voxel::new(true, cell.material));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Bakes `frames` frames starting with the current state, stepping `steps_per_frame` times
+    /// between each
+    pub fn bake(&mut self, frames: usize, steps_per_frame: usize) -> Vec<Model> {
+        let mut out = vec![];
+        for i in 0..frames {
+            if i > 0 {
+                for _ in 0..steps_per_frame {
+                    self.step();
+                }
+            }
+            out.push(self.to_model());
+        }
+
+        out
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.size.0 + x) * self.size.2 + z
+    }
+
+    fn in_bounds(&self, x: usize, y: usize, z: usize) -> bool {
+        x < self.size.0 && y < self.size.1 && z < self.size.2
+    }
+
+    fn life(&self, element: Element) -> u32 {
+        match element {
+            Element::Smoke => self.smoke_life,
+            Element::Fire => self.fire_life,
+            _ => 0,
+        }
+    }
+
+    fn pick(&mut self, element: Element) -> Material {
+        let palette = match element {
+            Element::Solid => return Material::default(),
+            Element::Sand => &self.palette.sand,
+            Element::Water => &self.palette.water,
+            Element::Smoke => &self.palette.smoke,
+            Element::Fire => &self.palette.fire,
+        };
+
+        match palette.len() {
+            0 => Material::default(),
+            len => palette[self.rng.below(len)],
+        }
+    }
+
+    fn new_cell(&mut self, element: Element) -> Cell {
+        Cell {
+            element,
+            material: self.pick(element),
+            life: self.life(element),
+        }
+    }
+
+    /// Moves a cell to the first free neighbour, swapping with lighter cells in the way. Groups
+    /// of offsets are tried in order, in a random order within each group.
+    fn try_moves(
+        &mut self,
+        moved: &mut [bool],
+        pos: (usize, usize, usize),
+        groups: &[&[(i64, i64, i64)]],
+    ) -> bool {
+        for group in groups {
+            let mut offsets = group.to_vec();
+            self.rng.shuffle(&mut offsets);
+            if self.try_offsets(moved, pos, &offsets) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn try_offsets(
+        &mut self,
+        moved: &mut [bool],
+        (x, y, z): (usize, usize, usize),
+        offsets: &[(i64, i64, i64)],
+    ) -> bool {
+        let from = self.index(x, y, z);
+        let element = match self.cells[from] {
+            Some(c) => c.element,
+            None => return false,
+        };
+
+        for (dx, dy, dz) in offsets {
+            let (tx, ty, tz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+            if tx < 0 || ty < 0 || tz < 0 {
+                continue;
+            }
+            let (tx, ty, tz) = (tx as usize, ty as usize, tz as usize);
+            if !self.in_bounds(tx, ty, tz) {
+                continue;
+            }
+
+            let to = self.index(tx, ty, tz);
+            let free = match self.cells[to] {
+                None => true,
+                Some(c) => !moved[to] && c.element.density() < element.density(),
+            };
+            if free {
+                self.cells.swap(from, to);
+                moved[to] = true;
+                moved[from] = self.cells[from].is_some();
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+const DOWN: [(i64, i64, i64); 1] = [(0, -1, 0)];
+const UP: [(i64, i64, i64); 1] = [(0, 1, 0)];
+const DIAGONALS_DOWN: [(i64, i64, i64); 4] = [(1, -1, 0), (-1, -1, 0), (0, -1, 1), (0, -1, -1)];
+const DIAGONALS_UP: [(i64, i64, i64); 4] = [(1, 1, 0), (-1, 1, 0), (0, 1, 1), (0, 1, -1)];
+const SIDEWAYS: [(i64, i64, i64); 4] = [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)];
+const RISING: [(i64, i64, i64); 5] = [(0, 1, 0), (1, 1, 0), (-1, 1, 0), (0, 1, 1), (0, 1, -1)];
@@ -0,0 +1,237 @@
+use crate::models::{model::Model, voxel::Voxel};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelChange {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+    pub voxel: Voxel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StoredFrame {
+    Key(Model),
+    /// Changes from the previous frame, which always has the same layout
+    Delta(Vec<VoxelChange>),
+}
+
+/// Frames stored as full keyframes followed by per-frame diffs.
+/// A keyframe is forced at least every `keyframe_interval` frames, so materializing any frame
+/// applies at most `keyframe_interval - 1` diffs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameStore {
+    frames: Vec<StoredFrame>,
+    keyframe_interval: usize,
+}
+
+impl FrameStore {
+    pub fn new(keyframe_interval: usize) -> Self {
+        Self {
+            frames: vec![],
+            keyframe_interval: keyframe_interval.max(1),
+        }
+    }
+
+    pub fn from_frames<I>(frames: I, keyframe_interval: usize) -> Self
+    where
+        I: IntoIterator<Item = Model>,
+    {
+        let mut out = Self::new(keyframe_interval);
+        out.extend(frames);
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn keyframe_interval(&self) -> usize {
+        self.keyframe_interval
+    }
+
+    /// Whether the frame is stored in full rather than as a diff
+    pub fn is_stored_keyframe(&self, index: usize) -> bool {
+        matches!(self.frames.get(index), Some(StoredFrame::Key(_)))
+    }
+
+    /// Whether the frame is identical to the one before it
+    pub fn is_hold(&self, index: usize) -> bool {
+        match self.frames.get(index) {
+            Some(StoredFrame::Delta(changes)) => changes.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Changes a frame makes to the one before it, or `None` if it is stored in full
+    pub fn changes(&self, index: usize) -> Option<&[VoxelChange]> {
+        match self.frames.get(index) {
+            Some(StoredFrame::Delta(changes)) => Some(changes),
+            _ => None,
+        }
+    }
+
+    /// Number of voxels held in memory, counting keyframes in full and diffs by their changes
+    pub fn stored_voxels(&self) -> usize {
+        self.frames
+            .iter()
+            .map(|f| match f {
+                StoredFrame::Key(m) => m
+                    .value
+                    .iter()
+                    .flat_map(|l| l.value.iter())
+                    .map(|r| r.len())
+                    .sum(),
+                StoredFrame::Delta(changes) => changes.len(),
+            })
+            .sum()
+    }
+
+    /// Materializes a frame from the closest keyframe at or before it
+    pub fn get(&self, index: usize) -> Option<Model> {
+        if index >= self.frames.len() {
+            return None;
+        }
+
+        let key = self.key_index(index);
+        let mut model = match &self.frames[key] {
+            StoredFrame::Key(m) => m.clone(),
+            StoredFrame::Delta(_) => unreachable!("Key index points at a diff"),
+        };
+
+        for frame in &self.frames[key + 1..=index] {
+            if let StoredFrame::Delta(changes) = frame {
+                apply(&mut model, changes);
+            }
+        }
+
+        Some(model)
+    }
+
+    /// Materializes every frame in order, applying each diff only once
+    pub fn iter(&self) -> impl Iterator<Item = Model> + '_ {
+        let mut current: Option<Model> = None;
+        self.frames.iter().map(move |f| {
+            let model = match (f, current.take()) {
+                (StoredFrame::Key(m), _) => m.clone(),
+                (StoredFrame::Delta(changes), Some(mut m)) => {
+                    apply(&mut m, changes);
+                    m
+                }
+                (StoredFrame::Delta(_), None) => unreachable!("First frame is a diff"),
+            };
+            current = Some(model.clone());
+            model
+        })
+    }
+
+    pub fn push(&mut self, model: Model) {
+        let index = self.frames.len();
+        let frame = match index.checked_sub(1).and_then(|i| self.get(i)) {
+            Some(prev)
+                if index - self.key_index(index - 1) < self.keyframe_interval
+                    && same_layout(&prev, &model) =>
+            {
+                StoredFrame::Delta(diff(&prev, &model))
+            }
+            _ => StoredFrame::Key(model),
+        };
+
+        self.frames.push(frame);
+    }
+
+    pub fn extend<I>(&mut self, frames: I)
+    where
+        I: IntoIterator<Item = Model>,
+    {
+        for model in frames {
+            self.push(model);
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, model: Model) {
+        self.edit_from(index, |frames| frames.insert(0, model));
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Model> {
+        if index >= self.frames.len() {
+            return None;
+        }
+
+        let mut removed = None;
+        self.edit_from(index, |frames| removed = Some(frames.remove(0)));
+        removed
+    }
+
+    pub fn set(&mut self, index: usize, model: Model) {
+        if index >= self.frames.len() {
+            return;
+        }
+
+        self.edit_from(index, |frames| frames[0] = model);
+    }
+
+    /// Materializes everything from `index` onwards, edits it and encodes it again
+    fn edit_from<F>(&mut self, index: usize, edit: F)
+    where
+        F: FnOnce(&mut Vec<Model>),
+    {
+        let index = index.min(self.frames.len());
+        let mut tail = self.iter().skip(index).collect::<Vec<_>>();
+        self.frames.truncate(index);
+
+        edit(&mut tail);
+        self.extend(tail);
+    }
+
+    fn key_index(&self, index: usize) -> usize {
+        (0..=index)
+            .rev()
+            .find(|i| matches!(self.frames[*i], StoredFrame::Key(_)))
+            .expect("Frame store doesn't start with a keyframe")
+    }
+}
+
+/// Diffs can only describe voxel changes, so labels and layer sizes have to match
+fn same_layout(a: &Model, b: &Model) -> bool {
+    a.label == b.label
+        && a.value.len() == b.value.len()
+        && a.value.iter().zip(&b.value).all(|(la, lb)| {
+            la.label == lb.label
+                && la.value.len() == lb.value.len()
+                && la
+                    .value
+                    .iter()
+                    .zip(&lb.value)
+                    .all(|(ra, rb)| ra.len() == rb.len())
+        })
+}
+
+fn diff(prev: &Model, next: &Model) -> Vec<VoxelChange> {
+    let mut out = vec![];
+    for (y, (la, lb)) in prev.value.iter().zip(&next.value).enumerate() {
+        for (x, (ra, rb)) in la.value.iter().zip(&lb.value).enumerate() {
+            for (z, (va, vb)) in ra.iter().zip(rb).enumerate() {
+                if va != vb {
+                    out.push(VoxelChange {
+                        x,
+                        y,
+                        z,
+                        voxel: *vb,
+                    });
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn apply(model: &mut Model, changes: &[VoxelChange]) {
+    for c in changes {
+        model.set(c.x, c.y, c.z, c.voxel);
+    }
+}
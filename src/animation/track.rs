@@ -0,0 +1,192 @@
+use crate::{
+    models::{model::Model, voxel::Voxel},
+    utils::{self, consts::*},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    interpolation::Interpolation,
+    storage::FrameStore,
+    tween::{self, MaterialMode, TweenStrategy},
+};
+
+/// Layers of the model a track is allowed to change
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrackTarget {
+    #[default]
+    All,
+    Layers(Vec<usize>),
+}
+
+impl TrackTarget {
+    pub fn contains(&self, layer: usize) -> bool {
+        match self {
+            TrackTarget::All => true,
+            TrackTarget::Layers(layers) => layers.contains(&layer),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Replaces the targeted layers of the tracks below
+    #[default]
+    Override,
+    /// Adds filled voxels on top of the tracks below without removing any
+    Additive,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub label: &'static str,
+    pub frames: FrameStore,
+    /// How many ticks of the animation's frame rate each frame is shown for, a frame with no
+    /// ticks is skipped over
+    pub durations: Vec<u32>,
+    pub interpolation: Interpolation,
+    /// How voxels are blended between frames when `interpolation` isn't stepped
+    pub tween: TweenStrategy,
+    pub target: TrackTarget,
+    pub blend: BlendMode,
+    /// How strongly the track is applied, between 0 and 1
+    pub weight: f32,
+}
+
+impl Track {
+    pub fn new(label: &'static str, frames: Vec<Model>) -> Self {
+        Self {
+            label,
+            durations: vec![1; frames.len()],
+            frames: FrameStore::from_frames(frames, KEYFRAME_INTERVAL),
+            interpolation: Interpolation::Step,
+            tween: TweenStrategy::CrossDissolve,
+            target: TrackTarget::All,
+            blend: BlendMode::Override,
+            weight: 1.0,
+        }
+    }
+
+    /// Number of stored frames, regardless of how long they're shown for
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Total length in ticks
+    pub fn duration(&self) -> u32 {
+        self.durations.iter().sum()
+    }
+
+    /// Tick at which a frame starts
+    pub fn frame_start(&self, index: usize) -> u32 {
+        self.durations.iter().take(index).sum()
+    }
+
+    /// Frame shown at `tick` and how far through it the tick is, between 0 and 1
+    pub fn locate(&self, tick: f32) -> Option<(usize, f32)> {
+        let mut start = 0.0;
+        for (i, d) in self.durations.iter().enumerate() {
+            let end = start + *d as f32;
+            if tick < end {
+                return Some((i, ((tick - start) / *d as f32).max(0.0)));
+            }
+            start = end;
+        }
+
+        // Past the end, hold the last frame that is actually shown
+        let last = self.durations.iter().rposition(|d| *d > 0)?;
+        Some((last, 1.0))
+    }
+
+    pub fn frame(&self, index: usize) -> Option<Model> {
+        self.frames.get(index)
+    }
+
+    /// Model shown at `tick`, tweening towards the next frame if the track interpolates
+    pub fn sample(&self, tick: f32) -> Option<Model> {
+        let (index, t) = self.locate(tick)?;
+        let t = self.interpolation.apply(t);
+        let current = self.frame(index)?;
+
+        match self.frame(index + 1) {
+            Some(next) if t > 0.0 => Some(tween::tween_at(
+                &current,
+                &next,
+                t,
+                self.tween,
+                MaterialMode::Interpolate,
+            )),
+            _ => Some(current),
+        }
+    }
+
+    /// Inserts an empty frame the size of the one at `index` right after it
+    pub fn insert_frame(&mut self, index: usize) {
+        let (label, (w, h, d)) = match self.frames.get(index) {
+            Some(m) => (m.label, m.dimensions()),
+            None => (self.label, (0, 0, 0)),
+        };
+        let index = (index + 1).min(self.len());
+        self.frames.insert(index, Model::empty(label, w, h, d));
+        self.durations.insert(index, 1);
+    }
+
+    pub fn duplicate_frame(&mut self, index: usize) {
+        if let Some(m) = self.frames.get(index) {
+            self.frames.insert(index + 1, m);
+            self.durations.insert(index + 1, self.durations[index]);
+        }
+    }
+
+    /// Deletes a frame unless it is the last one left
+    pub fn delete_frame(&mut self, index: usize) -> bool {
+        if self.len() > 1 && self.frames.remove(index).is_some() {
+            self.durations.remove(index);
+            return true;
+        }
+
+        false
+    }
+}
+
+/// Applies a track's frame on top of the result of the tracks below it
+pub fn blend(base: &mut Model, frame: &Model, track: &Track) {
+    let weight = track.weight.clamp(0.0, 1.0);
+    if weight <= 0.0 {
+        return;
+    }
+
+    let blended = match track.blend {
+        // Dissolving towards the frame keeps partial weights stable from one tick to the next
+        BlendMode::Override => tween::tween_at(
+            base,
+            frame,
+            weight,
+            TweenStrategy::CrossDissolve,
+            MaterialMode::Interpolate,
+        ),
+        BlendMode::Additive => {
+            let mut out = base.clone();
+            for ((x, y, z), voxel) in frame.filled_voxels() {
+                let material = match base.get(x, y, z) {
+                    Some(v) if v.filled => v.material.lerp(&voxel.material, weight),
+                    _ if utils::hash3(x, y, z) < weight => voxel.material,
+                    _ => continue,
+                };
+                out.set(x, y, z, Voxel::new(true, material));
+            }
+            out
+        }
+    };
+
+    for (y, layer) in base.value.iter_mut().enumerate() {
+        if track.target.contains(y) {
+            if let Some(l) = blended.value.get(y) {
+                layer.value = l.value.clone();
+            }
+        }
+    }
+}
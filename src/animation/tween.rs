@@ -0,0 +1,325 @@
+use crate::{
+    models::{material::Material, model::Model, voxel::Voxel},
+    utils,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TweenStrategy {
+    /// Voxels only present in one keyframe fade in or out in a stable pseudo-random order
+    CrossDissolve,
+    /// Voxels are matched by their offset from each keyframe's centroid and travel between them
+    CentroidMigration,
+    /// Signed distance fields of both keyframes are blended and thresholded back into voxels
+    SignedDistance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialMode {
+    Interpolate,
+    /// Interpolate, then snap to the closest material used by either keyframe
+    SnapToPalette,
+}
+
+// Stands in for infinity in the distance transform, which can't subtract infinities
+const FAR: f32 = 1e20;
+
+/// Generates `frames` in-betweens, not including the keyframes themselves
+pub fn tween(
+    from: &Model,
+    to: &Model,
+    frames: usize,
+    strategy: TweenStrategy,
+    material_mode: MaterialMode,
+) -> Vec<Model> {
+    (1..=frames)
+        .map(|i| {
+            let t = i as f32 / (frames + 1) as f32;
+            tween_at(from, to, t, strategy, material_mode)
+        })
+        .collect()
+}
+
+/// Single in-between at `t`, where 0 is `from` and 1 is `to`
+pub fn tween_at(
+    from: &Model,
+    to: &Model,
+    t: f32,
+    strategy: TweenStrategy,
+    material_mode: MaterialMode,
+) -> Model {
+    let t = t.clamp(0.0, 1.0);
+    let (fw, fh, fd) = from.dimensions();
+    let (tw, th, td) = to.dimensions();
+
+    let mut out = Model::empty(from.label, fw.max(tw), fh.max(th), fd.max(td));
+    for (i, layer) in out.value.iter_mut().enumerate() {
+        if let Some(l) = from.value.get(i).or(to.value.get(i)) {
+            layer.label = l.label;
+        }
+    }
+
+    match strategy {
+        TweenStrategy::CrossDissolve => cross_dissolve(from, to, t, &mut out),
+        TweenStrategy::CentroidMigration => centroid_migration(from, to, t, &mut out),
+        TweenStrategy::SignedDistance => signed_distance(from, to, t, &mut out),
+    }
+
+    if material_mode == MaterialMode::SnapToPalette {
+        let mut palette = from.palette();
+        for m in to.palette() {
+            utils::push_if_absent(&mut palette, m);
+        }
+
+        for layer in out.value.iter_mut() {
+            for row in layer.value.iter_mut() {
+                for voxel in row.iter_mut().filter(|v| v.filled) {
+                    voxel.material = voxel.material.nearest(&palette);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn cross_dissolve(from: &Model, to: &Model, t: f32, out: &mut Model) {
+    let (w, h, d) = out.dimensions();
+    for y in 0..h {
+        for x in 0..w {
+            for z in 0..d {
+                let a = from.get(x, y, z).filter(|v| v.filled);
+                let b = to.get(x, y, z).filter(|v| v.filled);
+
+                let material = match (a, b) {
+                    (Some(a), Some(b)) => Some(a.material.lerp(&b.material, t)),
+                    (Some(a), None) if utils::hash3(x, y, z) >= t => Some(a.material),
+                    (None, Some(b)) if utils::hash3(x, y, z) < t => Some(b.material),
+                    _ => None,
+                };
+
+                if let Some(material) = material {
+                    out.set(x, y, z, Voxel::new(true, material));
+                }
+            }
+        }
+    }
+}
+
+fn centroid_migration(from: &Model, to: &Model, t: f32, out: &mut Model) {
+    let a = from.filled_voxels();
+    let b = to.filled_voxels();
+
+    // Nothing to travel from or to
+    if a.is_empty() || b.is_empty() {
+        return cross_dissolve(from, to, t, out);
+    }
+
+    let pos = |p: (usize, usize, usize)| glam::vec3(p.0 as f32, p.1 as f32, p.2 as f32);
+    let centroid = |list: &Vec<((usize, usize, usize), Voxel)>| {
+        list.iter().map(|(p, _)| pos(*p)).sum::<glam::Vec3>() / list.len() as f32
+    };
+    let ca = centroid(&a);
+    let cb = centroid(&b);
+
+    let nearest =
+        |offset: glam::Vec3, list: &Vec<((usize, usize, usize), Voxel)>, c: glam::Vec3| {
+            let mut best = 0;
+            let mut best_dist = f32::INFINITY;
+            for (i, (p, _)) in list.iter().enumerate() {
+                let dist = (pos(*p) - c).distance_squared(offset);
+                if dist < best_dist {
+                    best = i;
+                    best_dist = dist;
+                }
+            }
+            best
+        };
+
+    // Every voxel of both keyframes takes part in at least one pair, so both ends are exact
+    let mut pairs = vec![];
+    let mut used = vec![false; b.len()];
+    for (i, (p, _)) in a.iter().enumerate() {
+        let j = nearest(pos(*p) - ca, &b, cb);
+        used[j] = true;
+        pairs.push((i, j));
+    }
+    for (j, (p, _)) in b.iter().enumerate() {
+        if !used[j] {
+            pairs.push((nearest(pos(*p) - cb, &a, ca), j));
+        }
+    }
+
+    for (i, j) in pairs {
+        let p = pos(a[i].0).lerp(pos(b[j].0), t).round();
+        let material = a[i].1.material.lerp(&b[j].1.material, t);
+        out.set(
+            p.x as usize,
+            p.y as usize,
+            p.z as usize,
+            Voxel::new(true, material),
+        );
+    }
+}
+
+fn signed_distance(from: &Model, to: &Model, t: f32, out: &mut Model) {
+    let dims = out.dimensions();
+    let (w, h, d) = dims;
+    let sa = signed_distance_field(from, dims);
+    let sb = signed_distance_field(to, dims);
+
+    for y in 0..h {
+        for x in 0..w {
+            for z in 0..d {
+                // Fields are padded by one voxel on every side
+                let i = ((y + 1) * (w + 2) + x + 1) * (d + 2) + z + 1;
+                if sa[i] * (1.0 - t) + sb[i] * t >= 0.0 {
+                    continue;
+                }
+
+                let material = match (
+                    nearest_material(from, x, y, z),
+                    nearest_material(to, x, y, z),
+                ) {
+                    (Some(a), Some(b)) => a.lerp(&b, t),
+                    (Some(a), None) => a,
+                    (None, Some(b)) => b,
+                    (None, None) => Material::default(),
+                };
+                out.set(x, y, z, Voxel::new(true, material));
+            }
+        }
+    }
+}
+
+/// Negative inside the model, positive outside, crossing zero halfway between voxel centers.
+/// The field covers `dims` plus one empty voxel of padding on every side.
+fn signed_distance_field(model: &Model, dims: (usize, usize, usize)) -> Vec<f32> {
+    let (w, h, d) = (dims.0 + 2, dims.1 + 2, dims.2 + 2);
+    let mut filled = vec![false; w * h * d];
+    for ((x, y, z), _) in model.filled_voxels() {
+        filled[((y + 1) * w + x + 1) * d + z + 1] = true;
+    }
+    let empty = filled.iter().map(|f| !f).collect::<Vec<_>>();
+
+    let to_filled = squared_distance_transform(&filled, (w, h, d));
+    let to_empty = squared_distance_transform(&empty, (w, h, d));
+
+    // An empty model has no surface, so keep everything well outside
+    let outside = (w + h + d) as f32;
+
+    (0..filled.len())
+        .map(|i| match filled[i] {
+            true => 0.5 - to_empty[i].sqrt(),
+            false if to_filled[i] >= FAR => outside,
+            false => to_filled[i].sqrt() - 0.5,
+        })
+        .collect()
+}
+
+/// Exact squared euclidean distance from every cell to the closest cell set in `mask`,
+/// using separable lower envelopes of parabolas (Felzenszwalb & Huttenlocher)
+fn squared_distance_transform(mask: &[bool], dims: (usize, usize, usize)) -> Vec<f32> {
+    let (w, h, d) = dims;
+    let mut grid = mask
+        .iter()
+        .map(|m| match m {
+            true => 0.0,
+            false => FAR,
+        })
+        .collect::<Vec<_>>();
+
+    // Columns (z)
+    for y in 0..h {
+        for x in 0..w {
+            transform_line(&mut grid, (y * w + x) * d, 1, d);
+        }
+    }
+
+    // Rows (x)
+    for y in 0..h {
+        for z in 0..d {
+            transform_line(&mut grid, y * w * d + z, d, w);
+        }
+    }
+
+    // Layers (y)
+    for x in 0..w {
+        for z in 0..d {
+            transform_line(&mut grid, x * d + z, w * d, h);
+        }
+    }
+
+    grid
+}
+
+fn transform_line(grid: &mut [f32], start: usize, stride: usize, len: usize) {
+    let f = (0..len)
+        .map(|i| grid[start + i * stride])
+        .collect::<Vec<_>>();
+    let mut v = vec![0usize; len];
+    let mut z = vec![0.0f32; len + 1];
+    let mut k = 0;
+
+    z[0] = f32::NEG_INFINITY;
+    z[1] = f32::INFINITY;
+
+    for q in 1..len {
+        loop {
+            let p = v[k];
+            let s = ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2 * (q - p)) as f32;
+            if s <= z[k] && k > 0 {
+                k -= 1;
+                continue;
+            }
+
+            k += 1;
+            v[k] = q;
+            z[k] = s;
+            z[k + 1] = f32::INFINITY;
+            break;
+        }
+    }
+
+    k = 0;
+    for q in 0..len {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q.abs_diff(v[k]) as f32;
+        grid[start + q * stride] = (offset * offset + f[v[k]]).min(FAR);
+    }
+}
+
+/// Material of the closest filled voxel, searching outwards in growing cubes
+fn nearest_material(model: &Model, x: usize, y: usize, z: usize) -> Option<Material> {
+    let (w, h, d) = model.dimensions();
+    let max_radius = w.max(h).max(d) as i64;
+    let (x, y, z) = (x as i64, y as i64, z as i64);
+
+    for r in 0..=max_radius {
+        for oy in -r..=r {
+            for ox in -r..=r {
+                for oz in -r..=r {
+                    // Only the surface of the cube, the inside was searched already
+                    if ox.abs().max(oy.abs()).max(oz.abs()) != r {
+                        continue;
+                    }
+
+                    let (px, py, pz) = (x + ox, y + oy, z + oz);
+                    if px < 0 || py < 0 || pz < 0 {
+                        continue;
+                    }
+
+                    if let Some(v) = model.get(px as usize, py as usize, pz as usize) {
+                        if v.filled {
+                            return Some(v.material);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
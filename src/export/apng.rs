@@ -0,0 +1,51 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+
+use crate::animation::Animation;
+
+use super::{frame_delays, ExportOptions, FrameRenderer, Repeat, TimedFrame};
+
+/// Encodes frames that have already been rendered, losslessly and keeping alpha
+pub fn write_frames<W>(out: W, frames: &[TimedFrame], options: &ExportOptions) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let first = frames.first().context("No frames to export")?;
+    let mut encoder = png::Encoder::new(out, first.image.width, first.image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    // Zero plays means forever
+    let plays = match options.repeat {
+        Repeat::Forever => 0,
+        Repeat::Times(n) => n.max(1) as u32,
+    };
+    encoder.set_animated(frames.len() as u32, plays)?;
+
+    let mut writer = encoder.write_header()?;
+    for (frame, delay) in frames.iter().zip(frame_delays(frames, 1000.0)) {
+        writer.set_frame_delay(delay.min(u16::MAX as u32) as u16, 1000)?;
+        writer.write_image_data(frame.image.as_bytes())?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Renders every exported tick into an animated PNG
+pub fn write<R, P>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    path: P,
+) -> anyhow::Result<()>
+where
+    R: FrameRenderer + ?Sized,
+    P: AsRef<Path>,
+{
+    let frames = super::timed_frames(animation, renderer, options)?;
+    let file = std::fs::File::create(&path)
+        .with_context(|| format!("Couldn't create {}", path.as_ref().display()))?;
+    write_frames(std::io::BufWriter::new(file), &frames, options)
+}
@@ -0,0 +1,177 @@
+use std::{borrow::Cow, collections::HashMap, io::Write, path::Path};
+
+use anyhow::Context;
+
+use crate::{
+    animation::Animation,
+    utils::{image::color_to_rgba8, push_if_absent},
+};
+
+use super::{frame_delays, ExportOptions, FrameRenderer, Repeat, TimedFrame};
+
+const MAX_COLORS: usize = 256;
+
+/// Indexed colors for every frame. Starts with the background, or a transparent entry if the
+/// background is see-through, then the materials, then the most common other colors in the
+/// frames, such as anti-aliased edges, while there is room.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<[u8; 3]>,
+    pub transparent: Option<u8>,
+    lookup: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    pub fn new(frames: &[TimedFrame], materials: &[[u8; 4]], background: [f32; 4]) -> Self {
+        let transparent = (background[3] < 1.0).then_some(0);
+        let background = color_to_rgba8(background);
+        let mut colors = vec![[background[0], background[1], background[2]]];
+        for m in materials {
+            push_if_absent(&mut colors, [m[0], m[1], m[2]]);
+        }
+        // The transparent entry is only ever picked by alpha, so the background needs its own
+        if transparent.is_some() {
+            colors.insert(0, [0, 0, 0]);
+        }
+        colors.truncate(MAX_COLORS);
+
+        let mut counts = HashMap::new();
+        for p in frames.iter().flat_map(|f| &f.image.pixels) {
+            if transparent.is_none() || p[3] >= 128 {
+                *counts.entry([p[0], p[1], p[2]]).or_insert(0usize) += 1;
+            }
+        }
+        let mut common = counts
+            .into_iter()
+            .filter(|(c, _)| !colors.contains(c))
+            .collect::<Vec<_>>();
+        // Ties are broken by color so exports are the same every time
+        common.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let room = MAX_COLORS - colors.len();
+        colors.extend(common.into_iter().take(room).map(|(c, _)| c));
+
+        Self {
+            colors,
+            transparent,
+            lookup: HashMap::new(),
+        }
+    }
+
+    /// Index of the closest color, or the transparent entry for mostly see-through pixels
+    pub fn index(&mut self, pixel: [u8; 4]) -> u8 {
+        if let Some(t) = self.transparent {
+            if pixel[3] < 128 {
+                return t;
+            }
+        }
+
+        let rgb = [pixel[0], pixel[1], pixel[2]];
+        if let Some(i) = self.lookup.get(&rgb) {
+            return *i;
+        }
+
+        let distance = |c: &[u8; 3]| {
+            (0..3)
+                .map(|i| (c[i] as i32 - rgb[i] as i32).pow(2))
+                .sum::<i32>()
+        };
+        let index = self
+            .colors
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.transparent != Some(*i as u8))
+            .min_by_key(|(_, c)| distance(c))
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0);
+
+        self.lookup.insert(rgb, index);
+        index
+    }
+}
+
+/// Colors of every material in the exported ticks, as the renderer draws them
+pub fn materials<R>(animation: &Animation, renderer: &R, options: &ExportOptions) -> Vec<[u8; 4]>
+where
+    R: FrameRenderer + ?Sized,
+{
+    let mut out = vec![];
+    for tick in options.frame_range(animation) {
+        if let Some(model) = animation.sample(tick as f32) {
+            for material in model.palette() {
+                push_if_absent(&mut out, renderer.material_color(&material));
+            }
+        }
+    }
+
+    out
+}
+
+/// Encodes frames that have already been rendered, with a palette favouring `materials`
+pub fn write_frames<W>(
+    out: W,
+    frames: &[TimedFrame],
+    materials: &[[u8; 4]],
+    options: &ExportOptions,
+) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let first = frames.first().context("No frames to export")?;
+    let (width, height) = (
+        u16::try_from(first.image.width).context("Too wide for a GIF")?,
+        u16::try_from(first.image.height).context("Too tall for a GIF")?,
+    );
+
+    let mut palette = Palette::new(frames, materials, options.background);
+    let mut encoder = gif::Encoder::new(out, width, height, palette.colors.as_flattened())?;
+    match options.repeat {
+        Repeat::Forever => encoder.set_repeat(gif::Repeat::Infinite)?,
+        // Without the loop extension it plays once, and its count is of plays after the first
+        Repeat::Times(n) if n > 1 => encoder.set_repeat(gif::Repeat::Finite(n - 1))?,
+        Repeat::Times(_) => {}
+    }
+
+    for (frame, delay) in frames.iter().zip(frame_delays(frames, 100.0)) {
+        let buffer = frame
+            .image
+            .pixels
+            .iter()
+            .map(|p| palette.index(*p))
+            .collect::<Vec<_>>();
+
+        encoder.write_frame(&gif::Frame {
+            width,
+            height,
+            delay: delay.min(u16::MAX as u32) as u16,
+            transparent: palette.transparent,
+            // Otherwise the last frame shows through the see-through parts of the next
+            dispose: match palette.transparent {
+                Some(_) => gif::DisposalMethod::Background,
+                None => gif::DisposalMethod::Keep,
+            },
+            buffer: Cow::Owned(buffer),
+            ..Default::default()
+        })?;
+    }
+
+    encoder.into_inner()?.flush()?;
+    Ok(())
+}
+
+/// Renders every exported tick into an animated GIF
+pub fn write<R, P>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    path: P,
+) -> anyhow::Result<()>
+where
+    R: FrameRenderer + ?Sized,
+    P: AsRef<Path>,
+{
+    let frames = super::timed_frames(animation, renderer, options)?;
+    let materials = materials(animation, renderer, options);
+    let file = std::fs::File::create(&path)
+        .with_context(|| format!("Couldn't create {}", path.as_ref().display()))?;
+    write_frames(std::io::BufWriter::new(file), &frames, &materials, options)
+}
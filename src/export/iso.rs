@@ -0,0 +1,200 @@
+use glam::Vec3;
+
+use crate::{
+    animation::camera::CameraKey,
+    models::model::Model,
+    utils::image::{color_to_rgba8, Image},
+};
+
+use super::{ExportOptions, FrameRenderer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsoProjection {
+    /// The usual pixel art projection, edges step two pixels across for every one down
+    Dimetric,
+    /// Every axis is the same length on screen, edges are at 30 degrees
+    Isometric,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Face {
+    Top,
+    Left,
+    Right,
+}
+
+/// Draws voxels on the CPU as pixel art, every voxel covering the same pixels. Looks from above,
+/// down the diagonal between the `x` and `z` axes, turned in quarter turns to whichever side the
+/// camera is on. The camera's target is drawn in the middle of the image, its zoom and field of
+/// view are ignored so the size of voxels stays fixed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoRenderer {
+    pub projection: IsoProjection,
+    /// Height of a voxel's top face in pixels is twice this, everything else is sized from it
+    pub scale: u32,
+    /// Brightness of the top, left and right faces
+    pub shading: [f32; 3],
+    /// Drawn around the outside of the model
+    pub outline: Option<[u8; 4]>,
+}
+
+impl Default for IsoRenderer {
+    fn default() -> Self {
+        Self {
+            projection: IsoProjection::Dimetric,
+            scale: 2,
+            shading: [1.0, 0.8, 0.6],
+            outline: None,
+        }
+    }
+}
+
+impl IsoRenderer {
+    /// Half the width and height of a top face, and the height of a side, in pixels
+    fn footprint_size(&self) -> (i32, i32, i32) {
+        let half_height = self.scale.max(1) as i32;
+        let half_width = match self.projection {
+            IsoProjection::Dimetric => half_height * 2,
+            IsoProjection::Isometric => (half_height as f32 * 3f32.sqrt()).round() as i32,
+        };
+        (half_width, half_height, half_height * 2)
+    }
+
+    /// Pixels a voxel covers, relative to where its lowest corner lands
+    fn footprint(&self) -> Vec<(i32, i32, Face)> {
+        let (hw, ht, side) = self.footprint_size();
+        let (w, h, s) = (hw as f32, ht as f32, side as f32);
+        let mut out = vec![];
+
+        for v in -side..ht * 2 {
+            for u in -hw..hw {
+                let (x, y) = (u as f32 + 0.5, v as f32 + 0.5);
+                let face = if x.abs() / w + (y - h + s).abs() / h <= 1.0 {
+                    Face::Top
+                } else if x < 0.0 {
+                    let top = h - s + (x + w) * h / w;
+                    match y >= top && y <= top + s {
+                        true => Face::Left,
+                        false => continue,
+                    }
+                } else {
+                    let top = h * 2.0 - s - x * h / w;
+                    match y >= top && y <= top + s {
+                        true => Face::Right,
+                        false => continue,
+                    }
+                };
+                out.push((u, v, face));
+            }
+        }
+
+        out
+    }
+
+    fn project(&self, p: Vec3) -> (f32, f32) {
+        let (hw, ht, side) = self.footprint_size();
+        (
+            (p.x - p.z) * hw as f32,
+            (p.x + p.z) * ht as f32 - p.y * side as f32,
+        )
+    }
+}
+
+/// Quarter turns taking the side the camera is on to the side the renderer looks from
+fn quarter_turns(camera: &CameraKey) -> u32 {
+    let offset = camera.eye - camera.target;
+    let angle = offset.x.atan2(offset.z).rem_euclid(std::f32::consts::TAU);
+    ((angle + 1e-3) / std::f32::consts::FRAC_PI_2) as u32 % 4
+}
+
+/// Turns a position a quarter turn at a time, the opposite way to the camera
+fn turn(x: f32, z: f32, turns: u32) -> (f32, f32) {
+    (0..turns).fold((x, z), |(x, z), _| (-z, x))
+}
+
+impl FrameRenderer for IsoRenderer {
+    fn render(
+        &mut self,
+        model: &Model,
+        camera: &CameraKey,
+        options: &ExportOptions,
+    ) -> anyhow::Result<Image> {
+        let (width, height) = (options.width, options.height);
+        let mut out = Image::new(width, height, color_to_rgba8(options.background));
+        let mut covered = vec![false; (width * height) as usize];
+        let turns = quarter_turns(camera);
+
+        // Everything is drawn relative to the target, rounded so every voxel lands on whole pixels
+        let target = model.from_mesh_space(camera.target);
+        let (tx, tz) = turn(target.x, target.z, turns);
+        let (cx, cy) = self.project(Vec3::new(tx, target.y, tz));
+        let origin = (
+            width as i32 / 2 - cx.round() as i32,
+            height as i32 / 2 - cy.round() as i32,
+        );
+
+        // Back to front, so nearer voxels are drawn over further ones
+        let mut voxels = model
+            .filled_voxels()
+            .into_iter()
+            .map(|((x, y, z), voxel)| {
+                // Turns the voxel's center, then finds its lowest corner
+                let (x, z) = turn(x as f32 + 0.5, z as f32 + 0.5, turns);
+                let corner = Vec3::new(x - 0.5, y as f32, z - 0.5).round().as_ivec3();
+                (corner, voxel.material)
+            })
+            .collect::<Vec<_>>();
+        voxels.sort_by_key(|(p, _)| (p.x + p.y + p.z, p.y, p.x));
+
+        let footprint = self.footprint();
+        for (p, material) in voxels {
+            let (px, py) = self.project(p.as_vec3());
+            let (ax, ay) = (origin.0 + px as i32, origin.1 + py as i32);
+            let base = material.color.to_array();
+
+            for (u, v, face) in &footprint {
+                let (x, y) = (ax + u, ay + v);
+                if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                    continue;
+                }
+
+                let shade = match face {
+                    Face::Top => self.shading[0],
+                    Face::Left => self.shading[1],
+                    Face::Right => self.shading[2],
+                };
+                let [r, g, b, a] = base;
+                out.set(
+                    x as u32,
+                    y as u32,
+                    color_to_rgba8([r * shade, g * shade, b * shade, a]),
+                );
+                covered[(y as u32 * width + x as u32) as usize] = true;
+            }
+        }
+
+        if let Some(color) = self.outline {
+            let is_covered = |x: i32, y: i32| {
+                x >= 0
+                    && y >= 0
+                    && x < width as i32
+                    && y < height as i32
+                    && covered[(y as u32 * width + x as u32) as usize]
+            };
+
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    let edge = !is_covered(x, y)
+                        && [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                            .iter()
+                            .any(|(dx, dy)| is_covered(x + dx, y + dy));
+                    if edge {
+                        out.set(x as u32, y as u32, color);
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
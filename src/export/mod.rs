@@ -0,0 +1,171 @@
+pub mod apng;
+pub mod gif;
+pub mod iso;
+pub mod path_tracer;
+pub mod sequence;
+pub mod sprite_sheet;
+pub mod stack;
+pub mod y4m;
+
+use std::ops::Range;
+
+use crate::{
+    animation::{camera::CameraKey, Animation},
+    models::{material::Material, model::Model},
+    utils::image::{color_to_rgba8, Image},
+};
+
+/// Anything that can turn a model into an image, so exporters don't care how frames are drawn
+pub trait FrameRenderer {
+    fn render(
+        &mut self,
+        model: &Model,
+        camera: &CameraKey,
+        options: &ExportOptions,
+    ) -> anyhow::Result<Image>;
+
+    /// Color flat surfaces of a material come out as, for building palettes
+    fn material_color(&self, material: &Material) -> [u8; 4] {
+        color_to_rgba8(material.color.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportCamera {
+    /// Follows the animation's camera track, or the default view if it has no keys
+    Animated,
+    Fixed(CameraKey),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Straight RGBA, an alpha below 1 leaves the background see-through in formats with alpha
+    pub background: [f32; 4],
+    pub camera: ExportCamera,
+    /// Ticks to export, everything if `None`
+    pub frames: Option<Range<u32>>,
+    /// Looping of animated formats
+    pub repeat: Repeat,
+}
+
+/// How many times animated formats play through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Repeat {
+    #[default]
+    Forever,
+    /// Plays this many times, at least once, then stays on the last frame
+    Times(u16),
+}
+
+/// Rendered image shown for `duration` seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedFrame {
+    pub image: Image,
+    pub duration: f32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            width: 512,
+            height: 512,
+            background: [0.0, 0.0, 0.0, 1.0],
+            camera: ExportCamera::Animated,
+            frames: None,
+            repeat: Repeat::Forever,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Ticks to export, clamped to the animation
+    pub fn frame_range(&self, animation: &Animation) -> Range<u32> {
+        let duration = animation.duration();
+        match &self.frames {
+            Some(r) => r.start.min(duration)..r.end.min(duration),
+            None => 0..duration,
+        }
+    }
+
+    pub fn camera_at(&self, animation: &Animation, tick: u32) -> CameraKey {
+        match self.camera {
+            ExportCamera::Animated => animation.camera.sample(tick as f32).unwrap_or(CameraKey {
+                frame: tick,
+                ..Default::default()
+            }),
+            ExportCamera::Fixed(key) => key,
+        }
+    }
+}
+
+/// Renders every exported tick in order, handing each image to `write` along with its tick
+pub fn render_frames<R, F>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    mut write: F,
+) -> anyhow::Result<()>
+where
+    R: FrameRenderer + ?Sized,
+    F: FnMut(u32, Image) -> anyhow::Result<()>,
+{
+    for tick in options.frame_range(animation) {
+        let model = match animation.sample(tick as f32) {
+            Some(m) => m,
+            None => continue,
+        };
+        let camera = options.camera_at(animation, tick);
+        write(tick, renderer.render(&model, &camera, options)?)?;
+    }
+
+    Ok(())
+}
+
+/// Renders every exported tick, merging runs of identical images into one longer frame so held
+/// poses don't take up any room in animated formats
+pub fn timed_frames<R>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+) -> anyhow::Result<Vec<TimedFrame>>
+where
+    R: FrameRenderer + ?Sized,
+{
+    let tick_duration = match animation.fps > 0.0 {
+        true => 1.0 / animation.fps,
+        false => 0.0,
+    };
+
+    let mut out: Vec<TimedFrame> = vec![];
+    render_frames(animation, renderer, options, |_, image| {
+        match out.last_mut() {
+            Some(last) if last.image == image => last.duration += tick_duration,
+            _ => out.push(TimedFrame {
+                image,
+                duration: tick_duration,
+            }),
+        }
+        Ok(())
+    })?;
+
+    Ok(out)
+}
+
+/// Frame durations in whole `units` per second. Rounds the time each frame ends at rather than
+/// each duration, so rounding errors don't add up over long animations.
+pub fn frame_delays(frames: &[TimedFrame], units: f32) -> Vec<u32> {
+    let mut elapsed = 0.0;
+    let mut last_end = 0;
+    frames
+        .iter()
+        .map(|f| {
+            elapsed += f.duration;
+            let end = (elapsed * units).round() as u32;
+            let delay = end.saturating_sub(last_end);
+            last_end = end;
+            delay
+        })
+        .collect()
+}
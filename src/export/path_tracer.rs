@@ -0,0 +1,524 @@
+use glam::{IVec3, Mat4, Vec3, Vec4, Vec4Swizzles};
+
+use crate::{
+    animation::camera::CameraKey,
+    graphics::cam,
+    models::{material::Material, model::Model},
+    utils::{
+        consts::*,
+        image::{color_to_rgba8, linear_to_srgb, srgb_to_linear, Image},
+        rng::Rng,
+    },
+};
+
+use super::{ExportOptions, FrameRenderer};
+
+/// Light coming from every direction, plus a sun that casts soft shadows. Colors are linear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub zenith: Vec3,
+    pub horizon: Vec3,
+    /// Below the horizon
+    pub ground: Vec3,
+    /// Towards the sun
+    pub sun_direction: Vec3,
+    /// Light from the sun on a white surface facing it, on top of the sky's
+    pub sun_color: Vec3,
+    /// Angular radius in radians, larger suns give softer shadows
+    pub sun_radius: f32,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            zenith: Vec3::new(0.35, 0.55, 0.9),
+            horizon: Vec3::new(0.8, 0.85, 0.9),
+            ground: Vec3::new(0.3, 0.28, 0.25),
+            sun_direction: Vec3::from_array(SUN_DIRECTION).normalize(),
+            sun_color: Vec3::new(1.0, 0.95, 0.85),
+            sun_radius: 0.05,
+        }
+    }
+}
+
+impl Sky {
+    fn radiance(&self, direction: Vec3, with_sun: bool) -> Vec3 {
+        let sky = match direction.y >= 0.0 {
+            true => self.horizon.lerp(self.zenith, direction.y),
+            false => self.ground,
+        };
+
+        // Spread over the disc so hitting it directly matches sampling it
+        let cos_radius = self.sun_radius.cos();
+        if with_sun && direction.dot(self.sun_direction.normalize()) >= cos_radius {
+            let solid_angle = std::f32::consts::TAU * (1.0 - cos_radius);
+            return sky + self.sun_color * std::f32::consts::PI / solid_angle.max(1e-6);
+        }
+
+        sky
+    }
+
+    fn sample_sun(&self, rng: &mut Rng) -> Vec3 {
+        let axis = self.sun_direction.normalize();
+        let cos_theta = 1.0 - rng.next_f32() * (1.0 - self.sun_radius.cos());
+        around(axis, cos_theta, rng.next_f32() * std::f32::consts::TAU)
+    }
+}
+
+/// Offline renderer tracing rays straight through the voxel grid, for final quality stills.
+/// Frames the model the same way as the viewport and needs no GPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracer {
+    /// Per pixel
+    pub samples: u32,
+    /// Light bounces after the first hit
+    pub bounces: u32,
+    pub sky: Sky,
+    /// Color of an endless floor under the model, if any
+    pub floor: Option<Vec3>,
+    pub seed: u64,
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self {
+            samples: PATH_TRACE_SAMPLES,
+            bounces: PATH_TRACE_BOUNCES,
+            sky: Sky::default(),
+            floor: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Samples added up so far, so renders can be refined and looked at as they go
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accumulator {
+    width: u32,
+    height: u32,
+    /// Premultiplied linear color and coverage
+    sum: Vec<Vec4>,
+    samples: u32,
+}
+
+impl Accumulator {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            sum: vec![Vec4::ZERO; (width * height) as usize],
+            samples: 0,
+        }
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Average of the samples so far, encoded as sRGB
+    pub fn image(&self) -> Image {
+        let n = self.samples.max(1) as f32;
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .sum
+                .iter()
+                .map(|s| {
+                    let alpha = s.w / n;
+                    let rgb = match s.w > 0.0 {
+                        true => s.xyz() / s.w,
+                        false => Vec3::ZERO,
+                    };
+                    let [r, g, b] = rgb.to_array().map(|c| linear_to_srgb(c.clamp(0.0, 1.0)));
+                    color_to_rgba8([r, g, b, alpha])
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PathTracer {
+    /// Adds `passes` samples to every pixel of `accumulator`, an image with no pixels taking none
+    pub fn accumulate(
+        &self,
+        model: &Model,
+        camera: &CameraKey,
+        options: &ExportOptions,
+        accumulator: &mut Accumulator,
+        passes: u32,
+    ) {
+        let (width, height) = (accumulator.width, accumulator.height);
+        if width == 0 || height == 0 {
+            accumulator.samples += passes;
+            return;
+        }
+
+        let grid = Grid::new(model);
+        let view = View::new(model, camera, width, height);
+        let background = {
+            let [r, g, b, a] = options.background;
+            Vec3::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)).extend(1.0) * a
+        };
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let rows = (height as usize).div_ceil(threads).max(1);
+        let first_pass = accumulator.samples;
+        std::thread::scope(|scope| {
+            for (chunk, pixels) in accumulator
+                .sum
+                .chunks_mut(rows * width as usize)
+                .enumerate()
+            {
+                let (grid, view) = (&grid, &view);
+                scope.spawn(move || {
+                    for (i, sum) in pixels.iter_mut().enumerate() {
+                        let index = chunk * rows * width as usize + i;
+                        let (x, y) = (
+                            (index % width as usize) as u32,
+                            (index / width as usize) as u32,
+                        );
+
+                        for pass in first_pass..first_pass + passes {
+                            // Every pixel and pass gets its own stream, so threads don't matter
+                            let seed = self.seed ^ ((pass as u64) << 40) ^ index as u64;
+                            let mut rng = Rng::new(Rng::new(seed).next_u64());
+
+                            let (jx, jy) = (rng.next_f32(), rng.next_f32());
+                            let (origin, direction) = view.ray(x as f32 + jx, y as f32 + jy);
+                            *sum += match self.radiance(grid, origin, direction, &mut rng) {
+                                Some(color) => color.extend(1.0),
+                                None => background,
+                            };
+                        }
+                    }
+                });
+            }
+        });
+
+        accumulator.samples += passes;
+    }
+
+    /// Light coming back along a ray, or `None` if the ray doesn't hit anything
+    fn radiance(&self, grid: &Grid, origin: Vec3, direction: Vec3, rng: &mut Rng) -> Option<Vec3> {
+        let (mut origin, mut direction) = (origin, direction);
+        let mut throughput = Vec3::ONE;
+        let mut out = Vec3::ZERO;
+        // Only rays that didn't sample the sun already can see it
+        let mut sees_sun = true;
+
+        for bounce in 0..=self.bounces {
+            let hit = match self.intersect(grid, origin, direction) {
+                Some(h) => h,
+                None if bounce == 0 => return None,
+                None => {
+                    out += throughput * self.sky.radiance(direction, sees_sun);
+                    break;
+                }
+            };
+
+            let material = hit.material;
+            let albedo = material.color.xyz();
+            out += throughput * albedo * material.emission;
+
+            let normal = hit.normal;
+            let point = origin + direction * hit.t + normal * 1e-4;
+            let cos = (-direction).dot(normal).clamp(0.0, 1.0);
+            let schlick = (1.0 - cos).powi(5);
+            let fresnel = 0.04 + 0.96 * schlick;
+            let metal_fresnel = albedo + (Vec3::ONE - albedo) * schlick;
+            let specular = fresnel + (1.0 - fresnel) * material.metalness;
+
+            origin = point;
+            if rng.next_f32() < specular {
+                // Reflections blur as the surface gets rougher
+                let reflected = direction - normal * 2.0 * direction.dot(normal);
+                direction =
+                    (reflected + random_unit(rng) * material.roughness.powi(2)).normalize_or_zero();
+                if direction.dot(normal) <= 0.0 {
+                    break;
+                }
+
+                let tint = Vec3::splat(fresnel * (1.0 - material.metalness))
+                    + metal_fresnel * material.metalness;
+                throughput *= tint / specular;
+                sees_sun = true;
+            } else {
+                throughput *= albedo;
+
+                let light = self.sky.sample_sun(rng);
+                let facing = light.dot(normal);
+                if facing > 0.0 && self.intersect(grid, point, light).is_none() {
+                    out += throughput * self.sky.sun_color * facing;
+                }
+
+                direction = around(
+                    normal,
+                    rng.next_f32().sqrt(),
+                    rng.next_f32() * std::f32::consts::TAU,
+                );
+                sees_sun = false;
+            }
+
+            // Dim paths are cut short at random, the rest are brightened to make up for it
+            if bounce >= 2 {
+                let keep = throughput.max_element().min(0.95);
+                if rng.next_f32() >= keep {
+                    break;
+                }
+                throughput /= keep;
+            }
+        }
+
+        Some(out)
+    }
+
+    fn intersect(&self, grid: &Grid, origin: Vec3, direction: Vec3) -> Option<Hit> {
+        let voxel = grid.trace(origin, direction);
+        let floor = match self.floor {
+            Some(color) if direction.y < 0.0 && origin.y > 0.0 => Some(Hit {
+                t: -origin.y / direction.y,
+                normal: Vec3::Y,
+                material: Material::new(color.extend(1.0)),
+            }),
+            _ => None,
+        };
+
+        match (voxel, floor) {
+            (Some(v), Some(f)) if f.t < v.t => Some(f),
+            (Some(v), _) => Some(v),
+            (None, f) => f,
+        }
+    }
+}
+
+impl FrameRenderer for PathTracer {
+    fn render(
+        &mut self,
+        model: &Model,
+        camera: &CameraKey,
+        options: &ExportOptions,
+    ) -> anyhow::Result<Image> {
+        let mut accumulator = Accumulator::new(options.width, options.height);
+        self.accumulate(
+            model,
+            camera,
+            options,
+            &mut accumulator,
+            self.samples.max(1),
+        );
+        Ok(accumulator.image())
+    }
+
+    fn material_color(&self, material: &Material) -> [u8; 4] {
+        let [r, g, b, _] = material.color.to_array().map(linear_to_srgb);
+        color_to_rgba8([r, g, b, 1.0])
+    }
+}
+
+/// A still traced a few samples at a time, so whatever drives it stays responsive meanwhile
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progressive {
+    tracer: PathTracer,
+    model: Model,
+    camera: CameraKey,
+    options: ExportOptions,
+    accumulator: Accumulator,
+}
+
+impl Progressive {
+    pub fn new(
+        tracer: PathTracer,
+        model: Model,
+        camera: CameraKey,
+        options: ExportOptions,
+    ) -> Self {
+        Self {
+            accumulator: Accumulator::new(options.width, options.height),
+            tracer,
+            model,
+            camera,
+            options,
+        }
+    }
+
+    /// Adds up to `passes` more samples, returning whether it's got all the tracer's samples
+    pub fn step(&mut self, passes: u32) -> bool {
+        let passes = passes.min(self.remaining());
+        self.tracer.accumulate(
+            &self.model,
+            &self.camera,
+            &self.options,
+            &mut self.accumulator,
+            passes,
+        );
+        self.remaining() == 0
+    }
+
+    fn remaining(&self) -> u32 {
+        self.tracer
+            .samples
+            .max(1)
+            .saturating_sub(self.accumulator.samples)
+    }
+
+    /// Samples per pixel so far, and how many it'll have once finished
+    pub fn progress(&self) -> (u32, u32) {
+        (self.accumulator.samples, self.tracer.samples.max(1))
+    }
+
+    pub fn image(&self) -> Image {
+        self.accumulator.image()
+    }
+}
+
+struct Hit {
+    t: f32,
+    normal: Vec3,
+    material: Material,
+}
+
+/// Filled voxels of a model, looked up by position
+struct Grid {
+    size: IVec3,
+    cells: Vec<Option<Material>>,
+}
+
+impl Grid {
+    fn new(model: &Model) -> Self {
+        let (w, h, d) = model.dimensions();
+        let mut cells = vec![None; w * h * d];
+        for ((x, y, z), voxel) in model.filled_voxels() {
+            cells[(y * w + x) * d + z] = Some(voxel.material);
+        }
+
+        Self {
+            size: IVec3::new(w as i32, h as i32, d as i32),
+            cells,
+        }
+    }
+
+    fn get(&self, p: IVec3) -> Option<Material> {
+        if p.cmplt(IVec3::ZERO).any() || p.cmpge(self.size).any() {
+            return None;
+        }
+        self.cells[((p.y * self.size.x + p.x) * self.size.z + p.z) as usize]
+    }
+
+    /// Steps through the grid one voxel at a time until the ray hits one (DDA)
+    fn trace(&self, origin: Vec3, direction: Vec3) -> Option<Hit> {
+        let inverse = direction.recip();
+        let t0 = (Vec3::ZERO - origin) * inverse;
+        let t1 = (self.size.as_vec3() - origin) * inverse;
+        let (near, far) = (t0.min(t1), t0.max(t1));
+        let (enter, exit) = (near.max_element().max(0.0), far.min_element());
+        if enter > exit {
+            return None;
+        }
+
+        let step = direction.signum().as_ivec3();
+        let start = origin + direction * enter;
+        let mut cell = start.floor().as_ivec3().clamp(IVec3::ZERO, self.size - 1);
+        let mut normal = match near.max_element() > 0.0 {
+            true => entered_face(near, step),
+            false => -direction,
+        };
+        let mut t = enter;
+        let next_boundary = (cell + step.max(IVec3::ZERO)).as_vec3();
+        let mut t_max = Vec3::select(
+            direction.cmpeq(Vec3::ZERO),
+            Vec3::INFINITY,
+            (next_boundary - origin) * inverse,
+        );
+        let t_delta = inverse.abs();
+
+        loop {
+            if let Some(material) = self.get(cell) {
+                return Some(Hit {
+                    t,
+                    normal,
+                    material,
+                });
+            }
+
+            let axis = match (t_max.x < t_max.y, t_max.x < t_max.z, t_max.y < t_max.z) {
+                (true, true, _) => 0,
+                (false, _, true) => 1,
+                _ => 2,
+            };
+            t = t_max[axis];
+            t_max[axis] += t_delta[axis];
+            cell[axis] += step[axis];
+            normal = Vec3::ZERO;
+            normal[axis] = -step[axis] as f32;
+
+            if cell[axis] < 0 || cell[axis] >= self.size[axis] {
+                return None;
+            }
+        }
+    }
+}
+
+fn entered_face(near: Vec3, step: IVec3) -> Vec3 {
+    let axis = match (near.x > near.y, near.x > near.z, near.y > near.z) {
+        (true, true, _) => 0,
+        (false, _, true) => 1,
+        _ => 2,
+    };
+    let mut normal = Vec3::ZERO;
+    normal[axis] = -step[axis] as f32;
+    normal
+}
+
+/// Direction at `cos_theta` from `axis`, turned `phi` around it
+fn around(axis: Vec3, cos_theta: f32, phi: f32) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (a, b) = axis.any_orthonormal_pair();
+    (a * phi.cos() * sin_theta + b * phi.sin() * sin_theta + axis * cos_theta).normalize()
+}
+
+fn random_unit(rng: &mut Rng) -> Vec3 {
+    let cos_theta = rng.range_f32(-1.0, 1.0);
+    around(Vec3::Y, cos_theta, rng.next_f32() * std::f32::consts::TAU)
+}
+
+/// Rays through the same projection as the viewport, in the model's voxel coordinates
+struct View {
+    /// Where rays start, in voxels
+    origin: Vec3,
+    /// Where rays start in mesh space, which the inverse view projection unprojects into
+    eye: Vec3,
+    inverse: Mat4,
+    width: f32,
+    height: f32,
+}
+
+impl View {
+    fn new(model: &Model, camera: &CameraKey, width: u32, height: u32) -> Self {
+        let view = cam::Camera::from_key(camera, width as f32 / height.max(1) as f32);
+        let eye = view.eye();
+
+        Self {
+            origin: model.from_mesh_space(eye),
+            eye,
+            inverse: view.build_view_projection_matrix().inverse(),
+            width: width as f32,
+            height: height as f32,
+        }
+    }
+
+    fn unproject(&self, x: f32, y: f32) -> Vec3 {
+        let ndc = Vec4::new(
+            x / self.width * 2.0 - 1.0,
+            1.0 - y / self.height * 2.0,
+            0.5,
+            1.0,
+        );
+        let p = self.inverse * ndc;
+        p.xyz() / p.w
+    }
+
+    fn ray(&self, x: f32, y: f32) -> (Vec3, Vec3) {
+        (self.origin, (self.unproject(x, y) - self.eye).normalize())
+    }
+}
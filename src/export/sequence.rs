@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::{animation::Animation, utils::image::Image};
+
+use super::{ExportOptions, FrameRenderer};
+
+pub fn write_png<P>(image: &Image, path: P) -> anyhow::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let file = std::fs::File::create(&path)
+        .with_context(|| format!("Couldn't create {}", path.as_ref().display()))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_bytes())?;
+    Ok(())
+}
+
+/// Path of a frame in a sequence, numbered by tick so gaps in the range are visible
+pub fn frame_path<P>(dir: P, prefix: &str, tick: u32) -> PathBuf
+where
+    P: AsRef<Path>,
+{
+    dir.as_ref().join(format!("{}{:04}.png", prefix, tick))
+}
+
+/// Writes every exported tick as a numbered PNG into `dir`, returning the files written
+pub fn write_sequence<R, P>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    dir: P,
+    prefix: &str,
+) -> anyhow::Result<Vec<PathBuf>>
+where
+    R: FrameRenderer + ?Sized,
+    P: AsRef<Path>,
+{
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Couldn't create {}", dir.as_ref().display()))?;
+
+    let mut out = vec![];
+    super::render_frames(animation, renderer, options, |tick, image| {
+        let path = frame_path(&dir, prefix, tick);
+        write_png(&image, &path)?;
+        out.push(path);
+        Ok(())
+    })?;
+
+    Ok(out)
+}
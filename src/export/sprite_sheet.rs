@@ -0,0 +1,242 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::{camera::CameraKey, marker, Animation},
+    utils::image::Image,
+};
+
+use super::{ExportOptions, FrameRenderer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SheetOptions {
+    /// Angles to render every frame from, evenly spaced around the target and turning
+    /// counterclockwise seen from above, starting at the export camera
+    pub directions: u32,
+    /// Empty pixels between sprites, so filtering doesn't bleed their neighbours in
+    pub padding: u32,
+    /// Crops sprites to the bounds of what isn't see-through
+    pub trim: bool,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            directions: 8,
+            padding: 1,
+            trim: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpriteFrame {
+    pub tick: u32,
+    pub direction: u32,
+    /// Milliseconds
+    pub duration: u32,
+    /// Identical sprites share a rect
+    pub rect: Rect,
+    /// Where the camera's target lands, from the top left of `rect`, so sprites line up when
+    /// drawn at the same point
+    pub pivot: [i32; 2],
+}
+
+/// Metadata written next to a sheet, with frames ordered by direction then tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Atlas {
+    pub image: String,
+    pub animation: String,
+    pub fps: f32,
+    pub size: [u32; 2],
+    /// Size of sprites before trimming
+    pub sprite_size: [u32; 2],
+    pub directions: u32,
+    pub frames: Vec<SpriteFrame>,
+    /// Marker events, the same as the sidecar's
+    pub markers: serde_json::Value,
+}
+
+/// Export camera turned around its target
+pub fn direction_camera(camera: &CameraKey, direction: u32, directions: u32) -> CameraKey {
+    let angle = std::f32::consts::TAU * direction as f32 / directions.max(1) as f32;
+    CameraKey {
+        eye: camera.target + glam::Quat::from_rotation_y(angle) * (camera.eye - camera.target),
+        ..*camera
+    }
+}
+
+/// Renders every exported tick from every direction and packs them into one image. The same
+/// input always gives the same sheet and atlas.
+pub fn build<R>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    sheet: &SheetOptions,
+    image_name: &str,
+) -> anyhow::Result<(Image, Atlas)>
+where
+    R: FrameRenderer + ?Sized,
+{
+    let directions = sheet.directions.max(1);
+    let mut sprites = vec![];
+    let mut lookup = HashMap::new();
+    let mut frames = vec![];
+
+    let range = options.frame_range(animation);
+    for tick in range.clone() {
+        let model = match animation.sample(tick as f32) {
+            Some(m) => m,
+            None => continue,
+        };
+
+        // Rounds where each tick ends so durations add up to the animation's length
+        let end = |t: u32| match animation.fps > 0.0 {
+            true => ((t - range.start) as f32 * 1000.0 / animation.fps).round() as u32,
+            false => 0,
+        };
+        let duration = end(tick + 1) - end(tick);
+
+        let camera = options.camera_at(animation, tick);
+        for direction in 0..directions {
+            let camera = direction_camera(&camera, direction, directions);
+            let image = renderer.render(&model, &camera, options)?;
+            let center = [image.width as i32 / 2, image.height as i32 / 2];
+            let (offset, image) = match sheet.trim {
+                true => trim(&image),
+                false => ((0, 0), image),
+            };
+
+            let index = *lookup.entry(image.clone()).or_insert_with(|| {
+                sprites.push(image);
+                sprites.len() - 1
+            });
+            let frame = SpriteFrame {
+                tick,
+                direction,
+                duration,
+                rect: Rect::default(),
+                pivot: [center[0] - offset.0 as i32, center[1] - offset.1 as i32],
+            };
+            frames.push((frame, index));
+        }
+    }
+
+    let (rects, size) = pack(&sprites, sheet.padding);
+    let mut out = Image::new(size.0, size.1, [0; 4]);
+    for (sprite, rect) in sprites.iter().zip(&rects) {
+        out.blit(sprite, rect.x, rect.y);
+    }
+
+    frames.sort_by_key(|(f, _)| (f.direction, f.tick));
+    let atlas = Atlas {
+        image: image_name.to_string(),
+        animation: animation.label.to_string(),
+        fps: animation.fps,
+        size: [size.0, size.1],
+        sprite_size: [options.width, options.height],
+        directions,
+        frames: frames
+            .into_iter()
+            .map(|(f, i)| SpriteFrame {
+                rect: rects[i],
+                ..f
+            })
+            .collect(),
+        markers: marker::events(animation),
+    };
+
+    Ok((out, atlas))
+}
+
+/// Writes `{name}.png` and its `{name}.json` atlas into `dir`
+pub fn write<R, P>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    sheet: &SheetOptions,
+    dir: P,
+    name: &str,
+) -> anyhow::Result<()>
+where
+    R: FrameRenderer + ?Sized,
+    P: AsRef<Path>,
+{
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Couldn't create {}", dir.as_ref().display()))?;
+
+    let image_name = format!("{}.png", name);
+    let (image, atlas) = build(animation, renderer, options, sheet, &image_name)?;
+    super::sequence::write_png(&image, dir.as_ref().join(&image_name))?;
+
+    let path = dir.as_ref().join(format!("{}.json", name));
+    std::fs::write(&path, serde_json::to_string_pretty(&atlas)?)
+        .with_context(|| format!("Couldn't write {}", path.display()))?;
+    Ok(())
+}
+
+/// Crops to everything that isn't fully see-through, returning where the crop starts
+fn trim(image: &Image) -> ((u32, u32), Image) {
+    let (mut min, mut max) = ((u32::MAX, u32::MAX), (0, 0));
+    for (i, p) in image.pixels.iter().enumerate() {
+        if p[3] > 0 {
+            let (x, y) = (i as u32 % image.width, i as u32 / image.width);
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+    }
+
+    match min.0 <= max.0 {
+        true => (
+            min,
+            image.crop(min.0, min.1, max.0 - min.0 + 1, max.1 - min.1 + 1),
+        ),
+        false => ((0, 0), Image::new(0, 0, [0; 4])),
+    }
+}
+
+/// Packs sprites tallest first into rows of a sheet roughly as wide as it is tall
+fn pack(sprites: &[Image], padding: u32) -> (Vec<Rect>, (u32, u32)) {
+    let area = sprites
+        .iter()
+        .map(|s| (s.width + padding) as u64 * (s.height + padding) as u64)
+        .sum::<u64>();
+    let widest = sprites.iter().map(|s| s.width).max().unwrap_or(0);
+    let width = ((area as f64).sqrt().ceil() as u32).max(widest);
+
+    let mut order = (0..sprites.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| (std::cmp::Reverse(sprites[*i].height), *i));
+
+    let mut rects = vec![Rect::default(); sprites.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    let mut size = (0, 0);
+    for i in order {
+        let (w, h) = (sprites[i].width, sprites[i].height);
+        if w == 0 || h == 0 {
+            continue;
+        }
+
+        if x > 0 && x + w > width {
+            x = 0;
+            y += row_height + padding;
+            row_height = 0;
+        }
+
+        rects[i] = Rect { x, y, w, h };
+        size = (size.0.max(x + w), size.1.max(y + h));
+        x += w + padding;
+        row_height = row_height.max(h);
+    }
+
+    (rects, size)
+}
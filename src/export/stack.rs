@@ -0,0 +1,96 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    animation::Animation,
+    models::model::Model,
+    utils::image::{color_to_rgba8, Image},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackLayout {
+    /// A PNG for every layer of every frame
+    Slices,
+    /// A PNG for every frame with its layers side by side, the bottom layer on the left
+    Strip,
+}
+
+/// Top down view of a layer, `x` going right and `z` going down, one pixel per voxel. Every slice
+/// of a model is the same size, empty voxels are see-through.
+pub fn slice(model: &Model, y: usize) -> Image {
+    let (w, _, d) = model.dimensions();
+    let mut out = Image::new(w as u32, d as u32, [0; 4]);
+    if let Some(layer) = model.value.get(y) {
+        for (x, row) in layer.value.iter().enumerate() {
+            for (z, voxel) in row.iter().enumerate() {
+                if voxel.filled {
+                    out.set(
+                        x as u32,
+                        z as u32,
+                        color_to_rgba8(voxel.material.color.into()),
+                    );
+                }
+            }
+        }
+    }
+
+    out
+}
+
+pub fn strip(model: &Model) -> Image {
+    let (w, h, d) = model.dimensions();
+    let mut out = Image::new((w * h) as u32, d as u32, [0; 4]);
+    for y in 0..h {
+        out.blit(&slice(model, y), (y * w) as u32, 0);
+    }
+
+    out
+}
+
+/// Writes the layers of every tick in `ticks` into `dir` for sprite stacking, returning the files
+/// written. Slices are named `{prefix}{tick}_{layer}.png` and strips `{prefix}{tick}.png`.
+pub fn write<P>(
+    animation: &Animation,
+    ticks: Range<u32>,
+    layout: StackLayout,
+    dir: P,
+    prefix: &str,
+) -> anyhow::Result<Vec<PathBuf>>
+where
+    P: AsRef<Path>,
+{
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Couldn't create {}", dir.as_ref().display()))?;
+
+    let mut out = vec![];
+    let duration = animation.duration();
+    for tick in ticks.start.min(duration)..ticks.end.min(duration) {
+        let model = match animation.sample(tick as f32) {
+            Some(m) => m,
+            None => continue,
+        };
+
+        match layout {
+            StackLayout::Slices => {
+                for y in 0..model.value.len() {
+                    let path = dir
+                        .as_ref()
+                        .join(format!("{}{:04}_{:02}.png", prefix, tick, y));
+                    super::sequence::write_png(&slice(&model, y), &path)?;
+                    out.push(path);
+                }
+            }
+            StackLayout::Strip => {
+                let path = super::sequence::frame_path(&dir, prefix, tick);
+                super::sequence::write_png(&strip(&model), &path)?;
+                out.push(path);
+            }
+        }
+    }
+
+    Ok(out)
+}
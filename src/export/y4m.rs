@@ -0,0 +1,96 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+
+use crate::{animation::Animation, utils::image::Image};
+
+use super::{ExportOptions, FrameRenderer};
+
+/// Writes the stream header. Frames are 4:2:0 in limited range BT.601, which every encoder reads.
+pub fn write_header<W>(out: &mut W, width: u32, height: u32, fps: f32) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    // Frame rates are fractions, keep three decimals of the animation's
+    let (num, den) = (((fps * 1000.0).round() as u32).max(1), 1000);
+    let divisor = gcd(num, den);
+    writeln!(
+        out,
+        "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg",
+        width,
+        height,
+        num / divisor,
+        den / divisor
+    )?;
+    Ok(())
+}
+
+/// Writes a frame, ignoring alpha
+pub fn write_frame<W>(out: &mut W, image: &Image) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let (w, h) = (image.width as usize, image.height as usize);
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+    let mut y_plane = Vec::with_capacity(w * h);
+    let mut u_plane = vec![0.0; cw * ch];
+    let mut v_plane = vec![0.0; cw * ch];
+    let mut counts = vec![0.0; cw * ch];
+
+    for (i, p) in image.pixels.iter().enumerate() {
+        let (x, y) = (i % w, i / w);
+        let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+        y_plane.push((16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0).round() as u8);
+
+        // Chroma is averaged over 2x2 blocks
+        let c = (y / 2) * cw + x / 2;
+        u_plane[c] += 128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0;
+        v_plane[c] += 128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0;
+        counts[c] += 1.0;
+    }
+
+    let chroma = |plane: Vec<f32>| {
+        plane
+            .iter()
+            .zip(&counts)
+            .map(|(v, n)| (v / n).round() as u8)
+            .collect::<Vec<_>>()
+    };
+
+    out.write_all(b"FRAME\n")?;
+    out.write_all(&y_plane)?;
+    out.write_all(&chroma(u_plane))?;
+    out.write_all(&chroma(v_plane))?;
+    Ok(())
+}
+
+/// Renders every exported tick into an uncompressed YUV4MPEG2 stream
+pub fn write<R, P>(
+    animation: &Animation,
+    renderer: &mut R,
+    options: &ExportOptions,
+    path: P,
+) -> anyhow::Result<()>
+where
+    R: FrameRenderer + ?Sized,
+    P: AsRef<Path>,
+{
+    let file = std::fs::File::create(&path)
+        .with_context(|| format!("Couldn't create {}", path.as_ref().display()))?;
+    let mut out = std::io::BufWriter::new(file);
+
+    write_header(&mut out, options.width, options.height, animation.fps)?;
+    super::render_frames(animation, renderer, options, |_, image| {
+        write_frame(&mut out, &image)
+    })?;
+
+    out.flush()?;
+    Ok(())
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    match b {
+        0 => a.max(1),
+        _ => gcd(b, a % b),
+    }
+}
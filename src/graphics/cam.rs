@@ -1,48 +1,195 @@
-use std::f32::consts::PI;
+use std::f32::consts::{PI, TAU};
 
-use cgmath::Matrix;
 use wgpu::util::DeviceExt;
 
-use crate::utils::consts::*;
+use crate::{animation::camera::CameraKey, utils::consts::*};
 
-#[derive(Debug, Clone, Copy)]
+use super::{input::InputMouseButton, navigation::NavAction};
+
+/// Orbits a target in mesh space. Everything the viewport, exports and path traced stills see
+/// comes from here.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Camera {
-    pub eye: cgmath::Point3<f32>,
-    pub target: cgmath::Point3<f32>,
-    pub up: cgmath::Vector3<f32>,
+    /// Point orbited, framed and zoomed towards, in mesh space
+    pub target: glam::Vec3,
+    /// From the target to the eye
+    pub distance: f32,
+    /// Around the vertical axis, turning from looking along -z at 0
+    pub yaw: f32,
+    /// Above the target's horizon. Past a quarter turn the eye goes over the pole and the view
+    /// turns upside down instead of snapping around.
+    pub pitch: f32,
     pub aspect: f32,
+    /// Vertical field of view in degrees
     pub fovy: f32,
     pub znear: f32,
     pub zfar: f32,
+    /// Perspective at 0 and orthographic at 1, anything between is part of a transition
+    pub orthographic: f32,
 }
 
 impl Camera {
-    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let view = self.get_view_matrix();
+    /// The view a camera key was made from
+    pub fn from_key(key: &CameraKey, aspect: f32) -> Self {
+        let mut camera = Self {
+            target: key.target,
+            distance: zoom_distance(key.zoom),
+            yaw: 0.0,
+            pitch: 0.0,
+            aspect,
+            fovy: key.fov,
+            znear: CAM_ZNEAR,
+            zfar: CAM_ZFAR,
+            orthographic: key.orthographic,
+        };
+        camera.look_from(key.eye - key.target);
+        camera
+    }
+
+    /// Current view as a camera key, with the eye a unit away from the target
+    pub fn to_key(&self, frame: u32) -> CameraKey {
+        CameraKey {
+            frame,
+            eye: self.target + self.direction(),
+            target: self.target,
+            fov: self.fovy,
+            zoom: distance_zoom(self.distance),
+            orthographic: self.orthographic,
+        }
+    }
+
+    /// Turns the eye to look from `direction`, keeping the distance
+    pub fn look_from(&mut self, direction: glam::Vec3) {
+        let direction = direction.normalize_or_zero();
+        if direction != glam::Vec3::ZERO {
+            self.yaw = direction.x.atan2(direction.z);
+            self.pitch = direction.y.clamp(-1.0, 1.0).asin();
+        }
+    }
+
+    /// Unit vector from the target to the eye
+    pub fn direction(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        glam::vec3(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw)
+    }
+
+    pub fn eye(&self) -> glam::Vec3 {
+        self.target + self.direction() * self.distance
+    }
+
+    /// Which way the pitch turns the eye, so it's never parallel to the view even at the poles
+    pub fn up(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        glam::vec3(-sin_pitch * sin_yaw, cos_pitch, -sin_pitch * cos_yaw)
+    }
+
+    pub fn right(&self) -> glam::Vec3 {
+        self.up().cross(self.direction())
+    }
+
+    /// Whether the eye has gone over a pole, where dragging sideways has to turn the other way
+    pub fn upside_down(&self) -> bool {
+        self.pitch.cos() < 0.0
+    }
+
+    pub fn build_view_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::look_at_rh(self.eye(), self.target, self.up())
+    }
+
+    pub fn build_projection_matrix(&self) -> glam::Mat4 {
+        let fovy = self.fovy.to_radians();
+        let perspective = glam::Mat4::perspective_rh(fovy, self.aspect, self.znear, self.zfar);
+        if self.orthographic <= 0.0 {
+            return perspective;
+        }
+
+        // As big as the perspective view is at the target, so switching keeps what's there in
+        // place. Nothing is clipped for being behind the eye.
+        let half_height = (fovy / 2.0).tan() * self.distance;
+        let half_width = half_height * self.aspect;
+        let ortho = glam::Mat4::orthographic_rh(
+            -half_width,
+            half_width,
+            -half_height,
+            half_height,
+            -self.zfar,
+            self.zfar,
+        );
+
+        let t = self.orthographic.min(1.0);
+        perspective * (1.0 - t) + ortho * t
+    }
+
+    pub fn build_view_projection_matrix(&self) -> glam::Mat4 {
+        self.build_projection_matrix() * self.build_view_matrix()
+    }
 
-        let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
+    /// Where a point in mesh space lands in a window of `size`, in pixels from the top left, or
+    /// None when it's behind the eye
+    pub fn project(&self, point: glam::Vec3, size: glam::Vec2) -> Option<glam::Vec2> {
+        let clip = self.build_view_projection_matrix() * point.extend(1.0);
+        if clip.w <= f32::EPSILON {
+            return None;
+        }
+
+        let ndc = clip.truncate().truncate() / clip.w;
+        Some(glam::vec2(ndc.x + 1.0, 1.0 - ndc.y) / 2.0 * size)
+    }
+
+    /// Direction that distance from the camera grows along
+    pub fn depth_axis(&self) -> glam::Vec3 {
+        -self.direction()
+    }
 
-        return OPENGL_TO_WGPU_MATRIX * proj * view;
+    /// Turns around the target, by radians
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = wrap_angle(self.yaw + yaw);
+        self.pitch = wrap_angle(self.pitch + pitch);
     }
 
-    pub fn get_view_matrix(&self) -> cgmath::Matrix4<f32> {
-        let eye = glam::vec3(self.eye.x, self.eye.y, self.eye.z);
+    /// Slides the target across the view, by fractions of the distance so it keeps up with the
+    /// screen however far away the camera is
+    pub fn pan(&mut self, x: f32, y: f32) {
+        self.target += (self.right() * x + self.up() * y) * self.distance;
+    }
 
-        let r = self.get_right();
-        let u = glam::vec3(self.up.x, self.up.y, self.up.z);
-        let f = self.get_forward();
+    /// Moves towards the target by zoom steps, away for negative steps
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = zoom_distance(distance_zoom(self.distance) + steps);
+    }
 
-        let tx = eye.dot(r);
-        let ty = eye.dot(u);
-        let tz = eye.dot(f);
+    /// Turns the view around the eye rather than the target, the way looking around in first
+    /// person does. Pitch stops short of the poles so the horizon stays level, though a view
+    /// already past them isn't snapped back.
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        let eye = self.eye();
+        let limit = FLY_PITCH_LIMIT.max(self.pitch.abs());
+        self.yaw = wrap_angle(self.yaw + yaw);
+        self.pitch = (self.pitch + pitch).clamp(-limit, limit);
+        self.target = eye - self.direction() * self.distance;
+    }
 
-        cgmath::Matrix4 {
-            x: cgmath::vec4(r.x, r.y, r.z, tx),
-            y: cgmath::vec4(u.x, u.y, u.z, ty),
-            z: cgmath::vec4(f.x, f.y, f.z, tz),
-            w: cgmath::vec4(0.0, 0.0, 0.0, 1.0),
+    /// Moves the eye and target together, `offset` being along the view's right, straight up and
+    /// the way the view faces
+    pub fn fly(&mut self, offset: glam::Vec3) {
+        self.target +=
+            self.right() * offset.x + glam::Vec3::Y * offset.y + self.depth_axis() * offset.z;
+    }
+
+    /// The same view moved so a box in mesh space fills it, whichever way it's turned
+    pub fn framing(&self, min: glam::Vec3, max: glam::Vec3) -> Self {
+        let radius = ((max - min).length() / 2.0).max(f32::EPSILON);
+        let half_fovy = self.fovy.to_radians() / 2.0;
+        let half_fovx = (half_fovy.tan() * self.aspect).atan();
+
+        Self {
+            target: (min + max) / 2.0,
+            distance: (radius * FRAME_MARGIN / half_fovy.min(half_fovx).sin())
+                .clamp(CAM_DISTANCE_MIN, CAM_DISTANCE_MAX),
+            ..*self
         }
-        .transpose()
     }
 
     pub fn create_staging_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
@@ -55,47 +202,108 @@ impl Camera {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_SRC,
         })
     }
+}
 
-    pub fn apply_transforms(&mut self, rot: &glam::Vec3) {
-        let rot = rot;
+/// How far the camera is from its target at a zoom level, each step in is `ZOOM_SENS` closer
+pub fn zoom_distance(zoom: f32) -> f32 {
+    (1.0 + ZOOM_SENS)
+        .powf(-zoom)
+        .clamp(CAM_DISTANCE_MIN, CAM_DISTANCE_MAX)
+}
 
-        let rotation = cgmath::point3(
-            (-rot.x).sin() * (rot.y).cos(),
-            -(rot.y).sin(),
-            (-rot.x).cos() * (rot.y).cos(),
-        );
+/// Zoom level the camera is at from how far it is from its target
+pub fn distance_zoom(distance: f32) -> f32 {
+    -distance.ln() / (1.0 + ZOOM_SENS).ln()
+}
+
+/// Into -PI to PI
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
 
-        self.eye = rotation;
+/// Views the numpad jumps to, looking at the target from along an axis or from a corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewPreset {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Isometric,
+}
 
-        self.rebuild_up(rot.y);
+impl ViewPreset {
+    /// Where the eye is from the target
+    pub fn direction(self) -> glam::Vec3 {
+        match self {
+            ViewPreset::Front => glam::Vec3::Z,
+            ViewPreset::Back => glam::Vec3::NEG_Z,
+            ViewPreset::Left => glam::Vec3::NEG_X,
+            ViewPreset::Right => glam::Vec3::X,
+            ViewPreset::Top => glam::Vec3::Y,
+            ViewPreset::Bottom => glam::Vec3::NEG_Y,
+            ViewPreset::Isometric => glam::Vec3::ONE,
+        }
     }
+}
 
-    pub fn rebuild_up(&mut self, yrot: f32) {
-        let forward = self.get_forward();
-        let temp_up = match yrot.abs() > (PI / 2.0) {
-            true => glam::vec3(0.0, -1.0, 0.0),
-            false => glam::vec3(0.0, 1.0, 0.0),
-        };
-        let right = forward.cross(temp_up).normalize();
-        let up = right.cross(forward).normalize();
-        self.up = cgmath::vec3(up.x, up.y, up.z);
+/// Eases the view to another instead of jumping, for presets, framing and bookmarks
+#[derive(Debug, Clone, Copy)]
+pub struct CamTransition {
+    from: Camera,
+    to: Camera,
+    elapsed: f32,
+}
+
+impl CamTransition {
+    pub fn new(from: Camera, to: Camera) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+        }
     }
 
-    pub fn get_forward(&self) -> glam::Vec3 {
-        (glam::vec3(self.target.x, self.target.y, self.target.z))
-            - (glam::vec3(self.eye.x, self.eye.y, self.eye.z).normalize())
+    /// View it ends on
+    pub fn target(&self) -> Camera {
+        self.to
     }
 
-    pub fn get_right(&self) -> glam::Vec3 {
-        let up = glam::vec3(self.up.x, self.up.y, self.up.z);
-        up.cross(self.get_forward()).normalize()
+    /// The view `delta_time` seconds further on, and whether it's finished
+    pub fn advance(&mut self, delta_time: f32) -> (Camera, bool) {
+        self.elapsed += delta_time;
+        let t = (self.elapsed / CAM_TRANSITION_TIME).clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+
+        // Angles turn whichever way around is shorter, and distance changes evenly in zoom steps
+        let (from, to) = (&self.from, &self.to);
+        let lerp = |a: f32, b: f32| a + (b - a) * eased;
+        let camera = Camera {
+            target: from.target.lerp(to.target, eased),
+            distance: zoom_distance(lerp(
+                distance_zoom(from.distance),
+                distance_zoom(to.distance),
+            )),
+            yaw: from.yaw + wrap_angle(to.yaw - from.yaw) * eased,
+            pitch: from.pitch + wrap_angle(to.pitch - from.pitch) * eased,
+            orthographic: lerp(from.orthographic, to.orthographic),
+            ..*to
+        };
+
+        (camera, t >= 1.0)
     }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct CamTemp {
-    pub button_held_last_frame: bool,
+    /// What the mouse drag in progress does, and the button holding it
+    pub drag: Option<(NavAction, InputMouseButton)>,
     pub cam_flipped: bool,
+    /// Moving with the keyboard and looking around with the cursor grabbed, instead of orbiting
+    pub flying: bool,
+    /// View the camera is easing towards
+    pub transition: Option<CamTransition>,
 }
 
 #[repr(C)]
@@ -106,13 +314,12 @@ pub struct CameraUniform {
 
 impl CameraUniform {
     pub fn new() -> Self {
-        use cgmath::SquareMatrix;
         Self {
-            view_proj: cgmath::Matrix4::identity().into(),
+            view_proj: glam::Mat4::IDENTITY.to_cols_array_2d(),
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view_proj = camera.build_view_projection_matrix().into();
+        self.view_proj = camera.build_view_projection_matrix().to_cols_array_2d();
     }
 }
@@ -6,17 +6,34 @@ pub fn create_depth_texture(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
     label: &str,
+) -> super::texture::Texture {
+    create_depth_texture_sized(
+        device,
+        config.width,
+        config.height,
+        WgpuObject::SAMPLE_COUNT,
+        label,
+    )
+}
+
+/// For depth that isn't drawn at the window's size, like shadow maps
+pub fn create_depth_texture_sized(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+    label: &str,
 ) -> super::texture::Texture {
     let size = wgpu::Extent3d {
-        width: config.width,
-        height: config.height,
+        width,
+        height,
         depth_or_array_layers: 1,
     };
     let desc = wgpu::TextureDescriptor {
         label: Some(label),
         size,
         mip_level_count: 1,
-        sample_count: WgpuObject::SAMPLE_COUNT,
+        sample_count,
         dimension: wgpu::TextureDimension::D2,
         format: DEPTH_FORMAT,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -0,0 +1,76 @@
+/// Width and height of a glyph in font pixels
+pub const GLYPH_SIZE: (usize, usize) = (3, 5);
+
+// 3x5 bitmap font, each glyph is a row per string from the top
+#[rustfmt::skip]
+const GLYPHS: &[(char, [&str; 5])] = &[
+    ('A', ["###", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', ["###", "#..", "#..", "#..", "###"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', ["###", "#..", "#.#", "#.#", "###"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", "###"]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#.#", "###", "###", "#.#", "#.#"]),
+    ('N', ["##.", "#.#", "#.#", "#.#", "#.#"]),
+    ('O', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('P', ["###", "#.#", "###", "#..", "#.."]),
+    ('Q', ["###", "#.#", "#.#", "###", "..#"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', ["###", "#..", "###", "..#", "###"]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", "#.#", ".#."]),
+    ('W', ["#.#", "#.#", "###", "###", "#.#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["###", "..#", "###", "#..", "###"]),
+    ('3', ["###", "..#", ".##", "..#", "###"]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "###", "..#", "###"]),
+    ('6', ["###", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", "..#", "..#", "..#"]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "###"]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('_', ["...", "...", "...", "...", "###"]),
+    ('.', ["...", "...", "...", "...", ".#."]),
+    (':', ["...", ".#.", "...", ".#.", "..."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    ('?', ["###", "..#", ".##", "...", ".#."]),
+];
+
+/// Positions of the lit pixels of a character, lowercase letters use the uppercase glyph and
+/// unknown characters draw as `?`
+pub fn glyph_pixels(c: char) -> Vec<(usize, usize)> {
+    let c = c.to_ascii_uppercase();
+    if c == ' ' {
+        return vec![];
+    }
+
+    let rows = GLYPHS
+        .iter()
+        .find(|(g, _)| *g == c)
+        .or_else(|| GLYPHS.iter().find(|(g, _)| *g == '?'))
+        .map(|(_, rows)| rows)
+        .expect("Font has no fallback glyph");
+
+    let mut out = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, pixel) in row.chars().enumerate() {
+            if pixel == '#' {
+                out.push((x, y));
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,213 @@
+use glam::{vec2, vec3};
+
+use crate::utils::consts::*;
+
+use super::{cam::Camera, lines::Line, overlay::OverlayRenderer};
+
+const LABEL_SCALE: f32 = 2.0;
+
+const GRID_MINOR_COLOR: [f32; 4] = [0.6, 0.6, 0.65, 0.25];
+const GRID_MAJOR_COLOR: [f32; 4] = [0.75, 0.75, 0.8, 0.5];
+const BOUNDS_COLOR: [f32; 4] = [1.0, 0.8, 0.3, 1.0];
+const LABEL_BACKGROUND_COLOR: [f32; 4] = [0.08, 0.08, 0.1, 0.7];
+const AXIS_COLORS: [[f32; 4]; 3] = [
+    [0.95, 0.3, 0.3, 1.0],
+    [0.4, 0.85, 0.35, 1.0],
+    [0.3, 0.5, 1.0, 1.0],
+];
+const AXIS_NAMES: [&str; 3] = ["X", "Y", "Z"];
+
+// Between the model's lowest faces and the floor catching shadows, so neither hides the grid
+const GRID_HEIGHT: f32 = -1.0 - 5e-4;
+// Pieces each grid line is split into, so it can fade out towards its ends
+const GRID_SEGMENTS: usize = 16;
+
+/// Reference drawing around the model, each switched with its own key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuideSettings {
+    /// Grid on the ground under the model, fading out into the distance
+    pub grid: bool,
+    /// Which way the axes point, in the corner of the viewport
+    pub gizmo: bool,
+    /// Box around the model with how many voxels it spans along each axis
+    pub bounds: bool,
+    /// Every vertex's normal, for debugging meshing
+    pub normals: bool,
+}
+
+impl Default for GuideSettings {
+    fn default() -> Self {
+        Self {
+            grid: true,
+            gizmo: true,
+            bounds: false,
+            normals: false,
+        }
+    }
+}
+
+/// Lines of the ground grid around what the camera looks at, lined up with the voxels, which are
+/// `voxel` wide in mesh space. Lines through the model's origin are colored for the axis they
+/// run along.
+pub fn grid_lines(camera: &Camera, voxel: f32) -> Vec<Line> {
+    let reach = (camera.distance * GRID_REACH).max(voxel);
+    let mut spacing = voxel;
+    while reach / spacing > GRID_LINES_MAX as f32 {
+        spacing *= GRID_MAJOR_EVERY as f32;
+    }
+
+    // Lines stay put in the world as the view moves, only fading in and out around the middle
+    let middle = vec2(camera.target.x, camera.target.z);
+    let count = (reach / spacing).ceil() as i64;
+    let nearest = ((middle + 1.0) / spacing).round();
+    let fade = |pos: glam::Vec2| (1.0 - pos.distance(middle) / reach).clamp(0.0, 1.0).powi(2);
+
+    let mut out = vec![];
+    for axis in [0, 2] {
+        // Lines running along the other axis, one for each step across this one
+        let (across, along) = match axis {
+            0 => (nearest.x as i64, nearest.y as i64),
+            _ => (nearest.y as i64, nearest.x as i64),
+        };
+        let start = -1.0 + (along - count) as f32 * spacing;
+        let length = (count * 2) as f32 * spacing;
+
+        for i in (across - count)..=(across + count) {
+            let offset = -1.0 + i as f32 * spacing;
+            let color = match i {
+                0 => AXIS_COLORS[2 - axis],
+                i if i % GRID_MAJOR_EVERY as i64 == 0 => GRID_MAJOR_COLOR,
+                _ => GRID_MINOR_COLOR,
+            };
+
+            for s in 0..GRID_SEGMENTS {
+                let t0 = start + length * s as f32 / GRID_SEGMENTS as f32;
+                let t1 = start + length * (s + 1) as f32 / GRID_SEGMENTS as f32;
+                let point = |t: f32| match axis {
+                    0 => vec2(offset, t),
+                    _ => vec2(t, offset),
+                };
+
+                let alpha = color[3] * fade((point(t0) + point(t1)) / 2.0);
+                if alpha <= 0.0 {
+                    continue;
+                }
+                let (p0, p1) = (point(t0), point(t1));
+                out.push(Line {
+                    pos0: vec3(p0.x, GRID_HEIGHT, p0.y),
+                    pos1: vec3(p1.x, GRID_HEIGHT, p1.y),
+                    color: [color[0], color[1], color[2], alpha],
+                });
+            }
+        }
+    }
+
+    out
+}
+
+/// The twelve edges of a box
+pub fn box_lines(min: glam::Vec3, max: glam::Vec3) -> Vec<Line> {
+    let corner = |i: usize| {
+        vec3(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    };
+
+    let mut out = vec![];
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                out.push(Line {
+                    pos0: corner(i),
+                    pos1: corner(i | bit),
+                    color: BOUNDS_COLOR,
+                });
+            }
+        }
+    }
+
+    out
+}
+
+/// How many voxels a box in mesh space spans along each axis
+pub fn box_dimensions(min: glam::Vec3, max: glam::Vec3, voxel: f32) -> [u32; 3] {
+    ((max - min) / voxel).round().as_uvec3().to_array()
+}
+
+/// Labels an edge of a box along each axis with how many voxels long it is, in a window of `size`
+pub fn draw_dimensions(
+    overlay: &mut OverlayRenderer,
+    camera: &Camera,
+    size: glam::Vec2,
+    (min, max): (glam::Vec3, glam::Vec3),
+    voxel: f32,
+) {
+    let dimensions = box_dimensions(min, max, voxel);
+    let middle = (min + max) / 2.0;
+
+    // Along the bottom front and up the front right, where they're easiest to find
+    let edges = [
+        vec3(middle.x, min.y, max.z),
+        vec3(max.x, middle.y, max.z),
+        vec3(max.x, min.y, middle.z),
+    ];
+    for axis in 0..3 {
+        let Some(pos) = camera.project(edges[axis], size) else {
+            continue;
+        };
+
+        let text = dimensions[axis].to_string();
+        let text_size = OverlayRenderer::text_size(&text, LABEL_SCALE);
+        let pos = (pos - text_size / 2.0).floor();
+        overlay.draw_rect(pos - 2.0, pos + text_size + 2.0, LABEL_BACKGROUND_COLOR);
+        overlay.draw_text(pos, &text, LABEL_SCALE, AXIS_COLORS[axis]);
+    }
+}
+
+/// Which way each axis points on the screen from the camera, and how far it points out of it
+pub fn gizmo_axes(camera: &Camera) -> [(glam::Vec2, f32); 3] {
+    let view = camera.build_view_matrix();
+    [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z].map(|axis| {
+        let dir = view.transform_vector3(axis);
+        (vec2(dir.x, -dir.y), dir.z)
+    })
+}
+
+/// Which way the axes point from the camera, in the top right corner of a window of `size`
+pub fn draw_gizmo(overlay: &mut OverlayRenderer, camera: &Camera, size: glam::Vec2) {
+    let center = vec2(
+        size.x - GIZMO_MARGIN - GIZMO_SIZE,
+        GIZMO_MARGIN + GIZMO_SIZE,
+    );
+    overlay.draw_rect(
+        center - GIZMO_SIZE - 4.0,
+        center + GIZMO_SIZE + 4.0,
+        LABEL_BACKGROUND_COLOR,
+    );
+
+    // Farthest first so the axes pointing out of the screen are drawn over the rest. The negative
+    // ends are dimmer dots.
+    let axes = gizmo_axes(camera);
+    let mut ends: Vec<(usize, f32)> = (0..3).flat_map(|i| [(i, 1.0), (i, -1.0)]).collect();
+    ends.sort_by(|a, b| (axes[a.0].1 * a.1).total_cmp(&(axes[b.0].1 * b.1)));
+
+    let label_size = OverlayRenderer::text_size("X", LABEL_SCALE);
+    for (axis, sign) in ends {
+        let tip = center + axes[axis].0 * sign * (GIZMO_SIZE - label_size.y);
+        let color = AXIS_COLORS[axis];
+        match sign > 0.0 {
+            true => {
+                overlay.draw_line(center, tip, 2.0, color);
+                let pos = (tip - label_size / 2.0).floor();
+                overlay.draw_rect(pos - 1.0, pos + label_size + 1.0, LABEL_BACKGROUND_COLOR);
+                overlay.draw_text(pos, AXIS_NAMES[axis], LABEL_SCALE, color);
+            }
+            false => {
+                let dim = [color[0] * 0.5, color[1] * 0.5, color[2] * 0.5, 1.0];
+                overlay.draw_rect(tip - 3.0, tip + 3.0, dim);
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::Context;
+
+use crate::{
+    animation::camera::CameraKey,
+    export::{ExportOptions, FrameRenderer},
+    models::{material::Material, model::Model, regen},
+    utils::{
+        consts::DEPTH_FORMAT,
+        image::{color_to_rgba8, linear_to_srgb, srgb_to_linear, Image},
+    },
+};
+
+use super::{
+    cam, init, msaa,
+    outline::{OutlineSettings, Outlines},
+    shadow::{ShadowMap, ShadowSettings},
+    vertex,
+    wgpu_object::WgpuObject,
+};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Renders models offscreen with the same pipeline as the viewport, for exports
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    bind_group_layout: wgpu::BindGroupLayout,
+    shadow_map: ShadowMap,
+    outlines: Outlines,
+    sample_count: u32,
+    pipeline: wgpu::RenderPipeline,
+    translucent_pipeline: wgpu::RenderPipeline,
+    /// Kept between frames, since exports render many at the same size
+    targets: Option<Targets>,
+}
+
+/// What a frame is drawn into and read back from, which only depends on its size
+struct Targets {
+    size: (u32, u32),
+    depth_view: wgpu::TextureView,
+    msaa_buffer: wgpu::TextureView,
+    target: wgpu::Texture,
+    target_view: wgpu::TextureView,
+    readback: wgpu::Buffer,
+    /// Bytes per row of the readback, which texture copies have to align
+    padded: u32,
+}
+
+impl Targets {
+    fn new(device: &wgpu::Device, width: u32, height: u32, sample_count: u32) -> Self {
+        let config = target_config(width, height);
+
+        // Not bound for sampling like the viewport's, which also keeps multisampled resolves
+        // working on the GL backend
+        let depth_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Headless Depth"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let msaa_buffer = msaa::create_multisampled_framebuffer(device, &config, sample_count);
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let padded = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless Readback"),
+            size: (padded * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            size: (width, height),
+            depth_view,
+            msaa_buffer,
+            target,
+            target_view,
+            readback,
+            padded,
+        }
+    }
+}
+
+/// Only used to size and format the targets and pipelines, nothing is presented
+fn target_config(width: u32, height: u32) -> wgpu::SurfaceConfiguration {
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: FORMAT,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: vec![FORMAT],
+        desired_maximum_frame_latency: 2,
+    }
+}
+
+impl HeadlessRenderer {
+    pub async fn new() -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .context("Unable to create rendering adapter")?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("headless_device"),
+                    required_features: adapter.features(),
+                    required_limits: wgpu::Limits::downlevel_defaults(),
+                },
+                None,
+            )
+            .await?;
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/main.wgsl"));
+        let bind_group_layout = init::create_uniform_bind_group_layout(&device);
+        let shadow_map = ShadowMap::new(&device, ShadowSettings::default());
+        let outlines = Outlines::new(&device, OutlineSettings::default());
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("HeadlessPipelineLayout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &shadow_map.bind_group_layout,
+                &outlines.bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        // Software adapters often can't do as many samples as the viewport asks for
+        let supported = adapter.get_texture_format_features(FORMAT).flags;
+        let sample_count = [WgpuObject::SAMPLE_COUNT, 4, 2, 1]
+            .into_iter()
+            .find(|c| *c <= WgpuObject::SAMPLE_COUNT && supported.sample_count_supported(*c))
+            .unwrap_or(1);
+
+        let config = target_config(1, 1);
+        let pipeline = init::create_render_pipeline_with_samples(
+            &device,
+            &pipeline_layout,
+            &shader,
+            &config,
+            false,
+            sample_count,
+        );
+        let translucent_pipeline = init::create_translucent_pipeline(
+            &device,
+            &pipeline_layout,
+            &shader,
+            &config,
+            false,
+            sample_count,
+        );
+
+        Ok(Self {
+            device,
+            queue,
+            bind_group_layout,
+            shadow_map,
+            outlines,
+            sample_count,
+            pipeline,
+            translucent_pipeline,
+            targets: None,
+        })
+    }
+
+    /// For exports started from the viewport, which keeps its own device for the window
+    pub fn new_blocking() -> anyhow::Result<Self> {
+        pollster::block_on(Self::new())
+    }
+
+    /// Exports from the viewport draw shadows the way it does
+    pub fn set_shadows(&mut self, settings: ShadowSettings) {
+        self.shadow_map
+            .set_settings(&self.device, &self.queue, settings);
+    }
+
+    /// Exports outline voxel edges like the viewport, though silhouettes are only thickened
+    /// there since they come from its post-processing
+    pub fn set_outlines(&mut self, settings: OutlineSettings) {
+        self.outlines.set_settings(&self.queue, settings);
+    }
+}
+
+impl FrameRenderer for HeadlessRenderer {
+    fn render(
+        &mut self,
+        model: &Model,
+        camera: &CameraKey,
+        options: &ExportOptions,
+    ) -> anyhow::Result<Image> {
+        let (width, height) = (options.width.max(1), options.height.max(1));
+        if self.targets.as_ref().map(|t| t.size) != Some((width, height)) {
+            self.targets = Some(Targets::new(&self.device, width, height, self.sample_count));
+        }
+        let targets = self.targets.as_ref().context("No render targets")?;
+
+        // Same camera setup as the viewport
+        let cam = cam::Camera::from_key(camera, width as f32 / height as f32);
+        let cam_buf = cam.create_staging_buffer(&self.device);
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("HeadlessBindGroup"),
+            layout: &self.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: cam_buf.as_entire_binding(),
+            }],
+        });
+
+        self.outlines.set_model(&self.queue, model);
+        let mut mesh = regen::gen_mesh(model);
+        mesh.sort_translucent(cam.depth_axis());
+        let buffers = vertex::create_buffers(&mesh, &self.device, false);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless_encoder"),
+            });
+
+        // Only solid faces cast shadows
+        self.shadow_map.render(
+            &mut encoder,
+            &buffers.vbo,
+            &buffers.idxbuf,
+            buffers.opaque_size,
+        );
+
+        {
+            // The target is sRGB, so the clear color has to be linear to come out as given
+            let [r, g, b, _] = options.background.map(|c| srgb_to_linear(c) as f64);
+            let a = options.background[3] as f64;
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("HeadlessRenderPass"),
+                color_attachments: &[Some(match self.sample_count {
+                    1 => wgpu::RenderPassColorAttachment {
+                        view: &targets.target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    },
+                    _ => wgpu::RenderPassColorAttachment {
+                        view: &targets.msaa_buffer,
+                        resolve_target: Some(&targets.target_view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+                            store: wgpu::StoreOp::Discard,
+                        },
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &targets.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            // Empty frames only need the background
+            if buffers.idx_size > 0 {
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.set_bind_group(1, &self.shadow_map.bind_group, &[]);
+                render_pass.set_bind_group(2, &self.outlines.bind_group, &[]);
+                render_pass.set_vertex_buffer(0, buffers.vbo.slice(..));
+                render_pass.set_index_buffer(buffers.idxbuf.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..buffers.opaque_size, 0, 0..1);
+                self.shadow_map.draw_ground(&mut render_pass);
+
+                render_pass.set_pipeline(&self.translucent_pipeline);
+                render_pass.set_vertex_buffer(0, buffers.vbo.slice(..));
+                render_pass.set_index_buffer(buffers.idxbuf.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(buffers.opaque_size..buffers.idx_size, 0, 0..1);
+            }
+        }
+
+        encoder.copy_texture_to_buffer(
+            targets.target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &targets.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(targets.padded),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = targets.readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = sender.send(r);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        let unpadded = width as usize * 4;
+        let mut bytes = Vec::with_capacity(unpadded * height as usize);
+        for row in slice.get_mapped_range().chunks(targets.padded as usize) {
+            bytes.extend_from_slice(&row[..unpadded]);
+        }
+        targets.readback.unmap();
+
+        Image::from_rgba(width, height, &bytes).context("Readback was the wrong size")
+    }
+
+    /// Vertex colors are written as linear, so the target encodes them
+    fn material_color(&self, material: &Material) -> [u8; 4] {
+        let [r, g, b, _] = material.color.to_array().map(linear_to_srgb);
+        color_to_rgba8([r, g, b, 1.0])
+    }
+}
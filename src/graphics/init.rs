@@ -1,14 +1,15 @@
 use wgpu::{util::DeviceExt, Backends, FragmentState, Limits, TextureFormat, VertexState};
 
 use super::{
-    cam, lines, msaa, transform,
+    cam, lines, msaa, navigation, outline, overlay, post, shadow,
     vertex::{self},
     wgpu_object::WgpuObject,
 };
 
 use crate::{
+    animation::Animation,
     models::{model, regen},
-    utils::{self, consts::*, log},
+    utils::{consts::*, log},
 };
 
 pub async fn gfx_init(window: &winit::window::Window) -> WgpuObject {
@@ -71,30 +72,35 @@ pub async fn gfx_init(window: &winit::window::Window) -> WgpuObject {
 
     surface.configure(&device, &config);
 
-    let depth_texture = super::depth::create_depth_texture(&device, &config, "depth_texture");
+    // The scene is drawn in HDR and post-processed onto the surface
+    let post_settings = post::PostSettings::default();
+    let sample_count = post_settings.sample_count();
+    let scene_config = post::scene_config(&config);
 
-    let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/main.wgsl"));
+    let depth_texture = super::depth::create_depth_texture_sized(
+        &device,
+        config.width,
+        config.height,
+        sample_count,
+        "depth_texture",
+    );
 
-    let vertex_index_buffer =
-        vertex::create_buffers(regen::gen_vert_idx(&model::get_model()), &device, wireframe);
+    let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/main.wgsl"));
 
-    let rotation = glam::Vec3::ZERO;
+    let animation = Animation::new("Animation", vec![model::get_model()]);
+    let model = animation.sample(0.0).expect("Animation has no frames");
 
-    let mut camera = cam::Camera {
-        eye: (-2.0, 1.0, 2.0).into(),
-        target: (0.0, 0.0, 0.0).into(),
-        up: cgmath::Vector3::unit_y(),
-        aspect: config.width as f32 / config.height as f32,
-        fovy: 45.0,
-        znear: 0.1,
-        zfar: 100.0,
-    };
+    let mesh = regen::gen_mesh(&model);
+    let vertex_index_buffer = vertex::create_buffers(&mesh, &device, wireframe);
 
-    camera.eye = cgmath::point3(
-        -(-rotation.x).sin() * (rotation.y).cos(),
-        -(rotation.y).sin(),
-        (-rotation.x).cos() * (rotation.y).cos(),
+    // Starts with the whole model in view
+    let mut camera = cam::Camera::from_key(
+        &Default::default(),
+        config.width as f32 / config.height as f32,
     );
+    if let Some((min, max)) = model.mesh_bounds(|_| true) {
+        camera = camera.framing(min, max);
+    }
 
     let mut camera_uniform = cam::CameraUniform::new();
     camera_uniform.update_view_proj(&camera);
@@ -105,83 +111,75 @@ pub async fn gfx_init(window: &winit::window::Window) -> WgpuObject {
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
     });
 
-    let transform_uniform = transform::TransformUniform::default();
-    let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("transform Buffer"),
-        contents: &utils::uniform_buffer_to_bytes(transform_uniform),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-    let transform_staging_buf = transform_uniform.create_staging_buffer(&device);
-
-    let uniform_bind_group_layout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("UniformBindGroupLayout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
+    let uniform_bind_group_layout = create_uniform_bind_group_layout(&device);
 
     let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("UniformBindGroup"),
         layout: &uniform_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: transform_buffer.as_entire_binding(),
-            },
-        ],
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
     });
 
+    let shadow_map = shadow::ShadowMap::new(&device, Default::default());
+    let mut outlines = outline::Outlines::new(&device, Default::default());
+    outlines.set_model(&queue, &model);
+
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("RenderPipelineLayout"),
-        bind_group_layouts: &[&uniform_bind_group_layout],
+        bind_group_layouts: &[
+            &uniform_bind_group_layout,
+            &shadow_map.bind_group_layout,
+            &outlines.bind_group_layout,
+        ],
         push_constant_ranges: &[],
     });
 
-    let render_pipeline = create_render_pipeline(
+    let render_pipeline = create_render_pipeline_with_samples(
         &device,
         &render_pipeline_layout,
         &shader,
-        &config,
+        &scene_config,
         wireframe,
+        sample_count,
     );
 
-    let msaa_buffer =
-        msaa::create_multisampled_framebuffer(&device, &config, WgpuObject::SAMPLE_COUNT);
-
-    let msaa_bundle = msaa::create_bundle(
+    let translucent_pipeline = create_translucent_pipeline(
         &device,
-        &config,
-        &render_pipeline,
-        &vertex_index_buffer.vbo,
-        &vertex_index_buffer.idxbuf,
-        vertex_index_buffer.idx_size,
+        &render_pipeline_layout,
+        &shader,
+        &scene_config,
+        wireframe,
+        sample_count,
     );
 
-    let line_rendering = lines::LineRendering::new(&device, &config, &uniform_bind_group_layout);
+    let msaa_buffer = msaa::create_multisampled_framebuffer(&device, &scene_config, sample_count);
+
+    let mut line_rendering =
+        lines::LineRendering::new(&device, &scene_config, &uniform_bind_group_layout);
+    line_rendering.rebuild_pipelines(&device, &scene_config, sample_count);
+
+    let mut post = post::PostProcessor::new(&device, &config, &depth_texture.view, post_settings);
+    post.set_camera(&queue, &camera);
+    post.set_outlines(&queue, &outlines.settings);
+
+    let overlay = overlay::OverlayRenderer::new(&device, &config);
+
+    // Custom bindings replace the default preset when there are any
+    let custom_navigation = match std::path::Path::new(NAVIGATION_PATH).exists() {
+        true => match navigation::NavigationBindings::load(NAVIGATION_PATH) {
+            Ok(bindings) => {
+                log::log(format!("Loaded {}", NAVIGATION_PATH), log::LogLevel::INFO);
+                Some(bindings)
+            }
+            Err(e) => {
+                log::log(format!("{:#}", e), log::LogLevel::ERROR);
+                None
+            }
+        },
+        false => None,
+    };
 
     let mut out = WgpuObject {
         surface,
@@ -191,30 +189,48 @@ pub async fn gfx_init(window: &winit::window::Window) -> WgpuObject {
         size,
         window: window,
         pipeline: render_pipeline,
+        translucent_pipeline,
         pipeline_layout: render_pipeline_layout,
         shader,
         vertex_buffer: vertex_index_buffer.vbo,
         vertex_buffer_size: vertex_index_buffer.vbo_size,
         index_buffer: vertex_index_buffer.idxbuf,
         index_buffer_size: vertex_index_buffer.idx_size,
+        opaque_index_size: vertex_index_buffer.opaque_size,
+        shadow_index_buffer: vertex_index_buffer.shadow_idxbuf,
+        shadow_index_size: vertex_index_buffer.shadow_size,
+        mesh,
+        sorted_depth_axis: None,
         cam: camera,
         cam_buf: camera_buffer,
         cam_staging_buf: None,
         cam_uniform: camera_uniform,
-        transform_uniform,
-        transform_buf: transform_buffer,
-        transform_staging_buf: Some(transform_staging_buf),
-        restage_transform: false,
         uniform_bind_group,
         msaa_buffer,
-        msaa_bundle,
         depth_texture,
+        shadow_map,
+        outlines,
+        post,
         wireframe,
         delta_time: 0.0,
-        cam_rotation: rotation,
-        cam_pos: glam::Vec3::ZERO,
         cam_temp: Default::default(),
+        navigation: custom_navigation.clone().unwrap_or_default(),
+        navigation_preset: match custom_navigation {
+            Some(_) => navigation::NavigationPreset::Custom,
+            None => Default::default(),
+        },
+        custom_navigation,
         line_rendering,
+        guides: Default::default(),
+        animation,
+        playback: Default::default(),
+        active_track: 0,
+        model,
+        title: String::new(),
+        overlay,
+        timeline: Default::default(),
+        camera_preview: false,
+        still: None,
     };
 
     out.update();
@@ -228,9 +244,124 @@ pub fn create_render_pipeline<'a>(
     shader: &wgpu::ShaderModule,
     config: &wgpu::SurfaceConfiguration,
     wireframe: bool,
+) -> wgpu::RenderPipeline {
+    create_render_pipeline_with_samples(
+        device,
+        render_pipeline_layout,
+        shader,
+        config,
+        wireframe,
+        WgpuObject::SAMPLE_COUNT,
+    )
+}
+
+/// For targets that can't use the viewport's sample count
+pub fn create_render_pipeline_with_samples(
+    device: &wgpu::Device,
+    render_pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    config: &wgpu::SurfaceConfiguration,
+    wireframe: bool,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    create_pipeline(
+        device,
+        render_pipeline_layout,
+        shader,
+        config,
+        Primitives::faces(wireframe),
+        sample_count,
+        false,
+    )
+}
+
+/// Debug and guide lines, blended like translucent faces when `translucent` is set
+pub fn create_line_pipeline(
+    device: &wgpu::Device,
+    render_pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+    translucent: bool,
+) -> wgpu::RenderPipeline {
+    create_pipeline(
+        device,
+        render_pipeline_layout,
+        shader,
+        config,
+        Primitives::Lines,
+        sample_count,
+        translucent,
+    )
+}
+
+/// Blends translucent faces over what's already drawn, without hiding what's behind them
+pub fn create_translucent_pipeline(
+    device: &wgpu::Device,
+    render_pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    config: &wgpu::SurfaceConfiguration,
+    wireframe: bool,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    create_pipeline(
+        device,
+        render_pipeline_layout,
+        shader,
+        config,
+        Primitives::faces(wireframe),
+        sample_count,
+        true,
+    )
+}
+
+/// What a pipeline draws, which picks its topology and fragment shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitives {
+    Faces,
+    /// Model faces as lines, drawn without outlines which would cover the whole line
+    Wireframe,
+    /// Line lists from the line shader, which only has the plain fragment shader
+    Lines,
+}
+
+impl Primitives {
+    fn faces(wireframe: bool) -> Self {
+        match wireframe {
+            true => Primitives::Wireframe,
+            false => Primitives::Faces,
+        }
+    }
+
+    fn topology(self) -> wgpu::PrimitiveTopology {
+        match self {
+            Primitives::Faces => wgpu::PrimitiveTopology::TriangleList,
+            Primitives::Wireframe | Primitives::Lines => wgpu::PrimitiveTopology::LineList,
+        }
+    }
+
+    fn fragment_entry(self) -> &'static str {
+        match self {
+            Primitives::Wireframe => "fs_wireframe",
+            Primitives::Faces | Primitives::Lines => "fs_main",
+        }
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    render_pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    config: &wgpu::SurfaceConfiguration,
+    primitives: Primitives,
+    sample_count: u32,
+    translucent: bool,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("RenderPipeline"),
+        label: Some(match translucent {
+            true => "TranslucentRenderPipeline",
+            false => "RenderPipeline",
+        }),
         layout: Some(&render_pipeline_layout),
         vertex: VertexState {
             module: &shader,
@@ -239,14 +370,18 @@ pub fn create_render_pipeline<'a>(
         },
         fragment: Some(FragmentState {
             module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(config.view_formats[0].into())],
+            entry_point: primitives.fragment_entry(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.view_formats[0],
+                blend: match translucent {
+                    true => Some(wgpu::BlendState::ALPHA_BLENDING),
+                    false => None,
+                },
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
         }),
         primitive: wgpu::PrimitiveState {
-            topology: match wireframe {
-                true => wgpu::PrimitiveTopology::LineList,
-                false => wgpu::PrimitiveTopology::TriangleList,
-            },
+            topology: primitives.topology(),
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
             // cull_mode: Some(wgpu::Face::Back),
@@ -257,16 +392,33 @@ pub fn create_render_pipeline<'a>(
         },
         depth_stencil: Some(wgpu::DepthStencilState {
             format: DEPTH_FORMAT,
-            depth_write_enabled: true,
+            depth_write_enabled: !translucent,
             depth_compare: wgpu::CompareFunction::LessEqual,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
         multisample: wgpu::MultisampleState {
-            count: WgpuObject::SAMPLE_COUNT,
+            count: sample_count,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
         multiview: None,
     })
 }
+
+/// Camera uniform, shared by every pipeline drawing the model
+pub fn create_uniform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("UniformBindGroupLayout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
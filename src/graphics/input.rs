@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, MouseScrollDelta},
@@ -6,11 +7,14 @@ use winit::{
 
 use crate::utils::log;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InputMouseButton {
     Left,
     Right,
     Middle,
+    Back,
+    Forward,
+    Other(u16),
 }
 
 // I know this looks terrible, but I promise it's safe
@@ -26,6 +30,9 @@ static mut LAST_MOUSE_POS: PhysicalPosition<f64> = PhysicalPosition::new(0.0, 0.
 
 static mut MOUSE_SCROLL_LAST: f32 = 0.0;
 
+// Raw movement, which keeps coming while the cursor is grabbed and can't move
+static mut MOUSE_MOTION: (f64, f64) = (0.0, 0.0);
+
 static mut SHIFT_DOWN: bool = false;
 static mut CTRL_DOWN: bool = false;
 static mut ALT_DOWN: bool = false;
@@ -101,19 +108,15 @@ pub fn poll_keyboard_event(event: &winit::event::KeyEvent) {
 }
 
 pub fn poll_mousebutton_event(event: &winit::event::MouseButton, state: &ElementState) {
-    match event {
-        winit::event::MouseButton::Left => mousebutton_ops(InputMouseButton::Left, state),
-        winit::event::MouseButton::Right => mousebutton_ops(InputMouseButton::Right, state),
-        winit::event::MouseButton::Middle => mousebutton_ops(InputMouseButton::Middle, state),
-        winit::event::MouseButton::Back => todo!(),
-        winit::event::MouseButton::Forward => todo!(),
-        winit::event::MouseButton::Other(_) => {
-            log::log(
-                "Certain mouse buttons are currently unsupported",
-                log::LogLevel::WARNING,
-            );
-        }
-    }
+    let button = match event {
+        winit::event::MouseButton::Left => InputMouseButton::Left,
+        winit::event::MouseButton::Right => InputMouseButton::Right,
+        winit::event::MouseButton::Middle => InputMouseButton::Middle,
+        winit::event::MouseButton::Back => InputMouseButton::Back,
+        winit::event::MouseButton::Forward => InputMouseButton::Forward,
+        winit::event::MouseButton::Other(b) => InputMouseButton::Other(*b),
+    };
+    mousebutton_ops(button, state);
 }
 
 pub fn poll_mouse_move_event(position: &winit::dpi::PhysicalPosition<f64>) {
@@ -129,6 +132,13 @@ pub fn poll_scroll_wheel(delta: &MouseScrollDelta) {
     }
 }
 
+pub fn poll_mouse_motion(delta: &(f64, f64)) {
+    unsafe {
+        MOUSE_MOTION.0 += delta.0;
+        MOUSE_MOTION.1 += delta.1;
+    }
+}
+
 pub fn reset_scroll_wheel() {
     unsafe { MOUSE_SCROLL_LAST = 0.0 }
 }
@@ -137,6 +147,7 @@ pub fn input_update() {
     unsafe { LAST_MOUSE_STATE = MOUSE_STATE.clone() }
     unsafe { LAST_KEY_STATE = KEY_STATE.clone() }
     unsafe { LAST_MOUSE_POS = MOUSE_POS.clone() }
+    unsafe { MOUSE_MOTION = (0.0, 0.0) }
     reset_scroll_wheel();
 }
 
@@ -196,6 +207,11 @@ pub fn get_mouse_delta() -> PhysicalPosition<f64> {
     }
 }
 
+/// Returns how far the mouse moved since the last update in pixels, even with the cursor grabbed
+pub fn get_mouse_motion() -> (f32, f32) {
+    unsafe { (MOUSE_MOTION.0 as f32, MOUSE_MOTION.1 as f32) }
+}
+
 /// Returns mouse position between -1 and 1 on both axes
 pub fn get_mouse_position_range(window_size: PhysicalSize<u32>) -> (f32, f32) {
     let pos = get_mouse_position();
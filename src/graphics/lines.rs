@@ -5,6 +5,7 @@ use crate::utils;
 use super::{
     init,
     vertex::{self, Vertex},
+    wgpu_object::WgpuObject,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -24,6 +25,8 @@ pub struct LineRenderer {
     pub pipeline_layout: wgpu::PipelineLayout,
     pub shader: wgpu::ShaderModule,
     pub depth_texture: super::texture::Texture,
+    /// Blended over the scene without hiding what's behind, for lines that fade out
+    translucent: bool,
     changed: bool,
 }
 
@@ -32,6 +35,7 @@ impl LineRenderer {
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         uniform_bg: &wgpu::BindGroupLayout,
+        translucent: bool,
     ) -> Self {
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Line Renderer Pipeline Layout"),
@@ -41,8 +45,14 @@ impl LineRenderer {
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/lines.wgsl"));
 
-        let pipeline =
-            init::create_render_pipeline(device, &pipeline_layout, &shader, config, true);
+        let pipeline = init::create_line_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            config,
+            WgpuObject::SAMPLE_COUNT,
+            translucent,
+        );
 
         let depth_texture =
             super::depth::create_depth_texture(device, config, "line_depth_texture");
@@ -57,6 +67,7 @@ impl LineRenderer {
             pipeline_layout,
             shader,
             depth_texture,
+            translucent,
             changed: false,
         };
 
@@ -65,6 +76,23 @@ impl LineRenderer {
         x
     }
 
+    /// Lines are drawn into the scene, so they follow its sample count
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        self.pipeline = init::create_line_pipeline(
+            device,
+            &self.pipeline_layout,
+            &self.shader,
+            config,
+            sample_count,
+            self.translucent,
+        );
+    }
+
     pub fn generate_buffers(&mut self, device: &wgpu::Device) {
         let mut vertices = vec![];
         let mut indices = vec![];
@@ -103,6 +131,7 @@ impl LineRenderer {
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
         });
+        self.changed = false;
     }
 
     pub fn changed(&self) -> bool {
@@ -115,6 +144,8 @@ impl LineRenderer {
     }
 }
 
+/// Lines drawn over the finished scene, and lines drawn into it behind everything else, such as
+/// the ground grid
 pub struct LineRendering {
     pub fg: LineRenderer,
     pub bg: LineRenderer,
@@ -127,11 +158,21 @@ impl LineRendering {
         uniform_bg: &wgpu::BindGroupLayout,
     ) -> Self {
         Self {
-            fg: LineRenderer::new(device, config, uniform_bg),
-            bg: LineRenderer::new(device, config, uniform_bg),
+            fg: LineRenderer::new(device, config, uniform_bg, false),
+            bg: LineRenderer::new(device, config, uniform_bg, true),
         }
     }
 
+    pub fn rebuild_pipelines(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        self.fg.rebuild_pipeline(device, config, sample_count);
+        self.bg.rebuild_pipeline(device, config, sample_count);
+    }
+
     pub fn draw_line_fg(&mut self, pos0: glam::Vec3, pos1: glam::Vec3, color: [f32; 4]) {
         self.fg.draw_line(pos0, pos1, color);
     }
@@ -1,11 +1,20 @@
 pub mod cam;
 pub mod depth;
+pub mod font;
+pub mod guides;
+pub mod headless;
 pub mod init;
 pub mod input;
 pub mod lines;
 pub mod msaa;
+pub mod navigation;
+pub mod outline;
+pub mod overlay;
+pub mod post;
 pub mod render;
+pub mod shadow;
 pub mod texture;
+pub mod timeline;
 pub mod transform;
 pub mod vertex;
 pub mod wgpu_object;
@@ -59,9 +68,14 @@ pub async fn run() {
                 wgpu_obj.resize(*physical_size);
             }
             WindowEvent::CursorMoved { position, .. } => input::poll_mouse_move_event(position),
+            WindowEvent::Focused(false) => wgpu_obj.set_flying(false),
             WindowEvent::CloseRequested => elwt.exit(),
             _ => {}
         },
+        Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } => input::poll_mouse_motion(&delta),
         Event::AboutToWait => {
             wgpu_obj.delta_time = std::time::Instant::now()
                 .duration_since(last_frame)
@@ -0,0 +1,229 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::consts::*;
+
+use super::input::{self, InputMouseButton};
+
+/// Modifier keys, held along with a mouse button or to slow a drag down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self {
+        shift: false,
+        ctrl: false,
+        alt: false,
+    };
+    pub const SHIFT: Self = Self {
+        shift: true,
+        ..Self::NONE
+    };
+    pub const CTRL: Self = Self {
+        ctrl: true,
+        ..Self::NONE
+    };
+    pub const ALT: Self = Self {
+        alt: true,
+        ..Self::NONE
+    };
+
+    /// Modifiers held down right now
+    pub fn held() -> Self {
+        Self {
+            shift: input::is_shift_down(),
+            ctrl: input::is_ctrl_down(),
+            alt: input::is_alt_down(),
+        }
+    }
+
+    /// Whether every modifier in `other` is held in these
+    pub fn contains(self, other: Self) -> bool {
+        (self.shift || !other.shift) && (self.ctrl || !other.ctrl) && (self.alt || !other.alt)
+    }
+}
+
+/// A mouse button and the modifiers that have to be held, and no others, to drag with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MouseBinding {
+    pub button: InputMouseButton,
+    #[serde(default)]
+    pub modifiers: Modifiers,
+}
+
+impl MouseBinding {
+    pub const fn new(button: InputMouseButton, modifiers: Modifiers) -> Self {
+        Self { button, modifiers }
+    }
+
+    /// Whether `held` is exactly these modifiers, not counting any in `ignored` the binding
+    /// doesn't ask for
+    fn matches(&self, held: Modifiers, ignored: Modifiers) -> bool {
+        let key = |bound: bool, held: bool, ignored: bool| bound == held || (ignored && !bound);
+        key(self.modifiers.shift, held.shift, ignored.shift)
+            && key(self.modifiers.ctrl, held.ctrl, ignored.ctrl)
+            && key(self.modifiers.alt, held.alt, ignored.alt)
+    }
+}
+
+/// What dragging the mouse does to the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavAction {
+    Orbit,
+    Pan,
+    Zoom,
+}
+
+/// Navigation as it works in other programs, to start from or customize
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NavigationPreset {
+    /// Middle mouse to orbit, with Shift to pan and Ctrl to zoom
+    #[default]
+    Blender,
+    /// Alt with the left, middle and right buttons to orbit, pan and zoom
+    Maya,
+    /// Right mouse to orbit and middle to pan, which Shift with right also does without a middle
+    /// button
+    MagicaVoxel,
+    /// Whatever was loaded from or last saved to `NAVIGATION_PATH`, kept by whoever has them.
+    /// Has no bindings of its own, so asking for them gives the default preset's.
+    Custom,
+}
+
+impl NavigationPreset {
+    pub const ALL: [NavigationPreset; 3] = [
+        NavigationPreset::Blender,
+        NavigationPreset::Maya,
+        NavigationPreset::MagicaVoxel,
+    ];
+
+    /// The preset after this one, coming around to `Custom` after the rest when there are custom
+    /// bindings to go back to
+    pub fn next(self, custom: bool) -> Self {
+        let mut cycle = Self::ALL.to_vec();
+        if custom {
+            cycle.push(NavigationPreset::Custom);
+        }
+
+        let next = cycle
+            .iter()
+            .position(|p| *p == self)
+            .map_or(0, |i| (i + 1) % cycle.len());
+        cycle[next]
+    }
+
+    pub fn bindings(self) -> NavigationBindings {
+        use InputMouseButton::*;
+
+        let defaults = NavigationBindings {
+            orbit: vec![],
+            pan: vec![],
+            zoom: vec![],
+            throttle: Modifiers::ALT,
+            orbit_speed: glam::vec2(ROT_SENS_X, ROT_SENS_Y),
+            pan_speed: PAN_SENS,
+            zoom_speed: 1.0,
+            drag_zoom_speed: ZOOM_DRAG_SENS,
+            throttle_speed: ROT_SENS_THROTTLE,
+        };
+
+        match self {
+            NavigationPreset::Blender => NavigationBindings {
+                orbit: vec![MouseBinding::new(Middle, Modifiers::NONE)],
+                pan: vec![MouseBinding::new(Middle, Modifiers::SHIFT)],
+                zoom: vec![MouseBinding::new(Middle, Modifiers::CTRL)],
+                ..defaults
+            },
+            NavigationPreset::Maya => NavigationBindings {
+                orbit: vec![MouseBinding::new(Left, Modifiers::ALT)],
+                pan: vec![MouseBinding::new(Middle, Modifiers::ALT)],
+                zoom: vec![MouseBinding::new(Right, Modifiers::ALT)],
+                throttle: Modifiers::SHIFT,
+                ..defaults
+            },
+            NavigationPreset::MagicaVoxel => NavigationBindings {
+                orbit: vec![MouseBinding::new(Right, Modifiers::NONE)],
+                pan: vec![
+                    MouseBinding::new(Middle, Modifiers::NONE),
+                    MouseBinding::new(Right, Modifiers::SHIFT),
+                ],
+                ..defaults
+            },
+            NavigationPreset::Custom => NavigationPreset::default().bindings(),
+        }
+    }
+}
+
+/// Which mouse buttons move the camera and how fast. Loaded from `NAVIGATION_PATH` when it
+/// exists, where anything left out is taken from the Blender preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NavigationBindings {
+    pub orbit: Vec<MouseBinding>,
+    pub pan: Vec<MouseBinding>,
+    pub zoom: Vec<MouseBinding>,
+    /// Held during a drag to slow it down, and ignored when picking which binding a drag is for
+    pub throttle: Modifiers,
+    pub orbit_speed: glam::Vec2,
+    pub pan_speed: f32,
+    /// Zoom steps per notch of the scroll wheel, negative to zoom the other way
+    pub zoom_speed: f32,
+    /// Zoom steps a drag across the window's height makes
+    pub drag_zoom_speed: f32,
+    /// How much slower dragging is with the throttle held
+    pub throttle_speed: f32,
+}
+
+impl Default for NavigationBindings {
+    fn default() -> Self {
+        NavigationPreset::default().bindings()
+    }
+}
+
+impl NavigationBindings {
+    /// What a drag with the buttons `down` and modifiers `held` would do, and the button that
+    /// keeps it going
+    pub fn action(
+        &self,
+        down: impl Fn(InputMouseButton) -> bool,
+        held: Modifiers,
+    ) -> Option<(NavAction, InputMouseButton)> {
+        [
+            (NavAction::Orbit, &self.orbit),
+            (NavAction::Pan, &self.pan),
+            (NavAction::Zoom, &self.zoom),
+        ]
+        .into_iter()
+        .flat_map(|(action, bindings)| bindings.iter().map(move |b| (action, b)))
+        .find(|(_, b)| down(b.button) && b.matches(held, self.throttle))
+        .map(|(action, b)| (action, b.button))
+    }
+
+    /// Whether a drag is slowed down by the modifiers held
+    pub fn throttled(&self, held: Modifiers) -> bool {
+        self.throttle != Modifiers::NONE && held.contains(self.throttle)
+    }
+
+    pub fn save<P>(&self, path: P) -> anyhow::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Couldn't write {}", path.as_ref().display()))
+    }
+
+    pub fn load<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read {}", path.as_ref().display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Couldn't parse {}", path.as_ref().display()))
+    }
+}
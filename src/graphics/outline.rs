@@ -0,0 +1,132 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    models::model::Model,
+    utils::{self, consts::*},
+};
+
+use uniform::OutlineUniform;
+
+/// How voxel edges are outlined, shared by the viewport and exports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineSettings {
+    pub enabled: bool,
+    /// Width of the lines between voxels, in pixels
+    pub thickness: f32,
+    pub color: [f32; 4],
+    /// How many times thicker the model's silhouette is drawn than the edges inside it
+    pub silhouette: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            thickness: OUTLINE_THICKNESS,
+            color: OUTLINE_COLORS[0],
+            silhouette: OUTLINE_SILHOUETTE,
+        }
+    }
+}
+
+// The derive's field checks are functions nothing calls, so the allow is kept to this module
+#[allow(dead_code)]
+mod uniform {
+    use encase::ShaderType;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone, ShaderType)]
+    pub(super) struct OutlineUniform {
+        pub(super) color: glam::Vec4,
+        /// Size of a voxel in mesh space, where the edges are
+        pub(super) voxel_size: f32,
+        pub(super) thickness: f32,
+        pub(super) enabled: u32,
+    }
+}
+
+/// Edges between voxels, drawn by the main shader from where faces are in the model's grid
+pub struct Outlines {
+    pub settings: OutlineSettings,
+    voxel_size: f32,
+    uniform_buf: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Outlines {
+    pub fn new(device: &wgpu::Device, settings: OutlineSettings) -> Self {
+        let voxel_size = 1.0;
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Outline Buffer"),
+            contents: &utils::uniform_buffer_to_bytes(Self::uniform(&settings, voxel_size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Outline Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Outline Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            settings,
+            voxel_size,
+            uniform_buf,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: OutlineSettings) {
+        self.settings = OutlineSettings {
+            thickness: settings.thickness.max(0.0),
+            silhouette: settings.silhouette.max(0.0),
+            ..settings
+        };
+        self.write_uniform(queue);
+    }
+
+    /// Lines the edges up with a model's voxels, whose size depends on how big the model is
+    pub fn set_model(&mut self, queue: &wgpu::Queue, model: &Model) {
+        let voxel_size = model.mesh_scale();
+        if voxel_size != self.voxel_size {
+            self.voxel_size = voxel_size;
+            self.write_uniform(queue);
+        }
+    }
+
+    fn write_uniform(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            &utils::uniform_buffer_to_bytes(Self::uniform(&self.settings, self.voxel_size)),
+        );
+    }
+
+    fn uniform(settings: &OutlineSettings, voxel_size: f32) -> OutlineUniform {
+        OutlineUniform {
+            color: glam::Vec4::from_array(settings.color),
+            voxel_size,
+            thickness: settings.thickness,
+            enabled: settings.enabled as u32,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use wgpu::util::DeviceExt;
+
+use crate::utils::{self, consts::*};
+
+use super::font::{self, GLYPH_SIZE};
+
+pub use gpu::OverlayVertex;
+use gpu::ScreenUniform;
+
+// The derives check the layout with code that's never called, so the allow is scoped to the two
+// types the shader reads
+#[allow(dead_code)]
+mod gpu {
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Pod, Zeroable, Default)]
+    pub struct OverlayVertex {
+        pub pos: [f32; 2],
+        pub color: [f32; 4],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Pod, Zeroable)]
+    pub(super) struct ScreenUniform {
+        pub(super) size: [f32; 2],
+        pub(super) _padding: [f32; 2],
+    }
+}
+
+/// Axis aligned rectangle in pixels, with the origin in the top left of the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min: glam::Vec2,
+    pub max: glam::Vec2,
+    pub color: [f32; 4],
+}
+
+/// Draws flat 2D rectangles and bitmap text in screen space on top of the resolved frame
+pub struct OverlayRenderer {
+    pub rects: Vec<Rect>,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_buffer_size: u32,
+    pub pipeline: wgpu::RenderPipeline,
+    pub screen_buf: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    changed: bool,
+}
+
+impl OverlayRenderer {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let screen_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Screen Buffer"),
+            contents: bytemuck::cast_slice(&[ScreenUniform {
+                size: [config.width as f32, config.height as f32],
+                _padding: [0.0; 2],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Overlay Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Overlay Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_buf.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/overlay.wgsl"));
+
+        // Drawn straight onto the resolved frame, so no depth and no multisampling
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &OVERLAY_VBO_ATTRIBS,
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.view_formats[0],
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            rects: vec![],
+            vertex_buffer: utils::empty_buffer(device),
+            index_buffer: utils::empty_buffer(device),
+            index_buffer_size: 0,
+            pipeline,
+            screen_buf,
+            bind_group,
+            changed: true,
+        }
+    }
+
+    pub fn resize(&mut self, queue: &wgpu::Queue, new_size: winit::dpi::PhysicalSize<u32>) {
+        queue.write_buffer(
+            &self.screen_buf,
+            0,
+            bytemuck::cast_slice(&[ScreenUniform {
+                size: [new_size.width as f32, new_size.height as f32],
+                _padding: [0.0; 2],
+            }]),
+        );
+        self.changed = true;
+    }
+
+    pub fn generate_buffers(&mut self, device: &wgpu::Device) {
+        let mut vertices = vec![];
+        let mut indices: Vec<u32> = vec![];
+
+        for r in &self.rects {
+            let offset = vertices.len() as u32;
+            for pos in [
+                [r.min.x, r.min.y],
+                [r.max.x, r.min.y],
+                [r.max.x, r.max.y],
+                [r.min.x, r.max.y],
+            ] {
+                vertices.push(OverlayVertex {
+                    pos,
+                    color: r.color,
+                });
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].iter().map(|i| i + offset));
+        }
+
+        self.vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        self.index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        self.index_buffer_size = indices.len() as u32;
+        self.changed = false;
+    }
+
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    pub fn draw_rect(&mut self, min: glam::Vec2, max: glam::Vec2, color: [f32; 4]) {
+        self.rects.push(Rect { min, max, color });
+        self.changed = true;
+    }
+
+    /// Draws a line `thickness` pixels wide out of a square for every pixel along it, so colors
+    /// should be opaque
+    pub fn draw_line(&mut self, from: glam::Vec2, to: glam::Vec2, thickness: f32, color: [f32; 4]) {
+        let steps = from.distance(to).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let pos = from.lerp(to, i as f32 / steps as f32);
+            self.draw_rect(pos - thickness / 2.0, pos + thickness / 2.0, color);
+        }
+    }
+
+    /// Draws text with its top left corner at `pos`, each font pixel `scale` pixels wide
+    pub fn draw_text(&mut self, pos: glam::Vec2, text: &str, scale: f32, color: [f32; 4]) {
+        let advance = (GLYPH_SIZE.0 + 1) as f32 * scale;
+        for (i, c) in text.chars().enumerate() {
+            let origin = pos + glam::vec2(i as f32 * advance, 0.0);
+            for (x, y) in font::glyph_pixels(c) {
+                let min = origin + glam::vec2(x as f32, y as f32) * scale;
+                self.draw_rect(min, min + glam::Vec2::splat(scale), color);
+            }
+        }
+    }
+
+    /// Size of text drawn with `draw_text`
+    pub fn text_size(text: &str, scale: f32) -> glam::Vec2 {
+        let count = text.chars().count() as f32;
+        glam::vec2(
+            ((GLYPH_SIZE.0 + 1) as f32 * count - 1.0).max(0.0),
+            GLYPH_SIZE.1 as f32,
+        ) * scale
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+        self.changed = true;
+    }
+}
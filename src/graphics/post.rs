@@ -0,0 +1,575 @@
+use wgpu::util::DeviceExt;
+
+use crate::utils::{self, consts::*};
+
+use super::{cam, outline::OutlineSettings, wgpu_object::WgpuObject};
+
+use uniform::PostUniform;
+
+/// Which post passes the viewport runs over its image, each can be switched while running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostSettings {
+    /// Darkens creases and corners, worked out from the depth buffer
+    pub ssao: bool,
+    /// Lets emissive faces glow into their surroundings
+    pub bloom: bool,
+    /// Smooths edges after drawing instead of multisampling, which is much cheaper
+    pub fxaa: bool,
+    /// Rolls off brightness past white instead of clipping it
+    pub tone_mapping: bool,
+    /// Scales brightness before tone mapping
+    pub exposure: f32,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            ssao: true,
+            bloom: true,
+            fxaa: false,
+            tone_mapping: true,
+            exposure: 1.0,
+        }
+    }
+}
+
+impl PostSettings {
+    /// Samples the scene is drawn with, FXAA stands in for multisampling
+    pub fn sample_count(&self) -> u32 {
+        match self.fxaa {
+            true => 1,
+            false => WgpuObject::SAMPLE_COUNT,
+        }
+    }
+}
+
+// Kept apart so the allow only covers the functions the derive checks fields with, which are
+// never called
+#[allow(dead_code)]
+mod uniform {
+    use encase::ShaderType;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone, ShaderType)]
+    pub(super) struct PostUniform {
+        /// For sizing the SSAO radius on screen
+        pub(super) projection: glam::Mat4,
+        pub(super) inverse_projection: glam::Mat4,
+        pub(super) outline_color: glam::Vec4,
+        /// In view space, so it follows the model as it's zoomed
+        pub(super) ssao_radius: f32,
+        pub(super) ssao_strength: f32,
+        pub(super) bloom_threshold: f32,
+        pub(super) bloom_strength: f32,
+        pub(super) exposure: f32,
+        pub(super) ssao: u32,
+        pub(super) bloom: u32,
+        pub(super) fxaa: u32,
+        pub(super) tone_mapping: u32,
+        /// In pixels, drawn outside the edge
+        pub(super) silhouette_width: f32,
+        /// Smallest jump in depth that counts as an edge, in view space like the SSAO radius
+        pub(super) silhouette_gap: f32,
+        pub(super) silhouette: u32,
+    }
+}
+
+impl Default for PostUniform {
+    fn default() -> Self {
+        Self {
+            projection: glam::Mat4::IDENTITY,
+            inverse_projection: glam::Mat4::IDENTITY,
+            outline_color: glam::Vec4::ZERO,
+            ssao_radius: SSAO_RADIUS,
+            ssao_strength: SSAO_STRENGTH,
+            bloom_threshold: BLOOM_THRESHOLD,
+            bloom_strength: BLOOM_STRENGTH,
+            exposure: 1.0,
+            ssao: 0,
+            bloom: 0,
+            fxaa: 0,
+            tone_mapping: 0,
+            silhouette_width: 0.0,
+            silhouette_gap: OUTLINE_SILHOUETTE_GAP,
+            silhouette: 0,
+        }
+    }
+}
+
+/// Textures the passes draw into and the bind groups reading them, remade with the window
+struct Targets {
+    hdr: wgpu::TextureView,
+    /// Occlusion in red and silhouettes in green
+    occlusion: wgpu::TextureView,
+    /// Half size, blurred back and forth between the two
+    bloom: [wgpu::TextureView; 2],
+    /// Tone mapped, waiting on FXAA
+    ldr: wgpu::TextureView,
+    depth: wgpu::BindGroup,
+    bright: wgpu::BindGroup,
+    blur: [wgpu::BindGroup; 2],
+    composite: wgpu::BindGroup,
+    present: wgpu::BindGroup,
+}
+
+/// Takes the scene drawn in HDR through SSAO, silhouettes, bloom, tone mapping and FXAA onto
+/// the screen
+pub struct PostProcessor {
+    pub settings: PostSettings,
+    uniform: PostUniform,
+    uniform_buf: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// Depends on whether the depth it reads is multisampled
+    depth_bind_group_layout: wgpu::BindGroupLayout,
+    depth_pipeline: wgpu::RenderPipeline,
+    sample_count: u32,
+    bright_pipeline: wgpu::RenderPipeline,
+    blur_pipelines: [wgpu::RenderPipeline; 2],
+    composite_pipeline: wgpu::RenderPipeline,
+    present_pipeline: wgpu::RenderPipeline,
+    targets: Targets,
+}
+
+impl PostProcessor {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        depth: &wgpu::TextureView,
+        settings: PostSettings,
+    ) -> Self {
+        let mut uniform = PostUniform::default();
+        Self::apply_settings(&mut uniform, &settings);
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Buffer"),
+            contents: &utils::uniform_buffer_to_bytes(uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Bind Group Layout"),
+            entries: &[
+                uniform_entry(),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/post.wgsl"));
+        let pipeline = |entry_point, format| {
+            fullscreen_pipeline(device, &pipeline_layout, &shader, entry_point, format)
+        };
+
+        let sample_count = settings.sample_count();
+        let (depth_bind_group_layout, depth_pipeline) = create_depth_pass(device, sample_count);
+
+        Self {
+            settings,
+            uniform,
+            bright_pipeline: pipeline("fs_bright", HDR_FORMAT),
+            blur_pipelines: [
+                pipeline("fs_blur_x", HDR_FORMAT),
+                pipeline("fs_blur_y", HDR_FORMAT),
+            ],
+            composite_pipeline: pipeline("fs_composite", HDR_FORMAT),
+            present_pipeline: pipeline("fs_present", config.view_formats[0]),
+            targets: create_targets(
+                device,
+                (&bind_group_layout, &depth_bind_group_layout),
+                &uniform_buf,
+                &sampler,
+                config,
+                depth,
+            ),
+            uniform_buf,
+            sampler,
+            bind_group_layout,
+            depth_bind_group_layout,
+            depth_pipeline,
+            sample_count,
+        }
+    }
+
+    /// HDR target the scene is drawn into, resolved if it's multisampled
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.targets.hdr
+    }
+
+    /// Remakes the targets at the surface's size, reading the new depth buffer
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        depth: &wgpu::TextureView,
+    ) {
+        let sample_count = self.settings.sample_count();
+        if sample_count != self.sample_count {
+            (self.depth_bind_group_layout, self.depth_pipeline) =
+                create_depth_pass(device, sample_count);
+            self.sample_count = sample_count;
+        }
+
+        self.targets = create_targets(
+            device,
+            (&self.bind_group_layout, &self.depth_bind_group_layout),
+            &self.uniform_buf,
+            &self.sampler,
+            config,
+            depth,
+        );
+    }
+
+    /// Switches passes, turning FXAA on or off also needs a resize for the new sample count
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: PostSettings) {
+        self.settings = PostSettings {
+            exposure: settings.exposure.clamp(EXPOSURE_MIN, EXPOSURE_MAX),
+            ..settings
+        };
+        Self::apply_settings(&mut self.uniform, &self.settings);
+        self.write_uniform(queue);
+    }
+
+    /// Silhouettes share the viewport's outline settings, and are only drawn while they're on
+    pub fn set_outlines(&mut self, queue: &wgpu::Queue, settings: &OutlineSettings) {
+        self.uniform.outline_color = glam::Vec4::from_array(settings.color);
+        self.uniform.silhouette_width = settings.thickness * settings.silhouette;
+        self.uniform.silhouette = (settings.enabled && self.uniform.silhouette_width > 0.0) as u32;
+        self.write_uniform(queue);
+    }
+
+    /// SSAO and silhouettes have to see surfaces where the camera does
+    pub fn set_camera(&mut self, queue: &wgpu::Queue, camera: &cam::Camera) {
+        let projection = camera.build_projection_matrix();
+        self.uniform.projection = projection;
+        self.uniform.inverse_projection = projection.inverse();
+        self.write_uniform(queue);
+    }
+
+    /// Works out occlusion and silhouettes from the depth buffer, before anything that shouldn't
+    /// affect them is drawn
+    pub fn read_depth(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.settings.ssao || self.uniform.silhouette != 0 {
+            draw(
+                encoder,
+                "DepthRenderPass",
+                &self.targets.occlusion,
+                &self.depth_pipeline,
+                &self.targets.depth,
+            );
+        }
+    }
+
+    /// Runs the rest of the chain on the HDR image, finishing on `output`
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let targets = &self.targets;
+        if self.settings.bloom {
+            let [bloom, blurred] = &targets.bloom;
+            let [blur_x, blur_y] = &self.blur_pipelines;
+            draw(
+                encoder,
+                "BloomRenderPass",
+                bloom,
+                &self.bright_pipeline,
+                &targets.bright,
+            );
+            draw(encoder, "BlurRenderPass", blurred, blur_x, &targets.blur[0]);
+            draw(encoder, "BlurRenderPass", bloom, blur_y, &targets.blur[1]);
+        }
+
+        draw(
+            encoder,
+            "CompositeRenderPass",
+            &targets.ldr,
+            &self.composite_pipeline,
+            &targets.composite,
+        );
+        draw(
+            encoder,
+            "PresentRenderPass",
+            output,
+            &self.present_pipeline,
+            &targets.present,
+        );
+    }
+
+    fn apply_settings(uniform: &mut PostUniform, settings: &PostSettings) {
+        uniform.exposure = settings.exposure;
+        uniform.ssao = settings.ssao as u32;
+        uniform.bloom = settings.bloom as u32;
+        uniform.fxaa = settings.fxaa as u32;
+        uniform.tone_mapping = settings.tone_mapping as u32;
+    }
+
+    fn write_uniform(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            &utils::uniform_buffer_to_bytes(self.uniform),
+        );
+    }
+}
+
+/// The surface's size with the HDR format the scene is drawn in, for making the scene's targets
+/// and pipelines
+pub fn scene_config(config: &wgpu::SurfaceConfiguration) -> wgpu::SurfaceConfiguration {
+    wgpu::SurfaceConfiguration {
+        view_formats: vec![HDR_FORMAT],
+        ..config.clone()
+    }
+}
+
+fn uniform_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// The depth pass reads depth with `textureLoad`, which is declared differently for multisampled
+/// textures
+fn create_depth_pass(
+    device: &wgpu::Device,
+    sample_count: u32,
+) -> (wgpu::BindGroupLayout, wgpu::RenderPipeline) {
+    let multisampled = sample_count > 1;
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Depth Bind Group Layout"),
+        entries: &[
+            uniform_entry(),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    // Bound as plain floats, since GL can't load from depth textures. The first sample is close
+    // enough for occlusion and silhouettes.
+    let depth = format!(
+        "@group(0) @binding(1)\nvar depth: {}<f32>;\n\
+        fn load_depth(pixel: vec2<i32>) -> f32 {{\n    return textureLoad(depth, pixel, 0).r;\n}}\n",
+        match multisampled {
+            true => "texture_multisampled_2d",
+            false => "texture_2d",
+        }
+    );
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("depth.wgsl"),
+        source: wgpu::ShaderSource::Wgsl((depth + include_str!("./shaders/depth.wgsl")).into()),
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Depth Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = fullscreen_pipeline(
+        device,
+        &pipeline_layout,
+        &shader,
+        "fs_depth",
+        OCCLUSION_FORMAT,
+    );
+
+    (bind_group_layout, pipeline)
+}
+
+fn fullscreen_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_fullscreen",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn create_targets(
+    device: &wgpu::Device,
+    (layout, depth_layout): (&wgpu::BindGroupLayout, &wgpu::BindGroupLayout),
+    uniform_buf: &wgpu::Buffer,
+    sampler: &wgpu::Sampler,
+    config: &wgpu::SurfaceConfiguration,
+    depth: &wgpu::TextureView,
+) -> Targets {
+    let (width, height) = (config.width.max(1), config.height.max(1));
+    let target = |label, width, height, format| {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    };
+    let hdr = target("HDR Target", width, height, HDR_FORMAT);
+    let occlusion = target("Occlusion Target", width, height, OCCLUSION_FORMAT);
+    let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+    let bloom = [
+        target("Bloom Target", half_width, half_height, HDR_FORMAT),
+        target("Bloom Target", half_width, half_height, HDR_FORMAT),
+    ];
+    let ldr = target("LDR Target", width, height, HDR_FORMAT);
+
+    // Every pass but the depth pass reads through the same layout, slots it doesn't use get anything that
+    // isn't its own target
+    let bind_group = |source, glow| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&occlusion),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(glow),
+                },
+            ],
+        })
+    };
+    let depth = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Depth Bind Group"),
+        layout: depth_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(depth),
+            },
+        ],
+    });
+
+    Targets {
+        depth,
+        bright: bind_group(&hdr, &bloom[1]),
+        blur: [
+            bind_group(&bloom[0], &bloom[0]),
+            bind_group(&bloom[1], &bloom[1]),
+        ],
+        composite: bind_group(&hdr, &bloom[0]),
+        present: bind_group(&ldr, &bloom[0]),
+        hdr,
+        occlusion,
+        bloom,
+        ldr,
+    }
+}
+
+/// A pass covering all of `target` with one fullscreen triangle
+fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    target: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
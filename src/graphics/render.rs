@@ -12,21 +12,22 @@ pub fn render(wobj: &mut WgpuObject) -> Result<(), SurfaceError> {
         ..Default::default()
     });
 
-    let color_attachment = match WgpuObject::SAMPLE_COUNT {
+    // The scene is drawn in HDR, then post-processed onto the surface
+    let color_attachment = |load| match wobj.post.settings.sample_count() {
         1 => wgpu::RenderPassColorAttachment {
-            view: &view,
+            view: wobj.post.view(),
             resolve_target: None,
             ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                load,
                 store: wgpu::StoreOp::Store,
             },
         },
         _ => wgpu::RenderPassColorAttachment {
             view: &wobj.msaa_buffer,
-            resolve_target: Some(&view),
+            resolve_target: Some(wobj.post.view()),
             ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                store: wgpu::StoreOp::Discard,
+                load,
+                store: wgpu::StoreOp::Store,
             },
         },
     };
@@ -46,20 +47,29 @@ pub fn render(wobj: &mut WgpuObject) -> Result<(), SurfaceError> {
     }
     wobj.cam_staging_buf = None;
 
-    // Copy transform info if it is updated
-    match &wobj.transform_staging_buf {
-        Some(b) => {
-            encoder.copy_buffer_to_buffer(b, 0, &wobj.transform_buf, 0, b.size());
-        }
-        None => {}
+    // Only solid faces cast shadows
+    wobj.shadow_map.render(
+        &mut encoder,
+        &wobj.vertex_buffer,
+        &wobj.shadow_index_buffer,
+        wobj.shadow_index_size,
+    );
+
+    // Regenerate line buffers if changed
+    if wobj.line_rendering.fg.changed() {
+        wobj.line_rendering.fg.generate_buffers(&wobj.device);
+    }
+    if wobj.line_rendering.bg.changed() {
+        wobj.line_rendering.bg.generate_buffers(&wobj.device);
     }
-    wobj.transform_staging_buf = None;
 
     // Main Render Pass
     {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("MainRenderPass"),
-            color_attachments: &[Some(color_attachment.clone())],
+            color_attachments: &[Some(color_attachment(wgpu::LoadOp::Clear(
+                wgpu::Color::BLACK,
+            )))],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &wobj.depth_texture.view,
                 depth_ops: Some(wgpu::Operations {
@@ -72,33 +82,46 @@ pub fn render(wobj: &mut WgpuObject) -> Result<(), SurfaceError> {
             occlusion_query_set: None,
         });
 
-        render_pass.execute_bundles(std::iter::once(&wobj.msaa_bundle));
-
         render_pass.set_pipeline(&wobj.pipeline);
         render_pass.set_bind_group(0, &wobj.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &wobj.shadow_map.bind_group, &[]);
+        render_pass.set_bind_group(2, &wobj.outlines.bind_group, &[]);
         render_pass.set_vertex_buffer(0, wobj.vertex_buffer.slice(..));
         render_pass.set_index_buffer(wobj.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..wobj.index_buffer_size, 0, 0..1);
-    }
+        render_pass.draw_indexed(0..wobj.opaque_index_size, 0, 0..1);
+        // The floor is two triangles, which the wireframe pipeline would draw as lines
+        if !wobj.wireframe {
+            wobj.shadow_map.draw_ground(&mut render_pass);
+        }
 
-    // Foreground Lines Render Pass
+        // Background lines, such as the grid, blended over the ground and behind the model
+        render_pass.set_pipeline(&wobj.line_rendering.bg.pipeline);
+        render_pass.set_vertex_buffer(0, wobj.line_rendering.bg.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(
+            wobj.line_rendering.bg.index_buffer.slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        render_pass.draw_indexed(0..wobj.line_rendering.bg.index_buffer_size, 0, 0..1);
 
-    // Regenerate buffers if changed
-    if wobj.line_rendering.fg.changed() {
-        wobj.line_rendering.fg.generate_buffers(&wobj.device);
-    }
-    if wobj.line_rendering.bg.changed() {
-        wobj.line_rendering.bg.generate_buffers(&wobj.device);
+        // Translucent faces last, sorted back to front, over everything solid
+        render_pass.set_pipeline(&wobj.translucent_pipeline);
+        render_pass.set_vertex_buffer(0, wobj.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(wobj.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(wobj.opaque_index_size..wobj.index_buffer_size, 0, 0..1);
     }
 
+    // Before the lines, which shouldn't darken or outline what's around them
+    wobj.post.read_depth(&mut encoder);
+
+    // Foreground Lines Render Pass
     {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("FGLinesRenderPass"),
-            color_attachments: &[Some(color_attachment.clone())],
+            color_attachments: &[Some(color_attachment(wgpu::LoadOp::Load))],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &wobj.depth_texture.view,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Load,
                     store: wgpu::StoreOp::Store,
                 }),
                 stencil_ops: None,
@@ -107,8 +130,6 @@ pub fn render(wobj: &mut WgpuObject) -> Result<(), SurfaceError> {
             occlusion_query_set: None,
         });
 
-        render_pass.execute_bundles(std::iter::once(&wobj.msaa_bundle));
-
         render_pass.set_pipeline(&wobj.line_rendering.fg.pipeline);
         render_pass.set_bind_group(0, &wobj.uniform_bind_group, &[]);
         render_pass.set_vertex_buffer(0, wobj.line_rendering.fg.vertex_buffer.slice(..));
@@ -120,6 +141,41 @@ pub fn render(wobj: &mut WgpuObject) -> Result<(), SurfaceError> {
         render_pass.draw_indexed(0..wobj.line_rendering.fg.index_buffer_size, 0, 0..1);
     }
 
+    wobj.post.render(&mut encoder, &view);
+
+    // Overlay Render Pass
+
+    if wobj.overlay.changed() {
+        wobj.overlay.generate_buffers(&wobj.device);
+    }
+
+    {
+        // Drawn over the resolved image so it stays crisp and on top of everything
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OverlayRenderPass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&wobj.overlay.pipeline);
+        render_pass.set_bind_group(0, &wobj.overlay.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, wobj.overlay.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(
+            wobj.overlay.index_buffer.slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        render_pass.draw_indexed(0..wobj.overlay.index_buffer_size, 0, 0..1);
+    }
+
     wobj.queue.submit(std::iter::once(encoder.finish()));
 
     output.present();
@@ -0,0 +1,142 @@
+struct PostUniform {
+    projection: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+    outline_color: vec4<f32>,
+    ssao_radius: f32,
+    ssao_strength: f32,
+    bloom_threshold: f32,
+    bloom_strength: f32,
+    exposure: f32,
+    ssao: u32,
+    bloom: u32,
+    fxaa: u32,
+    tone_mapping: u32,
+    silhouette_width: f32,
+    silhouette_gap: f32,
+    silhouette: u32,
+};
+@group(0) @binding(0)
+var<uniform> post: PostUniform;
+
+// `depth` and `load_depth` are declared ahead of this, for whichever sample count the scene uses
+
+const SAMPLES: i32 = 12;
+// Furthest silhouettes are looked for, in pixels
+const SILHOUETTE_REACH: i32 = 8;
+
+// One triangle covering the screen, with no buffers needed
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Where a pixel's surface is relative to the camera, from its depth
+fn view_position(pixel: vec2<i32>, size: vec2<f32>) -> vec3<f32> {
+    let uv = (vec2<f32>(pixel) + 0.5) / size;
+    let clip = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, load_depth(pixel), 1.0);
+    let view = post.inverse_projection * clip;
+    return view.xyz / view.w;
+}
+
+// Normal from whichever neighbours are on the same surface, so edges don't get smeared
+fn surface_normal(pixel: vec2<i32>, size: vec2<f32>, center: vec3<f32>) -> vec3<f32> {
+    let last = vec2<i32>(size) - 1;
+    let right = view_position(min(pixel + vec2<i32>(1, 0), last), size) - center;
+    let left = center - view_position(max(pixel - vec2<i32>(1, 0), vec2<i32>(0)), size);
+    let down = view_position(min(pixel + vec2<i32>(0, 1), last), size) - center;
+    let up = center - view_position(max(pixel - vec2<i32>(0, 1), vec2<i32>(0)), size);
+    let use_right = pixel.x == 0 || (pixel.x < last.x && abs(right.z) < abs(left.z));
+    let use_down = pixel.y == 0 || (pixel.y < last.y && abs(down.z) < abs(up.z));
+    var normal = normalize(cross(select(left, right, use_right), select(up, down, use_down)));
+    if dot(normal, center) > 0.0 {
+        normal = -normal;
+    }
+    return normal;
+}
+
+// How much light reaches a pixel's surface past what's around it
+fn ambient_occlusion(position: vec2<f32>, size: vec2<f32>) -> f32 {
+    let last = vec2<i32>(size) - 1;
+    let pixel = vec2<i32>(position);
+
+    // Nothing to occlude in the background
+    if load_depth(pixel) >= 1.0 {
+        return 1.0;
+    }
+    let center = view_position(pixel, size);
+    let normal = surface_normal(pixel, size, center);
+
+    let radius = post.ssao_radius;
+    // Perspective shrinks it with distance, orthographic doesn't
+    let clip_w = (post.projection * vec4<f32>(center, 1.0)).w;
+    let radius_pixels = radius * post.projection[1][1] * 0.5 * size.y / clip_w;
+    let bias = radius * 0.05;
+
+    // Turns the spiral of samples differently for every pixel, trading banding for grain
+    let noise = fract(52.9829189 * fract(dot(position, vec2<f32>(0.06711056, 0.00583715))));
+
+    // How far above the surface's horizon nearby points are, fading out towards the radius
+    var occlusion = 0.0;
+    for (var i = 0; i < SAMPLES; i++) {
+        let t = (f32(i) + 0.5) / f32(SAMPLES);
+        let angle = f32(i) * 2.3999632 + noise * 6.2831853;
+        let offset = vec2<f32>(cos(angle), sin(angle)) * t * radius_pixels;
+        let sample_pixel = clamp(pixel + vec2<i32>(offset), vec2<i32>(0), last);
+        if load_depth(sample_pixel) >= 1.0 {
+            continue;
+        }
+
+        let v = view_position(sample_pixel, size) - center;
+        let falloff = max(1.0 - dot(v, v) / (radius * radius), 0.0);
+        occlusion += falloff * max(dot(v, normal) - bias, 0.0) / (length(v) + bias);
+    }
+
+    return max(1.0 - occlusion * 2.0 * post.ssao_strength / f32(SAMPLES), 0.0);
+}
+
+// How much of a pixel is covered by the model's outline, which is drawn on whatever's behind an
+// edge where something standing further than the gap off its surface ends
+fn silhouette(position: vec2<f32>, size: vec2<f32>) -> f32 {
+    let last = vec2<i32>(size) - 1;
+    let pixel = vec2<i32>(position);
+    let depth = load_depth(pixel);
+    let background = depth >= 1.0;
+    var center = vec3<f32>(0.0);
+    var normal = vec3<f32>(0.0);
+    if !background {
+        center = view_position(pixel, size);
+        normal = surface_normal(pixel, size, center);
+    }
+
+    let reach = min(i32(ceil(post.silhouette_width)), SILHOUETTE_REACH);
+    var nearest = 1e9;
+    for (var y = -reach; y <= reach; y++) {
+        for (var x = -reach; x <= reach; x++) {
+            let sample_pixel = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), last);
+            if load_depth(sample_pixel) >= depth {
+                continue;
+            }
+            // Measured from the surface's plane, so faces seen side on don't outline themselves
+            let offset = dot(view_position(sample_pixel, size) - center, normal);
+            if background || offset > post.silhouette_gap {
+                nearest = min(nearest, length(vec2<f32>(f32(x), f32(y))));
+            }
+        }
+    }
+    return clamp(post.silhouette_width + 0.5 - nearest, 0.0, 1.0);
+}
+
+// Occlusion in red and silhouettes in green, each only worked out while it's switched on
+@fragment
+fn fs_depth(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(depth));
+    var out = vec4<f32>(1.0, 0.0, 0.0, 1.0);
+    if post.ssao != 0u {
+        out.r = ambient_occlusion(position.xy, size);
+    }
+    if post.silhouette != 0u {
+        out.g = silhouette(position.xy, size);
+    }
+    return out;
+}
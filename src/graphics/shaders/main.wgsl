@@ -4,23 +4,42 @@ struct CameraUniform {
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
-struct TransformUniform {
-    zoom: f32,
-    zoom_factor: f32,
-    pan: vec3<f32>,
-}
-@group(0) @binding(1)
-var<uniform> transform: TransformUniform;
+struct ShadowUniform {
+    light_view_proj: mat4x4<f32>,
+    sun_direction: vec3<f32>,
+    ambient: f32,
+    texel: f32,
+    normal_offset: f32,
+    enabled: u32,
+};
+@group(1) @binding(0)
+var<uniform> shadow: ShadowUniform;
+@group(1) @binding(1)
+var shadow_map: texture_depth_2d;
+@group(1) @binding(2)
+var shadow_sampler: sampler_comparison;
+
+struct OutlineUniform {
+    color: vec4<f32>,
+    voxel_size: f32,
+    thickness: f32,
+    enabled: u32,
+};
+@group(2) @binding(0)
+var<uniform> outline: OutlineUniform;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec4<f32>,
-    @location(2) normal: vec3<f32>
+    @location(2) normal: vec3<f32>,
+    @location(3) emission: f32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) @interpolate(perspective) color: vec4<f32>,
+    @location(1) position: vec3<f32>,
+    @location(2) emission: f32,
 };
 
 @vertex
@@ -29,17 +48,90 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
 
-    // out.clip_position = camera.view_proj * vec4<f32>(model.position * transform.zoom_factor, 1.0);
-    out.clip_position = camera.view_proj * vec4<f32>(model.position * transform.zoom_factor + transform.pan, 1.0);
-    let sun_dir = normalize(vec3<f32>(1.0, -1.0, 1.0));
-    // var factor = dot(normalize(model.normal), -sun_dir);
-    var factor = 1.0;
-    
-    out.color = model.color * factor;
+    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
+    out.color = model.color;
+    out.position = model.position;
+    out.emission = model.emission;
     return out;
 }
 
+// How much of the sun reaches a point, softened by sampling the shadow map around it
+fn sunlight(position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    // Pushed off the surface so faces don't shadow themselves
+    let offset = position + normal * shadow.normal_offset;
+    let clip = shadow.light_view_proj * vec4<f32>(offset, 1.0);
+    let uv = clip.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    // Nothing outside the map casts shadows
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || clip.z > 1.0 {
+        return 1.0;
+    }
+
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let texel = vec2<f32>(f32(x), f32(y)) * shadow.texel;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + texel, clip.z);
+        }
+    }
+    return lit / 9.0;
+}
+
+// How much of a pixel is covered by the lines along the voxel edges of the face it's on
+fn edge_coverage(position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let voxel = (position + 1.0) / outline.voxel_size;
+    // Pixels to the nearest voxel boundary along each axis
+    let distance = min(fract(voxel), 1.0 - fract(voxel)) / max(fwidth(voxel), vec3<f32>(1e-5));
+
+    // The face lies on a boundary along its own normal, so that axis is left out
+    let n = abs(normal);
+    var nearest = 1e9;
+    if n.x < max(n.y, n.z) {
+        nearest = min(nearest, distance.x);
+    }
+    if n.y <= max(n.x, n.z) {
+        nearest = min(nearest, distance.y);
+    }
+    if n.z < max(n.x, n.y) {
+        nearest = min(nearest, distance.z);
+    }
+
+    // The ground sits just under the model and has no voxels to outline
+    if position.y < -1.0 - 1e-4 {
+        return 0.0;
+    }
+    let half = outline.thickness * 0.5;
+    return 1.0 - smoothstep(half - 0.5, half + 0.5, nearest);
+}
+
+// Lit color of a face, before any outlines
+fn shade(in: VertexOutput, normal: vec3<f32>) -> vec4<f32> {
+    var color = in.color.xyz;
+    if shadow.enabled != 0u {
+        let facing = max(dot(normal, shadow.sun_direction), 0.0);
+        color *= mix(shadow.ambient, 1.0, facing * sunlight(in.position, normal));
+    }
+    // Glowing faces aren't darkened by shadows, and go past 1 for bloom to pick up
+    color += in.color.xyz * in.emission;
+    return vec4<f32>(color, in.color.a);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color.xyz, 1.0);
-}
\ No newline at end of file
+    // Vertex normals are shared between faces, so lighting uses the flat face instead
+    let normal = -normalize(cross(dpdx(in.position), dpdy(in.position)));
+    // Derivatives have to be taken before anything branches
+    let edge = edge_coverage(in.position, normal);
+
+    var color = shade(in, normal);
+    if outline.enabled != 0u {
+        color = vec4<f32>(mix(color.rgb, outline.color.rgb, edge * outline.color.a), color.a);
+    }
+    return color;
+}
+
+// Lines are already the edges, so they're drawn without outlines
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in, -normalize(cross(dpdx(in.position), dpdy(in.position))));
+}
@@ -0,0 +1,154 @@
+struct PostUniform {
+    projection: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+    outline_color: vec4<f32>,
+    ssao_radius: f32,
+    ssao_strength: f32,
+    bloom_threshold: f32,
+    bloom_strength: f32,
+    exposure: f32,
+    ssao: u32,
+    bloom: u32,
+    fxaa: u32,
+    tone_mapping: u32,
+    silhouette_width: f32,
+    silhouette_gap: f32,
+    silhouette: u32,
+};
+@group(0) @binding(0)
+var<uniform> post: PostUniform;
+@group(0) @binding(1)
+var linear_sampler: sampler;
+@group(0) @binding(2)
+var source: texture_2d<f32>;
+@group(0) @binding(3)
+var occlusion: texture_2d<f32>;
+@group(0) @binding(4)
+var glow: texture_2d<f32>;
+
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// One triangle covering the screen, with no buffers needed
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> FullscreenOutput {
+    var out: FullscreenOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn sample_source(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source, linear_sampler, uv, 0.0).rgb;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Only what's brighter than the threshold glows, which in practice means emissive faces
+@fragment
+fn fs_bright(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = sample_source(in.uv);
+    let luma = luminance(color);
+    return vec4<f32>(color * max(luma - post.bloom_threshold, 0.0) / max(luma, 1e-4), 1.0);
+}
+
+// Separable gaussian, run once across and once down
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    let step = direction * 1.5 / vec2<f32>(textureDimensions(source));
+
+    var color = sample_source(uv) * weights[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        color += (sample_source(uv + offset) + sample_source(uv - offset)) * weights[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_blur_x(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_y(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(0.0, 1.0));
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    return (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+}
+
+@fragment
+fn fs_composite(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    var color = sample_source(in.uv);
+
+    if post.ssao != 0u {
+        // Four bilinear taps average out the grain in the occlusion
+        let texel = 1.0 / vec2<f32>(textureDimensions(occlusion));
+        var visible = 0.0;
+        for (var i = 0; i < 4; i++) {
+            let corner = vec2<f32>(f32(i & 1), f32(i >> 1u)) * 2.0 - 1.0;
+            visible += textureSampleLevel(occlusion, linear_sampler, in.uv + corner * texel, 0.0).r;
+        }
+        color *= visible / 4.0;
+    }
+    if post.bloom != 0u {
+        color += textureSampleLevel(glow, linear_sampler, in.uv, 0.0).rgb * post.bloom_strength;
+    }
+    // Mixed in before tone mapping, like the outlines along voxel edges
+    if post.silhouette != 0u {
+        let edge = textureSampleLevel(occlusion, linear_sampler, in.uv, 0.0).g;
+        color = mix(color, post.outline_color.rgb, edge * post.outline_color.a);
+    }
+    if post.tone_mapping != 0u {
+        color = aces(color * post.exposure);
+    }
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
+
+// Roughly how bright a linear color looks once it's encoded for the screen
+fn perceived(color: vec3<f32>) -> f32 {
+    return sqrt(luminance(color));
+}
+
+// Timothy Lottes' FXAA, blurring along edges found from the contrast between neighbours
+@fragment
+fn fs_present(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let center = sample_source(in.uv);
+    if post.fxaa == 0u {
+        return vec4<f32>(center, 1.0);
+    }
+
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let nw = perceived(sample_source(in.uv + vec2<f32>(-1.0, -1.0) * texel));
+    let ne = perceived(sample_source(in.uv + vec2<f32>(1.0, -1.0) * texel));
+    let sw = perceived(sample_source(in.uv + vec2<f32>(-1.0, 1.0) * texel));
+    let se = perceived(sample_source(in.uv + vec2<f32>(1.0, 1.0) * texel));
+    let m = perceived(center);
+    let luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    let luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    var direction = vec2<f32>(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    let reduce = max((nw + ne + sw + se) * 0.25 * (1.0 / 8.0), 1.0 / 128.0);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-8.0), vec2<f32>(8.0)) * texel;
+
+    let a = 0.5 * (sample_source(in.uv + direction * (1.0 / 3.0 - 0.5))
+        + sample_source(in.uv + direction * (2.0 / 3.0 - 0.5)));
+    let b = a * 0.5 + 0.25 * (sample_source(in.uv - direction * 0.5)
+        + sample_source(in.uv + direction * 0.5));
+
+    // The wider blur crossed another edge, so only the narrow one is kept
+    let luma_b = perceived(b);
+    if luma_b < luma_min || luma_b > luma_max {
+        return vec4<f32>(a, 1.0);
+    }
+    return vec4<f32>(b, 1.0);
+}
@@ -0,0 +1,22 @@
+struct ShadowUniform {
+    light_view_proj: mat4x4<f32>,
+    sun_direction: vec3<f32>,
+    ambient: f32,
+    texel: f32,
+    normal_offset: f32,
+    enabled: u32,
+};
+@group(0) @binding(0)
+var<uniform> shadow: ShadowUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+// Depth only, seen from the sun in mesh space so shadows don't move with the view
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> @builtin(position) vec4<f32> {
+    return shadow.light_view_proj * vec4<f32>(model.position, 1.0);
+}
@@ -0,0 +1,326 @@
+use wgpu::util::DeviceExt;
+
+use crate::utils::{self, consts::*};
+
+use super::{depth, texture::Texture, vertex};
+
+use uniform::ShadowUniform;
+
+/// Meshes always fit in -1 to 1, so the map only has to cover that cube
+const LIGHT_RADIUS: f32 = 1.7320508;
+
+/// How the sun's shadows are drawn, shared by the viewport and exports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Width and height of the shadow map in texels, larger maps give sharper shadows
+    pub resolution: u32,
+    /// Draws a floor under the model to catch its shadow
+    pub ground: bool,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: SHADOW_MAP_SIZE,
+            ground: false,
+        }
+    }
+}
+
+// The derive checks every field with a function that's never called, which only an allow on
+// the whole module keeps from warning as dead code
+#[allow(dead_code)]
+mod uniform {
+    use encase::ShaderType;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone, ShaderType)]
+    pub(super) struct ShadowUniform {
+        pub(super) light_view_proj: glam::Mat4,
+        pub(super) sun_direction: glam::Vec3,
+        pub(super) ambient: f32,
+        /// One texel of the map in texture coordinates
+        pub(super) texel: f32,
+        /// How far surfaces are pushed towards their normal before looking them up, in mesh space
+        pub(super) normal_offset: f32,
+        pub(super) enabled: u32,
+    }
+}
+
+/// Depth of the model seen from the sun, drawn before the main pass and sampled by it
+pub struct ShadowMap {
+    pub settings: ShadowSettings,
+    depth_texture: Texture,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buf: wgpu::Buffer,
+    /// Just the uniform, for drawing into the map
+    pass_bind_group: wgpu::BindGroup,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    /// Uniform and map, for the main pass
+    pub bind_group: wgpu::BindGroup,
+    ground_vertex_buffer: wgpu::Buffer,
+    ground_index_buffer: wgpu::Buffer,
+}
+
+impl ShadowMap {
+    pub fn new(device: &wgpu::Device, settings: ShadowSettings) -> Self {
+        let settings = Self::clamped(settings);
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadow Buffer"),
+            contents: &utils::uniform_buffer_to_bytes(Self::uniform(&settings)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |visibility| wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let pass_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shadow Pass Bind Group Layout"),
+                entries: &[uniform_entry(wgpu::ShaderStages::VERTEX)],
+            });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                uniform_entry(wgpu::ShaderStages::FRAGMENT),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+
+        let pass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Pass Bind Group"),
+            layout: &pass_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&pass_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/shadow.wgsl"));
+
+        // Uses the model's own vertex buffer, only depth is written
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex::vertex_buffer_layout()],
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Just under the model's bottom, so it doesn't fight with the faces resting on it
+        let (e, y) = (GROUND_EXTENT, -1.0 - 1e-3);
+        let ground = [[-e, y, -e], [-e, y, e], [e, y, e], [e, y, -e]]
+            .map(|pos| vertex::Vertex::new(pos, GROUND_COLOR, [0.0, 1.0, 0.0]));
+        let ground_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ground Vertex Buffer"),
+            contents: bytemuck::cast_slice(&ground),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let ground_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ground Index Buffer"),
+            contents: bytemuck::cast_slice(&[0u32, 1, 2, 0, 2, 3]),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let depth_texture = Self::create_map(device, settings.resolution);
+        let bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &uniform_buf, &depth_texture);
+
+        Self {
+            settings,
+            depth_texture,
+            pipeline,
+            uniform_buf,
+            pass_bind_group,
+            bind_group_layout,
+            bind_group,
+            ground_vertex_buffer,
+            ground_index_buffer,
+        }
+    }
+
+    /// Changes how shadows are drawn, only making a new map when its size changes
+    pub fn set_settings(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        settings: ShadowSettings,
+    ) {
+        let settings = Self::clamped(settings);
+        if settings.resolution != self.settings.resolution {
+            self.depth_texture = Self::create_map(device, settings.resolution);
+            self.bind_group = Self::create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.uniform_buf,
+                &self.depth_texture,
+            );
+        }
+
+        self.settings = settings;
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            &utils::uniform_buffer_to_bytes(Self::uniform(&settings)),
+        );
+    }
+
+    /// Draws the model into the map, nothing is drawn while shadows are off
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        vertex_buffer: &wgpu::Buffer,
+        index_buffer: &wgpu::Buffer,
+        index_buffer_size: u32,
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ShadowRenderPass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        if index_buffer_size > 0 {
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.pass_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..index_buffer_size, 0, 0..1);
+        }
+    }
+
+    /// Draws the floor with whatever pipeline is set, if it's turned on
+    pub fn draw_ground<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.settings.enabled && self.settings.ground {
+            render_pass.set_vertex_buffer(0, self.ground_vertex_buffer.slice(..));
+            render_pass.set_index_buffer(
+                self.ground_index_buffer.slice(..),
+                wgpu::IndexFormat::Uint32,
+            );
+            render_pass.draw_indexed(0..6, 0, 0..1);
+        }
+    }
+
+    fn clamped(settings: ShadowSettings) -> ShadowSettings {
+        ShadowSettings {
+            resolution: settings
+                .resolution
+                .clamp(SHADOW_MAP_SIZE_MIN, SHADOW_MAP_SIZE_MAX),
+            ..settings
+        }
+    }
+
+    fn uniform(settings: &ShadowSettings) -> ShadowUniform {
+        let texel = 1.0 / settings.resolution.max(1) as f32;
+        ShadowUniform {
+            light_view_proj: light_view_proj(),
+            sun_direction: glam::Vec3::from_array(SUN_DIRECTION).normalize(),
+            ambient: SHADOW_AMBIENT,
+            texel,
+            normal_offset: LIGHT_RADIUS * 2.0 * texel * 1.5,
+            enabled: settings.enabled as u32,
+        }
+    }
+
+    fn create_map(device: &wgpu::Device, resolution: u32) -> Texture {
+        depth::create_depth_texture_sized(device, resolution, resolution, 1, "shadow_map")
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buf: &wgpu::Buffer,
+        depth_texture: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&depth_texture.sampler),
+                },
+            ],
+        })
+    }
+}
+
+/// Mesh space as seen from the sun, looking down at the model from outside the cube it fits in
+pub fn light_view_proj() -> glam::Mat4 {
+    let sun = glam::Vec3::from_array(SUN_DIRECTION).normalize();
+    let up = match sun.y.abs() > 0.99 {
+        true => glam::Vec3::Z,
+        false => glam::Vec3::Y,
+    };
+    let view = glam::Mat4::look_at_rh(sun * LIGHT_RADIUS * 2.0, glam::Vec3::ZERO, up);
+
+    // Deep enough to reach the far corners of the floor
+    let (r, far) = (LIGHT_RADIUS, LIGHT_RADIUS * 3.0 + GROUND_EXTENT * 2.0);
+    glam::Mat4::orthographic_rh(-r, r, -r, r, 0.0, far) * view
+}
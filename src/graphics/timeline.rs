@@ -0,0 +1,193 @@
+use glam::vec2;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    animation::{playback::Playback, Animation},
+    utils::consts::*,
+};
+
+use super::{input, overlay::OverlayRenderer};
+
+const LABEL_SCALE: f32 = 2.0;
+
+const BACKGROUND_COLOR: [f32; 4] = [0.08, 0.08, 0.1, 0.85];
+const TICK_COLOR: [f32; 4] = [0.45, 0.45, 0.5, 1.0];
+const KEYFRAME_COLOR: [f32; 4] = [0.9, 0.8, 0.3, 1.0];
+const LOOP_COLOR: [f32; 4] = [0.2, 0.45, 0.9, 0.35];
+const PLAYHEAD_COLOR: [f32; 4] = [1.0, 0.35, 0.2, 1.0];
+const CAMERA_KEY_COLOR: [f32; 4] = [0.45, 0.75, 1.0, 1.0];
+const MARKER_COLOR: [f32; 4] = [0.35, 0.85, 0.45, 1.0];
+const LABEL_BACKGROUND_COLOR: [f32; 4] = [0.08, 0.08, 0.1, 0.7];
+
+/// Strip along the bottom of the window showing every tick of the animation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timeline {
+    pub scrubbing: bool,
+}
+
+impl Timeline {
+    /// Top left and bottom right corners of the strip
+    pub fn bounds(size: PhysicalSize<u32>) -> (glam::Vec2, glam::Vec2) {
+        let (w, h) = (size.width as f32, size.height as f32);
+        (vec2(0.0, (h - TIMELINE_HEIGHT).max(0.0)), vec2(w, h))
+    }
+
+    /// Width taken up by a single frame
+    fn frame_width(size: PhysicalSize<u32>, frame_count: usize) -> f32 {
+        (size.width as f32 - TIMELINE_MARGIN * 2.0).max(1.0) / frame_count.max(1) as f32
+    }
+
+    /// Horizontal center of a frame
+    pub fn frame_x(frame: usize, frame_count: usize, size: PhysicalSize<u32>) -> f32 {
+        TIMELINE_MARGIN + (frame as f32 + 0.5) * Self::frame_width(size, frame_count)
+    }
+
+    pub fn frame_at(x: f32, frame_count: usize, size: PhysicalSize<u32>) -> usize {
+        let frame = ((x - TIMELINE_MARGIN) / Self::frame_width(size, frame_count)).floor();
+        (frame.max(0.0) as usize).min(frame_count.saturating_sub(1))
+    }
+
+    /// Scrubs to the frame under the mouse while the left button is held after clicking the
+    /// strip, returning whether scrubbing is in progress
+    pub fn update(
+        &mut self,
+        size: PhysicalSize<u32>,
+        animation: &Animation,
+        playback: &mut Playback,
+    ) -> bool {
+        let pos = input::get_mouse_position();
+        let frame = self.scrub(
+            vec2(pos.x as f32, pos.y as f32),
+            input::is_mouse_pressed(input::InputMouseButton::Left),
+            input::is_mouse_button_down(input::InputMouseButton::Left),
+            animation.duration() as usize,
+            size,
+        );
+
+        if let Some(frame) = frame {
+            playback.seek(frame, animation);
+        }
+
+        self.scrubbing
+    }
+
+    /// Frame to scrub to with the mouse at `pos` and the left button just `pressed` or held
+    /// `down`. Scrubbing starts by clicking the strip and keeps following the mouse anywhere in
+    /// the window until the button is let go.
+    pub fn scrub(
+        &mut self,
+        pos: glam::Vec2,
+        pressed: bool,
+        down: bool,
+        frame_count: usize,
+        size: PhysicalSize<u32>,
+    ) -> Option<usize> {
+        let (min, max) = Self::bounds(size);
+        if pressed && pos.cmpge(min).all() && pos.cmple(max).all() {
+            self.scrubbing = true;
+        }
+        if !down {
+            self.scrubbing = false;
+        }
+
+        match self.scrubbing {
+            true => Some(Self::frame_at(pos.x, frame_count, size)),
+            false => None,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        overlay: &mut OverlayRenderer,
+        size: PhysicalSize<u32>,
+        animation: &Animation,
+        active_track: usize,
+        playback: &Playback,
+    ) {
+        let (min, max) = Self::bounds(size);
+        overlay.draw_rect(min, max, BACKGROUND_COLOR);
+
+        let count = animation.duration() as usize;
+        if count == 0 {
+            return;
+        }
+
+        let step = Self::frame_width(size, count);
+        let x = |frame: usize| Self::frame_x(frame, count, size);
+
+        // Loop range
+        if playback.loop_range.is_some() {
+            let (start, end) = playback.loop_bounds(animation);
+            overlay.draw_rect(
+                vec2(x(start) - step / 2.0, min.y + 4.0),
+                vec2(x(end) + step / 2.0, max.y - 4.0),
+                LOOP_COLOR,
+            );
+        }
+
+        // Thin out ticks when frames get too close together to tell apart
+        let stride = (3.0 / step).ceil().max(1.0) as usize;
+        for i in (0..count).step_by(stride) {
+            let height = match i % 5 {
+                0 => 16.0,
+                _ => 8.0,
+            };
+            overlay.draw_rect(
+                vec2(x(i).floor(), max.y - 6.0 - height),
+                vec2(x(i).floor() + 1.0, max.y - 6.0),
+                TICK_COLOR,
+            );
+        }
+
+        // Markers, named above the strip so engines' events can be lined up with the motion
+        let mut label_end = f32::NEG_INFINITY;
+        for marker in &animation.markers {
+            let mx = x((marker.frame as usize).min(count - 1)).floor();
+            overlay.draw_rect(vec2(mx, min.y), vec2(mx + 1.0, max.y), MARKER_COLOR);
+            overlay.draw_rect(vec2(mx, min.y), vec2(mx + 6.0, min.y + 4.0), MARKER_COLOR);
+
+            // Skip names that would overlap the previous one
+            let size = OverlayRenderer::text_size(&marker.name, LABEL_SCALE);
+            if mx <= label_end {
+                continue;
+            }
+            let pos = vec2(mx, min.y - size.y - 6.0);
+            overlay.draw_rect(pos - 2.0, pos + size + 2.0, LABEL_BACKGROUND_COLOR);
+            overlay.draw_text(pos, &marker.name, LABEL_SCALE, MARKER_COLOR);
+            label_end = pos.x + size.x + 4.0;
+        }
+
+        // Camera keys
+        for key in &animation.camera.keys {
+            let kx = x((key.frame as usize).min(count - 1)).floor();
+            overlay.draw_rect(
+                vec2(kx - 2.0, max.y - 5.0),
+                vec2(kx + 3.0, max.y),
+                CAMERA_KEY_COLOR,
+            );
+        }
+
+        // Playhead
+        let px = x(playback.frame).floor();
+        overlay.draw_rect(vec2(px - 1.0, min.y), vec2(px + 1.0, max.y), PLAYHEAD_COLOR);
+
+        // Frames of the track being edited that change something, as opposed to holding the
+        // previous one
+        let track = match animation.tracks.get(active_track) {
+            Some(t) => t,
+            None => return,
+        };
+        for i in 0..track.len() {
+            if track.frames.is_hold(i) {
+                continue;
+            }
+
+            let start = (track.frame_start(i) as usize).min(count - 1);
+            overlay.draw_rect(
+                vec2(x(start) - 3.0, min.y + 6.0),
+                vec2(x(start) + 3.0, min.y + 12.0),
+                KEYFRAME_COLOR,
+            );
+        }
+    }
+}
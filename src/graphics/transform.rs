@@ -1,49 +1,6 @@
-use encase::ShaderType;
 use glam::{mat4, vec4};
-use wgpu::util::DeviceExt;
 
-use crate::utils::{self, consts};
-
-use super::{vertex, wgpu_object::WgpuObject};
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone, ShaderType)]
-pub struct TransformUniform {
-    pub zoom: f32,
-    zoom_factor: f32,
-    pub pan: glam::Vec3,
-}
-
-impl Default for TransformUniform {
-    fn default() -> Self {
-        Self {
-            zoom: -5.0,
-            zoom_factor: 0.0,
-            pan: glam::Vec3::ZERO,
-        }
-    }
-}
-
-impl TransformUniform {
-    pub fn create_staging_buffer(self, device: &wgpu::Device) -> wgpu::Buffer {
-        let mut uniform = self;
-
-        uniform.zoom_factor = (1.0 + consts::ZOOM_SENS).powf(self.zoom);
-
-        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Tranform Staging Buffer"),
-            contents: &utils::uniform_buffer_to_bytes(uniform),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_SRC,
-        })
-    }
-}
-
-pub fn clamped_rotation(wobj: &WgpuObject, clamp: f32) -> glam::Vec3 {
-    wobj.cam_rotation.clamp(
-        glam::vec3(f32::NEG_INFINITY, -clamp, f32::NEG_INFINITY),
-        glam::vec3(f32::INFINITY, clamp, f32::INFINITY),
-    )
-}
+use super::vertex;
 
 pub fn trans_mat(x: f32, y: f32, z: f32) -> glam::Mat4 {
     mat4(
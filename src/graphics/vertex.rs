@@ -11,11 +11,22 @@ pub struct Vertex {
     pub pos: [f32; 3],
     pub color: [f32; 4],
     pub normal: [f32; 3],
+    /// How much light the surface gives off on top of what it reflects
+    pub emission: f32,
 }
 
 impl Vertex {
     pub fn new(pos: [f32; 3], color: [f32; 4], normal: [f32; 3]) -> Self {
-        Self { pos, color, normal }
+        Self {
+            pos,
+            color,
+            normal,
+            emission: 0.0,
+        }
+    }
+
+    pub fn with_emission(self, emission: f32) -> Self {
+        Self { emission, ..self }
     }
 }
 
@@ -24,6 +35,11 @@ pub struct BufferOutput {
     pub vbo_size: u32,
     pub idxbuf: Buffer,
     pub idx_size: u32,
+    /// Indices of opaque faces, which come first
+    pub opaque_size: u32,
+    /// Opaque faces as triangles even in wireframe, for the shadow pass
+    pub shadow_idxbuf: Buffer,
+    pub shadow_size: u32,
 }
 
 pub fn new_vbo(device: &wgpu::Device) -> wgpu::Buffer {
@@ -47,14 +63,10 @@ pub fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     vertex_buffer_layout
 }
 
-pub fn create_buffers(
-    model: (Vec<Vertex>, Vec<u32>),
-    device: &wgpu::Device,
-    wireframe: bool,
-) -> BufferOutput {
+pub fn create_buffers(mesh: &regen::Mesh, device: &wgpu::Device, wireframe: bool) -> BufferOutput {
     let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("VertexBuffer"),
-        contents: bytemuck::cast_slice(&model.0),
+        contents: bytemuck::cast_slice(&mesh.vertices),
         usage: wgpu::BufferUsages::VERTEX,
     });
 
@@ -62,7 +74,7 @@ pub fn create_buffers(
         true => {
             let mut indices = vec![];
 
-            for i in model.1.chunks(3) {
+            for i in mesh.indices.chunks(3) {
                 indices.push(i[0]);
                 indices.push(i[1]);
                 indices.push(i[1]);
@@ -72,12 +84,24 @@ pub fn create_buffers(
             }
             indices
         }
-        false => model.1.to_vec(),
+        false => mesh.indices.to_vec(),
+    };
+    // Every triangle turns into three lines
+    let opaque_size = match wireframe {
+        true => mesh.opaque * 2,
+        false => mesh.opaque,
     };
 
     let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Index Buffer"),
         contents: bytemuck::cast_slice(&indices),
+        // Translucent faces are rewritten as they're sorted
+        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+    });
+
+    let shadow_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Shadow Index Buffer"),
+        contents: bytemuck::cast_slice(&mesh.indices[..mesh.opaque]),
         usage: wgpu::BufferUsages::INDEX,
     });
 
@@ -85,8 +109,11 @@ pub fn create_buffers(
 
     BufferOutput {
         vbo: vertex_buffer,
-        vbo_size: model.0.len() as u32,
+        vbo_size: mesh.vertices.len() as u32,
         idxbuf: index_buffer,
         idx_size: indices.len() as u32,
+        opaque_size: opaque_size as u32,
+        shadow_idxbuf: shadow_index_buffer,
+        shadow_size: mesh.opaque as u32,
     }
 }
@@ -1,16 +1,42 @@
-use std::f32::consts::PI;
-
-use winit::{keyboard::KeyCode, window::Window};
+use anyhow::Context;
+use winit::{
+    keyboard::KeyCode,
+    window::{CursorGrabMode, Window},
+};
 
 use crate::{
-    models::{model, regen},
+    animation::{
+        camera::CameraKey,
+        marker::{self, Marker},
+        playback::Playback,
+        project, Animation,
+    },
+    export::{
+        self,
+        path_tracer::{PathTracer, Progressive},
+        sprite_sheet::SheetOptions,
+        stack::StackLayout,
+        ExportCamera, ExportOptions,
+    },
+    models::{model::Model, regen},
     utils::{
-        cgv3_to_gv3,
-        consts::{ROT_CLAMP, ROT_SENS_X, ROT_SENS_Y},
+        consts::{
+            EXPORT_DIR, EXPOSURE_STEP, FLY_LOOK_SENS, FLY_SPEED, FLY_SPEED_FAST, FLY_SPEED_SLOW,
+            NAVIGATION_PATH, OUTLINE_COLORS, OUTLINE_THICKNESS, OUTLINE_THICKNESS_MAX,
+            PATH_TRACE_SAMPLES_PER_FRAME, PROJECT_PATH, SHADOW_MAP_SIZE_MAX, SHADOW_MAP_SIZE_MIN,
+            SPRITE_DIRECTIONS, SPRITE_SIZE,
+        },
+        image::Image,
+        log::{log, LogLevel},
     },
 };
 
-use super::{cam, init, input, lines, transform, vertex};
+use super::{
+    cam::{self, ViewPreset},
+    guides, headless, init, input, lines,
+    navigation::{self, Modifiers, NavAction, NavigationPreset},
+    outline, overlay, post, shadow, timeline, vertex,
+};
 
 pub struct WgpuObject<'a> {
     pub surface: wgpu::Surface<'a>,
@@ -20,30 +46,54 @@ pub struct WgpuObject<'a> {
     pub size: winit::dpi::PhysicalSize<u32>,
     pub window: &'a Window,
     pub pipeline: wgpu::RenderPipeline,
+    pub translucent_pipeline: wgpu::RenderPipeline,
     pub pipeline_layout: wgpu::PipelineLayout,
     pub shader: wgpu::ShaderModule,
     pub vertex_buffer: wgpu::Buffer,
     pub vertex_buffer_size: u32,
     pub index_buffer: wgpu::Buffer,
     pub index_buffer_size: u32,
+    /// Indices of opaque faces, drawn before the translucent ones after them
+    pub opaque_index_size: u32,
+    /// Opaque faces as triangles, which the shadow pass needs even in wireframe
+    pub shadow_index_buffer: wgpu::Buffer,
+    pub shadow_index_size: u32,
+    pub mesh: regen::Mesh,
+    /// View the translucent faces in the index buffer were last sorted for
+    pub sorted_depth_axis: Option<glam::Vec3>,
     pub cam: cam::Camera,
     pub cam_uniform: cam::CameraUniform,
     pub cam_buf: wgpu::Buffer,
     pub cam_staging_buf: Option<wgpu::Buffer>,
-    pub transform_uniform: transform::TransformUniform,
-    pub transform_buf: wgpu::Buffer,
-    pub restage_transform: bool,
-    pub transform_staging_buf: Option<wgpu::Buffer>,
     pub uniform_bind_group: wgpu::BindGroup,
     pub msaa_buffer: wgpu::TextureView,
-    pub msaa_bundle: wgpu::RenderBundle,
     pub depth_texture: super::texture::Texture,
+    pub shadow_map: shadow::ShadowMap,
+    pub outlines: outline::Outlines,
+    pub post: post::PostProcessor,
     pub wireframe: bool,
-    pub cam_rotation: glam::Vec3,
-    pub cam_pos: glam::Vec3,
     pub cam_temp: cam::CamTemp,
+    /// Mouse bindings moving the camera, and the preset F8 last switched to
+    pub navigation: navigation::NavigationBindings,
+    pub navigation_preset: navigation::NavigationPreset,
+    /// Bindings from `NAVIGATION_PATH`, kept while trying out the presets
+    pub custom_navigation: Option<navigation::NavigationBindings>,
     pub delta_time: f32,
     pub line_rendering: lines::LineRendering,
+    /// Grid, axis gizmo, bounding box and normals drawn around the model
+    pub guides: guides::GuideSettings,
+    pub animation: Animation,
+    pub playback: Playback,
+    /// Index of the track that frame editing and the timeline work on
+    pub active_track: usize,
+    pub model: Model,
+    pub title: String,
+    pub overlay: overlay::OverlayRenderer,
+    pub timeline: timeline::Timeline,
+    /// Whether the view follows the animation's camera track
+    pub camera_preview: bool,
+    /// Still being path traced, and the frame it's of
+    pub still: Option<(u32, Progressive)>,
 }
 
 impl WgpuObject<'_> {
@@ -54,102 +104,941 @@ impl WgpuObject<'_> {
     }
 
     pub fn update(&mut self) {
+        if input::is_key_pressed(KeyCode::Backquote)
+            || (self.cam_temp.flying && input::is_key_pressed(KeyCode::Escape))
+        {
+            self.set_flying(!self.cam_temp.flying);
+        }
+
         // Camera movement
-        if input::is_mouse_button_down(input::InputMouseButton::Middle) {
-            let x = input::get_mouse_delta_range(self.size).0 * self.delta_time;
-            let y = input::get_mouse_delta_range(self.size).1 * self.delta_time;
+        if self.cam_temp.flying {
+            self.update_fly();
+        } else {
+            self.update_navigation();
+        }
+        self.update_navigation_scheme();
+
+        let scroll = input::get_scroll_delta();
+        if scroll != 0.0 {
+            self.cam_temp.transition = None;
+            self.cam.zoom(scroll * self.navigation.zoom_speed);
+            self.cam_staging_buf = Some(self.cam.create_staging_buffer(&self.device));
+        }
+
+        self.update_view_presets();
+        self.update_framing();
+        self.update_bookmarks();
+        self.update_transition();
+
+        let frame_changed = self.update_playback();
+        if frame_changed {
+            match self.animation.sample(self.playback.frame as f32) {
+                Some(model) => {
+                    self.model = model;
+                    self.rebuild_mesh();
+                }
+                None => log(
+                    format!("Nothing to show at frame {}", self.playback.frame + 1),
+                    LogLevel::ERROR,
+                ),
+            }
+        }
+        self.update_camera_track(frame_changed);
+        self.update_still();
+        self.update_title();
+
+        self.overlay.clear();
+        self.update_guides();
+        self.timeline.draw(
+            &mut self.overlay,
+            self.size,
+            &self.animation,
+            self.active_track,
+            &self.playback,
+        );
+
+        // Wireframe
+        if input::is_key_pressed(KeyCode::F1) {
+            self.wireframe = !self.wireframe;
+            self.rebuild_mesh();
+            self.rebuild_pipelines();
+        }
 
-            // Recalculate flip factor if first frame button is held
-            if !self.cam_temp.button_held_last_frame {
-                // Is camera upside down?
-                self.cam_temp.cam_flipped = self.cam_rotation.y < -(PI / 2.0);
+        // Shadows, the floor catching them and how sharp they are
+        if input::is_key_pressed(KeyCode::F2) {
+            let mut settings = self.shadow_map.settings;
+            if input::is_ctrl_down() {
+                settings.resolution = match settings.resolution >= SHADOW_MAP_SIZE_MAX {
+                    true => SHADOW_MAP_SIZE_MIN,
+                    false => settings.resolution * 2,
+                };
+                log(
+                    format!("Shadow map is {0}x{0}", settings.resolution),
+                    LogLevel::INFO,
+                );
+            } else if input::is_shift_down() {
+                settings.ground = !settings.ground;
+            } else {
+                settings.enabled = !settings.enabled;
             }
+            self.shadow_map
+                .set_settings(&self.device, &self.queue, settings);
+        }
 
-            let throttle = input::is_alt_down();
+        // Voxel edge outlines, how thick they are and their color
+        if input::is_key_pressed(KeyCode::F7) {
+            let mut settings = self.outlines.settings;
+            if input::is_ctrl_down() {
+                let next = OUTLINE_COLORS
+                    .iter()
+                    .position(|c| *c == settings.color)
+                    .map_or(0, |i| (i + 1) % OUTLINE_COLORS.len());
+                settings.color = OUTLINE_COLORS[next];
+            } else if input::is_shift_down() {
+                settings.thickness = match settings.thickness >= OUTLINE_THICKNESS_MAX {
+                    true => OUTLINE_THICKNESS,
+                    false => settings.thickness + 1.0,
+                };
+                log(
+                    format!("Outlines are {}px thick", settings.thickness),
+                    LogLevel::INFO,
+                );
+            } else {
+                settings.enabled = !settings.enabled;
+            }
+            self.outlines.set_settings(&self.queue, settings);
+            self.post.set_outlines(&self.queue, &self.outlines.settings);
+        }
 
-            match input::is_shift_down() {
-                // Pan
-                true => {
-                    let speed = crate::utils::consts::PAN_SENS;
-                    let x = x * speed;
-                    let y = -y * speed;
+        self.update_post();
+        self.sort_translucent();
 
-                    // self.cam.rebuild_up(self.cam_rotation.y);
-                    let translation = self.cam.get_right() * x + cgv3_to_gv3(self.cam.up * y);
-                    self.cam_pos += glam::vec3(translation.x, translation.y, translation.z);
+        if self.cam_staging_buf.is_some() {
+            self.post.set_camera(&self.queue, &self.cam);
+        }
+
+        input::input_update();
+    }
+
+    /// F9 to F12 switch the ground grid, axis gizmo, bounding box and normals, which are drawn
+    /// again every frame to follow the camera and model
+    fn update_guides(&mut self) {
+        let settings = &mut self.guides;
+        for (key, enabled) in [
+            (KeyCode::F9, &mut settings.grid),
+            (KeyCode::F10, &mut settings.gizmo),
+            (KeyCode::F11, &mut settings.bounds),
+            (KeyCode::F12, &mut settings.normals),
+        ] {
+            if input::is_key_pressed(key) {
+                *enabled = !*enabled;
+            }
+        }
+
+        let size = glam::vec2(self.size.width as f32, self.size.height as f32);
+        let voxel = self.model.mesh_scale();
+
+        self.line_rendering.clear_lines_bg();
+        if self.guides.grid {
+            for line in guides::grid_lines(&self.cam, voxel) {
+                self.line_rendering
+                    .draw_line_bg(line.pos0, line.pos1, line.color);
+            }
+        }
+
+        self.line_rendering.clear_lines_fg();
+        if self.guides.bounds {
+            if let Some(bounds) = self.model.mesh_bounds(|_| true) {
+                for line in guides::box_lines(bounds.0, bounds.1) {
+                    self.line_rendering
+                        .draw_line_fg(line.pos0, line.pos1, line.color);
+                }
+                guides::draw_dimensions(&mut self.overlay, &self.cam, size, bounds, voxel);
+            }
+        }
+        if self.guides.normals {
+            for x in regen::gen_vert_idx(&self.model).0 {
+                self.line_rendering.draw_line_fg(
+                    x.pos.into(),
+                    glam::Vec3::from_array(x.pos) + glam::Vec3::from_array(x.normal),
+                    [0.0, 1.0, 0.0, 1.0],
+                );
+            }
+        }
+
+        if self.guides.gizmo {
+            guides::draw_gizmo(&mut self.overlay, &self.cam, size);
+        }
+    }
 
-                    // Send data to GPU
-                    self.transform_uniform.pan = self.cam_pos.into();
-                    self.restage_transform = true;
+    /// Orbits, pans and zooms with whichever mouse bindings the navigation scheme has. A drag
+    /// keeps doing what it started as until its button is let go.
+    fn update_navigation(&mut self) {
+        let nav = &self.navigation;
+        let held = Modifiers::held();
+        let dragging = self.cam_temp.drag.is_some();
+        self.cam_temp.drag = match self.cam_temp.drag {
+            Some((_, button)) if input::is_mouse_button_down(button) => self.cam_temp.drag,
+            _ => nav.action(input::is_mouse_button_down, held),
+        };
+        let Some((action, _)) = self.cam_temp.drag else {
+            return;
+        };
+
+        let (x, y) = input::get_mouse_delta_range(self.size);
+        let throttle_factor = match nav.throttled(held) {
+            true => nav.throttle_speed,
+            false => 1.0,
+        };
+
+        // Moving the view by hand takes over from any preset it was easing to
+        self.cam_temp.transition = None;
+
+        // Recalculate flip factor on the first frame of a drag
+        if !dragging {
+            // Is camera upside down?
+            self.cam_temp.cam_flipped = self.cam.upside_down();
+        }
+
+        match action {
+            // Orbit, over the poles too, turning the other way while upside down
+            NavAction::Orbit => {
+                let x = x * self.delta_time * nav.orbit_speed.x * throttle_factor;
+                let y = y * self.delta_time * nav.orbit_speed.y * throttle_factor;
+
+                match self.cam_temp.cam_flipped {
+                    true => self.cam.orbit(x, y),
+                    false => self.cam.orbit(-x, y),
                 }
-                // Orbit
+            }
+            // Pan, dragging what's under the cursor along with it
+            NavAction::Pan => {
+                let speed = self.delta_time * nav.pan_speed * throttle_factor;
+                self.cam.pan(-x * speed, y * speed);
+            }
+            // Zoom, in as the mouse moves up
+            NavAction::Zoom => self.cam.zoom(-y * nav.drag_zoom_speed * throttle_factor),
+        }
+
+        self.cam_staging_buf = Some(self.cam.create_staging_buffer(&self.device));
+    }
+
+    /// F8 cycles the navigation presets and any custom bindings, or with Shift writes the
+    /// bindings out to be customized
+    fn update_navigation_scheme(&mut self) {
+        if input::is_key_pressed(KeyCode::F8) {
+            match input::is_shift_down() {
+                true => match self.navigation.save(NAVIGATION_PATH) {
+                    Ok(_) => {
+                        self.custom_navigation = Some(self.navigation.clone());
+                        self.navigation_preset = NavigationPreset::Custom;
+                        log(format!("Saved {}", NAVIGATION_PATH), LogLevel::INFO);
+                    }
+                    Err(e) => log(format!("{:#}", e), LogLevel::ERROR),
+                },
+                // Cycling through the presets comes back around to the custom bindings
                 false => {
-                    let throttle_factor = match throttle {
-                        true => crate::utils::consts::ROT_SENS_THROTTLE,
-                        false => 1.0,
+                    let custom = self.custom_navigation.as_ref();
+                    self.navigation_preset = self.navigation_preset.next(custom.is_some());
+                    self.navigation = match (self.navigation_preset, custom) {
+                        (NavigationPreset::Custom, Some(bindings)) => bindings.clone(),
+                        (preset, _) => preset.bindings(),
                     };
+                    self.cam_temp.drag = None;
+                    log(
+                        format!("{:?} navigation", self.navigation_preset),
+                        LogLevel::INFO,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Switches between orbiting and flying, carrying on from the same view. The cursor is
+    /// grabbed and hidden while flying so it can keep turning the view.
+    pub fn set_flying(&mut self, flying: bool) {
+        if flying == self.cam_temp.flying {
+            return;
+        }
+        self.cam_temp.flying = flying;
+
+        let grab = match flying {
+            true => self
+                .window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined)),
+            false => self.window.set_cursor_grab(CursorGrabMode::None),
+        };
+        if let Err(e) = grab {
+            log(
+                format!("Couldn't grab the cursor: {}", e),
+                LogLevel::WARNING,
+            );
+        }
+        self.window.set_cursor_visible(!flying);
 
-                    let x = x * ROT_SENS_X * throttle_factor;
+        match flying {
+            true => log("Fly mode", LogLevel::INFO),
+            false => log("Orbit mode", LogLevel::INFO),
+        }
+    }
 
-                    let y = y * ROT_SENS_Y * throttle_factor;
+    /// WASD moves across the view and Q and E down and up, faster with Shift and slower with Alt,
+    /// while the mouse looks around
+    fn update_fly(&mut self) {
+        let (x, y) = input::get_mouse_motion();
+        let mut moved = x != 0.0 || y != 0.0;
+        if moved {
+            self.cam.look(-x * FLY_LOOK_SENS, y * FLY_LOOK_SENS);
+        }
 
-                    self.cam_rotation.y = (self.cam_rotation.y + y).clamp(-ROT_CLAMP, ROT_CLAMP);
+        // Ctrl is left to the shortcuts sharing these keys
+        let axis = |negative, positive| {
+            input::is_key_down(positive) as i32 as f32 - input::is_key_down(negative) as i32 as f32
+        };
+        let direction = glam::vec3(
+            axis(KeyCode::KeyA, KeyCode::KeyD),
+            axis(KeyCode::KeyQ, KeyCode::KeyE),
+            axis(KeyCode::KeyS, KeyCode::KeyW),
+        );
+        if direction != glam::Vec3::ZERO && !input::is_ctrl_down() {
+            let speed = match (input::is_shift_down(), input::is_alt_down()) {
+                (true, _) => FLY_SPEED * FLY_SPEED_FAST,
+                (_, true) => FLY_SPEED * FLY_SPEED_SLOW,
+                _ => FLY_SPEED,
+            };
+            self.cam
+                .fly(direction.normalize() * speed * self.delta_time);
+            moved = true;
+        }
 
-                    self.cam_rotation.x = (self.cam_rotation.x + x) % (2.0 * PI);
-                }
+        // Flying by hand takes over from any view it was easing to
+        if moved {
+            self.cam_temp.transition = None;
+            self.cam_staging_buf = Some(self.cam.create_staging_buffer(&self.device));
+        }
+    }
+
+    /// Numpad views and projection, eased into rather than jumped to. Views along an axis or from
+    /// a corner are orthographic, which lines voxels up with the screen.
+    fn update_view_presets(&mut self) {
+        let ctrl = input::is_ctrl_down();
+        let preset = [
+            (KeyCode::Numpad1, ViewPreset::Front, ViewPreset::Back),
+            (KeyCode::Numpad3, ViewPreset::Right, ViewPreset::Left),
+            (KeyCode::Numpad7, ViewPreset::Top, ViewPreset::Bottom),
+            (
+                KeyCode::Numpad0,
+                ViewPreset::Isometric,
+                ViewPreset::Isometric,
+            ),
+        ]
+        .into_iter()
+        .find(|(key, _, _)| input::is_key_pressed(*key))
+        .map(|(_, preset, opposite)| match ctrl {
+            true => opposite,
+            false => preset,
+        });
+
+        // Heads for wherever the view is already going, so presses during a transition add up
+        let mut to = self.cam_target();
+        match preset {
+            Some(preset) => {
+                log(format!("{:?} view", preset), LogLevel::INFO);
+                to.look_from(preset.direction());
+                to.orthographic = 1.0;
+            }
+            None if input::is_key_pressed(KeyCode::Numpad5) => {
+                to.orthographic = 1.0 - to.orthographic.round();
+            }
+            None => return,
+        }
+        self.transition_to(to);
+    }
+
+    /// Fits what the active track works on at this frame into the view, or with Shift everything
+    /// the animation ever fills
+    fn update_framing(&mut self) {
+        if !input::is_key_pressed(KeyCode::KeyF) {
+            return;
+        }
+
+        let bounds = match input::is_shift_down() {
+            true => (0..self.animation.duration())
+                .filter_map(|tick| self.animation.sample(tick as f32)?.mesh_bounds(|_| true))
+                .reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max))),
+            false => match self.animation.tracks.get(self.active_track) {
+                Some(track) => self.model.mesh_bounds(|layer| track.target.contains(layer)),
+                None => None,
+            },
+        };
+
+        match bounds {
+            Some((min, max)) => self.transition_to(self.cam_target().framing(min, max)),
+            None => log("Nothing to frame", LogLevel::WARNING),
+        }
+    }
+
+    /// Number keys go back to views saved to them with Ctrl, which are kept with the project
+    fn update_bookmarks(&mut self) {
+        const KEYS: [KeyCode; 9] = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+            KeyCode::Digit6,
+            KeyCode::Digit7,
+            KeyCode::Digit8,
+            KeyCode::Digit9,
+        ];
+        let Some(slot) = KEYS.iter().position(|key| input::is_key_pressed(*key)) else {
+            return;
+        };
+
+        if input::is_ctrl_down() {
+            self.animation.camera.bookmarks[slot] = Some(self.cam_target().to_key(0));
+            log(format!("Saved view {}", slot + 1), LogLevel::INFO);
+            return;
+        }
+        match self.animation.camera.bookmarks[slot] {
+            Some(key) => self.transition_to(cam::Camera::from_key(&key, self.cam.aspect)),
+            None => log(format!("No view saved to {}", slot + 1), LogLevel::WARNING),
+        }
+    }
+
+    /// View the camera is at or easing towards
+    fn cam_target(&self) -> cam::Camera {
+        match &self.cam_temp.transition {
+            Some(transition) => transition.target(),
+            None => self.cam,
+        }
+    }
+
+    /// Eases the camera to a view, keeping up with the window's aspect if it changes on the way
+    fn transition_to(&mut self, to: cam::Camera) {
+        self.cam_temp.transition = Some(cam::CamTransition::new(self.cam, to));
+    }
+
+    /// Moves the camera along its transition, if it has one
+    fn update_transition(&mut self) {
+        if let Some(transition) = &mut self.cam_temp.transition {
+            let (camera, done) = transition.advance(self.delta_time);
+            self.cam = cam::Camera {
+                aspect: self.cam.aspect,
+                ..camera
+            };
+            if done {
+                self.cam_temp.transition = None;
             }
 
-            self.cam.apply_transforms(&self.cam_rotation);
             self.cam_staging_buf = Some(self.cam.create_staging_buffer(&self.device));
-            self.cam_temp.button_held_last_frame = true;
-        } else {
-            self.cam_temp.button_held_last_frame = false;
+        }
+    }
+
+    /// Post-processing passes and exposure
+    fn update_post(&mut self) {
+        let mut settings = self.post.settings;
+        if input::is_key_pressed(KeyCode::F3) {
+            settings.ssao = !settings.ssao;
+        }
+        if input::is_key_pressed(KeyCode::F4) {
+            settings.bloom = !settings.bloom;
+        }
+        if input::is_key_pressed(KeyCode::F5) {
+            settings.fxaa = !settings.fxaa;
+        }
+        if input::is_key_pressed(KeyCode::F6) {
+            if input::is_shift_down() {
+                settings.exposure *= EXPOSURE_STEP;
+            } else if input::is_ctrl_down() {
+                settings.exposure /= EXPOSURE_STEP;
+            } else {
+                settings.tone_mapping = !settings.tone_mapping;
+            }
+        }
+        if settings == self.post.settings {
+            return;
+        }
+
+        let (resample, exposure) = (
+            settings.fxaa != self.post.settings.fxaa,
+            self.post.settings.exposure,
+        );
+        self.post.set_settings(&self.queue, settings);
+        if self.post.settings.exposure != exposure {
+            log(
+                format!("Exposure is {}", self.post.settings.exposure),
+                LogLevel::INFO,
+            );
         }
 
-        let prezoom = self.transform_uniform.zoom;
-        self.transform_uniform.zoom += input::get_scroll_delta();
-        if self.transform_uniform.zoom != prezoom {
-            self.restage_transform = true;
+        // FXAA replaces multisampling, so everything drawn into the scene changes sample count
+        if resample {
+            self.rebuild_targets();
+            self.rebuild_pipelines();
         }
+    }
 
+    /// Remakes what the scene is drawn with, for wireframe and sample count changes
+    fn rebuild_pipelines(&mut self) {
+        let config = post::scene_config(&self.config);
+        let sample_count = self.post.settings.sample_count();
+        self.pipeline = init::create_render_pipeline_with_samples(
+            &self.device,
+            &self.pipeline_layout,
+            &self.shader,
+            &config,
+            self.wireframe,
+            sample_count,
+        );
+        self.translucent_pipeline = init::create_translucent_pipeline(
+            &self.device,
+            &self.pipeline_layout,
+            &self.shader,
+            &config,
+            self.wireframe,
+            sample_count,
+        );
+        self.line_rendering
+            .rebuild_pipelines(&self.device, &config, sample_count);
+    }
+
+    /// Remakes what the scene is drawn into, at the surface's size
+    fn rebuild_targets(&mut self) {
+        self.depth_texture = super::depth::create_depth_texture_sized(
+            &self.device,
+            self.config.width,
+            self.config.height,
+            self.post.settings.sample_count(),
+            "depth_texture",
+        );
         super::msaa::rebuild_msaa(self);
+        self.post
+            .resize(&self.device, &self.config, &self.depth_texture.view);
+    }
 
-        // Draw normals - will remove later, for debugging
-        self.line_rendering.clear_lines_fg();
-        for x in regen::gen_vert_idx(&model::get_model()).0 {
-            self.line_rendering.draw_line_fg(
-                x.pos.into(),
-                glam::Vec3::from_array(x.pos) + glam::Vec3::from_array(x.normal),
-                [0.0, 1.0, 0.0, 1.0],
-            );
+    /// Handles transport and frame editing keys, returning whether the shown frame changed
+    fn update_playback(&mut self) -> bool {
+        let frame = self.playback.frame;
+        let mut edited = false;
+
+        if input::is_key_pressed(KeyCode::Space) {
+            self.playback.toggle();
+        }
+        if input::is_key_pressed(KeyCode::ArrowLeft) {
+            self.playback.step(-1, &self.animation);
+        }
+        if input::is_key_pressed(KeyCode::ArrowRight) {
+            self.playback.step(1, &self.animation);
+        }
+        if input::is_key_pressed(KeyCode::Home) {
+            self.playback.jump_to_start(&self.animation);
+        }
+        if input::is_key_pressed(KeyCode::End) {
+            self.playback.jump_to_end(&self.animation);
+        }
+        if input::is_key_pressed(KeyCode::BracketLeft) {
+            self.playback.slower();
+        }
+        if input::is_key_pressed(KeyCode::BracketRight) {
+            self.playback.faster();
         }
 
-        // Wireframe
-        if input::is_key_pressed(KeyCode::F1) {
-            self.wireframe = !self.wireframe;
-            let vib = vertex::create_buffers(
-                regen::gen_vert_idx(&model::get_model()),
-                &self.device,
-                self.wireframe,
-            );
-            self.index_buffer = vib.idxbuf;
-            self.index_buffer_size = vib.idx_size;
-            self.pipeline = init::create_render_pipeline(
-                &self.device,
-                &self.pipeline_layout,
-                &self.shader,
-                &self.config,
-                self.wireframe,
-            );
+        if input::is_key_pressed(KeyCode::KeyT) && !self.animation.tracks.is_empty() {
+            self.active_track = (self.active_track + 1) % self.animation.tracks.len();
         }
 
-        if self.restage_transform {
-            self.transform_staging_buf =
-                Some(self.transform_uniform.create_staging_buffer(&self.device));
-            self.restage_transform = false;
+        // Frame editing, on the active track's stored frame shown at the playhead
+        if let Some(track) = self.animation.tracks.get_mut(self.active_track) {
+            let current = match track.locate(self.playback.frame as f32) {
+                Some((i, _)) => i,
+                None => 0,
+            };
+            let mut seek = None;
+
+            if input::is_key_pressed(KeyCode::Insert) {
+                track.insert_frame(current);
+                seek = Some(track.frame_start(current + 1) as usize);
+            }
+            if input::is_ctrl_down() && input::is_key_pressed(KeyCode::KeyD) {
+                track.duplicate_frame(current);
+                seek = Some(track.frame_start(current + 1) as usize);
+            }
+            if input::is_key_pressed(KeyCode::Delete) && track.delete_frame(current) {
+                seek = Some(self.playback.frame);
+            }
+
+            if let Some(frame) = seek {
+                self.playback.seek(frame, &self.animation);
+                edited = true;
+            }
         }
 
-        input::input_update();
+        // Markers at the playhead
+        if input::is_key_pressed(KeyCode::KeyM) {
+            let frame = self.playback.frame as u32;
+            match input::is_shift_down() {
+                true => {
+                    self.animation.remove_markers_at(frame);
+                }
+                false => {
+                    let name = format!("marker_{}", self.animation.markers.len() + 1);
+                    self.animation.add_marker(Marker::new(name, frame, None));
+                }
+            }
+        }
+
+        // Project files
+        if input::is_ctrl_down() && input::is_key_pressed(KeyCode::KeyS) {
+            match project::save(&self.animation, PROJECT_PATH) {
+                Ok(_) => log(format!("Saved {}", PROJECT_PATH), LogLevel::INFO),
+                Err(e) => log(format!("{:#}", e), LogLevel::ERROR),
+            }
+        }
+        if input::is_ctrl_down() && input::is_key_pressed(KeyCode::KeyO) {
+            match project::load(PROJECT_PATH) {
+                Ok(animation) => {
+                    self.animation = animation;
+                    self.playback = Playback::default();
+                    self.active_track = 0;
+                    edited = true;
+                    log(format!("Loaded {}", PROJECT_PATH), LogLevel::INFO);
+                }
+                Err(e) => log(format!("{:#}", e), LogLevel::ERROR),
+            }
+        }
+
+        if input::is_ctrl_down() && input::is_key_pressed(KeyCode::KeyE) {
+            let result = if input::is_alt_down() {
+                self.export_stack()
+            } else if input::is_shift_down() {
+                self.export_sprite_sheet()
+            } else {
+                self.export()
+            };
+            match result {
+                Ok(_) => log(format!("Exported to {}", EXPORT_DIR), LogLevel::INFO),
+                Err(e) => log(format!("{:#}", e), LogLevel::ERROR),
+            }
+        }
+
+        // Pressed again while rendering, cancels it
+        if input::is_ctrl_down() && input::is_key_pressed(KeyCode::KeyR) {
+            match self.still.take() {
+                Some(_) => log("Cancelled render".to_string(), LogLevel::INFO),
+                None => {
+                    if let Err(e) = self.start_still() {
+                        log(format!("{:#}", e), LogLevel::ERROR);
+                    }
+                }
+            }
+        }
+
+        // Loop range
+        if input::is_key_pressed(KeyCode::KeyI) {
+            self.playback
+                .set_loop_start(self.playback.frame, &self.animation);
+        }
+        if !input::is_ctrl_down() && input::is_key_pressed(KeyCode::KeyO) {
+            self.playback
+                .set_loop_end(self.playback.frame, &self.animation);
+        }
+        if input::is_key_pressed(KeyCode::KeyL) {
+            match input::is_shift_down() {
+                true => self.playback.toggle_ping_pong(),
+                false => self.playback.clear_loop(),
+            }
+        }
+
+        // Scrubbing holds the playhead under the mouse
+        if !self
+            .timeline
+            .update(self.size, &self.animation, &mut self.playback)
+        {
+            self.playback.update(self.delta_time, &self.animation);
+
+            // Fire the events engines would see when playback reaches a marker, on every frame
+            // played rather than just the one landed on
+            for tick in self.playback.played(&self.animation) {
+                for marker in self.animation.markers_in(tick as u32..tick as u32 + 1) {
+                    log(
+                        format!(
+                            "Marker {} at frame {}{}",
+                            marker.name,
+                            marker.frame + 1,
+                            match &marker.payload {
+                                Some(p) => format!(": {}", p),
+                                None => String::new(),
+                            }
+                        ),
+                        LogLevel::INFO,
+                    );
+                }
+            }
+        }
+
+        edited || self.playback.frame != frame
+    }
+
+    /// Handles keying the camera and previewing through the animated camera. The view can still
+    /// be moved around while previewing, until the frame changes.
+    fn update_camera_track(&mut self, frame_changed: bool) {
+        let frame = self.playback.frame as u32;
+        let mut refresh = frame_changed;
+
+        if input::is_key_pressed(KeyCode::KeyC) {
+            self.camera_preview = !self.camera_preview;
+            refresh = true;
+        }
+        if input::is_key_pressed(KeyCode::KeyK) {
+            match input::is_shift_down() {
+                true => {
+                    self.animation.camera.remove_key(frame);
+                }
+                false => {
+                    let key = self.camera_key(frame);
+                    self.animation.camera.set_key(key);
+                }
+            }
+        }
+
+        if self.camera_preview && refresh {
+            if let Some(key) = self.animation.camera.sample(self.playback.frame as f32) {
+                self.apply_camera_key(&key);
+            }
+        }
+    }
+
+    /// Starts path tracing the current frame from the current view at the window's size, a few
+    /// samples every frame until it's done
+    fn start_still(&mut self) -> anyhow::Result<()> {
+        if self.size.width == 0 || self.size.height == 0 {
+            anyhow::bail!("Nothing to render into while the window is minimized");
+        }
+
+        let tick = self.playback.frame as u32;
+        let model = self
+            .animation
+            .sample(tick as f32)
+            .context("Nothing to render at this frame")?;
+        let options = ExportOptions {
+            width: self.size.width,
+            height: self.size.height,
+            ..Default::default()
+        };
+
+        let still = Progressive::new(PathTracer::default(), model, self.camera_key(tick), options);
+        self.still = Some((tick, still));
+        Ok(())
+    }
+
+    /// Traces more of the still being rendered, saving it once it's done
+    fn update_still(&mut self) {
+        let Some((tick, still)) = &mut self.still else {
+            return;
+        };
+        if !still.step(PATH_TRACE_SAMPLES_PER_FRAME) {
+            return;
+        }
+
+        let (tick, image) = (*tick, still.image());
+        self.still = None;
+        match self.save_still(tick, &image) {
+            Ok(path) => log(format!("Rendered {}", path.display()), LogLevel::INFO),
+            Err(e) => log(format!("{:#}", e), LogLevel::ERROR),
+        }
+    }
+
+    fn save_still(&self, tick: u32, image: &Image) -> anyhow::Result<std::path::PathBuf> {
+        std::fs::create_dir_all(EXPORT_DIR)
+            .with_context(|| format!("Couldn't create {}", EXPORT_DIR))?;
+        let path = export::sequence::frame_path(
+            EXPORT_DIR,
+            &format!("{}_still_", self.animation.label),
+            tick,
+        );
+        export::sequence::write_png(image, &path)?;
+        Ok(path)
+    }
+
+    /// Exports the loop range, or everything, as a strip of layers per frame for sprite stacking
+    fn export_stack(&self) -> anyhow::Result<()> {
+        let (start, end) = self.playback.loop_bounds(&self.animation);
+        export::stack::write(
+            &self.animation,
+            start as u32..end as u32 + 1,
+            StackLayout::Strip,
+            std::path::Path::new(EXPORT_DIR).join(format!("{}_stack", self.animation.label)),
+            self.animation.label,
+        )?;
+        Ok(())
+    }
+
+    /// Exports the loop range, or everything, as a sprite sheet seen from every direction around
+    /// the current view, on a see-through background
+    fn export_sprite_sheet(&self) -> anyhow::Result<()> {
+        let (start, end) = self.playback.loop_bounds(&self.animation);
+        let options = ExportOptions {
+            width: SPRITE_SIZE,
+            height: SPRITE_SIZE,
+            background: [0.0; 4],
+            camera: ExportCamera::Fixed(self.camera_key(0)),
+            frames: Some(start as u32..end as u32 + 1),
+            ..Default::default()
+        };
+        let sheet = SheetOptions {
+            directions: SPRITE_DIRECTIONS,
+            ..Default::default()
+        };
+
+        let mut renderer = headless::HeadlessRenderer::new_blocking()?;
+        renderer.set_shadows(self.shadow_map.settings);
+        renderer.set_outlines(self.outlines.settings);
+        export::sprite_sheet::write(
+            &self.animation,
+            &mut renderer,
+            &options,
+            &sheet,
+            EXPORT_DIR,
+            &format!("{}_sheet", self.animation.label),
+        )
+    }
+
+    /// Exports the loop range, or everything, at the window's size as an image sequence, a video
+    /// and looping GIF and APNG, with a sidecar of marker events. Uses the animated camera when
+    /// previewing through it and the current view otherwise.
+    fn export(&self) -> anyhow::Result<()> {
+        if self.size.width == 0 || self.size.height == 0 {
+            anyhow::bail!("Nothing to export at the size of a minimized window");
+        }
+
+        let (start, end) = self.playback.loop_bounds(&self.animation);
+        let options = ExportOptions {
+            width: self.size.width,
+            height: self.size.height,
+            camera: match self.camera_preview {
+                true => ExportCamera::Animated,
+                false => ExportCamera::Fixed(self.camera_key(0)),
+            },
+            frames: Some(start as u32..end as u32 + 1),
+            ..Default::default()
+        };
+
+        let mut renderer = headless::HeadlessRenderer::new_blocking()?;
+        renderer.set_shadows(self.shadow_map.settings);
+        renderer.set_outlines(self.outlines.settings);
+        let dir = std::path::Path::new(EXPORT_DIR);
+        export::sequence::write_sequence(
+            &self.animation,
+            &mut renderer,
+            &options,
+            dir,
+            self.animation.label,
+        )?;
+        export::y4m::write(
+            &self.animation,
+            &mut renderer,
+            &options,
+            dir.join(format!("{}.y4m", self.animation.label)),
+        )?;
+        marker::write_sidecar(
+            &self.animation,
+            dir.join(format!("{}.events.json", self.animation.label)),
+        )?;
+
+        // Rendered once for both animated formats
+        let frames = export::timed_frames(&self.animation, &mut renderer, &options)?;
+        let materials = export::gif::materials(&self.animation, &renderer, &options);
+        let create = |extension: &str| {
+            let path = dir.join(format!("{}.{}", self.animation.label, extension));
+            std::fs::File::create(&path)
+                .map(std::io::BufWriter::new)
+                .with_context(|| format!("Couldn't create {}", path.display()))
+        };
+        export::gif::write_frames(create("gif")?, &frames, &materials, &options)?;
+        export::apng::write_frames(create("apng")?, &frames, &options)
+    }
+
+    /// Current view as a camera key
+    pub fn camera_key(&self, frame: u32) -> CameraKey {
+        self.cam.to_key(frame)
+    }
+
+    /// Moves the view to a camera key, the same way orbiting, panning and zooming would
+    pub fn apply_camera_key(&mut self, key: &CameraKey) {
+        self.cam_temp.transition = None;
+        self.cam = cam::Camera::from_key(key, self.cam.aspect);
+        self.cam_staging_buf = Some(self.cam.create_staging_buffer(&self.device));
+    }
+
+    fn update_title(&mut self) {
+        let title = format!(
+            "Voxel Animator - {} - Track {}/{} - Frame {}/{} - {} fps x{}{}{}{}{}",
+            self.animation.label,
+            self.active_track + 1,
+            self.animation.tracks.len(),
+            self.playback.frame + 1,
+            self.animation.duration(),
+            self.animation.fps,
+            self.playback.speed,
+            match self.playback.ping_pong {
+                true => " ping-pong",
+                false => "",
+            },
+            match self.playback.playing {
+                true => "",
+                false => " (paused)",
+            },
+            match self.camera_preview {
+                true => " - Camera",
+                false => "",
+            },
+            match &self.still {
+                Some((_, still)) => {
+                    let (done, total) = still.progress();
+                    format!(" - Rendering {}/{}", done, total)
+                }
+                None => String::new(),
+            }
+        );
+
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    pub fn rebuild_mesh(&mut self) {
+        self.mesh = regen::gen_mesh(&self.model);
+        self.outlines.set_model(&self.queue, &self.model);
+        let vib = vertex::create_buffers(&self.mesh, &self.device, self.wireframe);
+        self.vertex_buffer = vib.vbo;
+        self.vertex_buffer_size = vib.vbo_size;
+        self.index_buffer = vib.idxbuf;
+        self.index_buffer_size = vib.idx_size;
+        self.opaque_index_size = vib.opaque_size;
+        self.shadow_index_buffer = vib.shadow_idxbuf;
+        self.shadow_index_size = vib.shadow_size;
+        self.sorted_depth_axis = None;
+    }
+
+    /// Keeps translucent faces ordered back to front as the view moves
+    fn sort_translucent(&mut self) {
+        let axis = self.cam.depth_axis();
+        let translucent = self.mesh.opaque < self.mesh.indices.len();
+        // Lines don't need sorting, and their indices don't line up with the mesh's
+        if self.wireframe || !translucent || self.sorted_depth_axis == Some(axis) {
+            return;
+        }
+
+        self.mesh.sort_translucent(axis);
+        self.queue.write_buffer(
+            &self.index_buffer,
+            (self.mesh.opaque * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&self.mesh.indices[self.mesh.opaque..]),
+        );
+        self.sorted_depth_axis = Some(axis);
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -157,10 +1046,9 @@ impl WgpuObject<'_> {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.depth_texture =
-                super::depth::create_depth_texture(&self.device, &self.config, "depth_texture");
             self.surface.configure(&self.device, &self.config);
-            super::msaa::rebuild_msaa(self);
+            self.rebuild_targets();
+            self.overlay.resize(&self.queue, new_size);
             self.cam.aspect = new_size.width as f32 / new_size.height as f32;
             self.cam_staging_buf = Some(self.cam.create_staging_buffer(&self.device));
         }
@@ -1,3 +1,5 @@
+pub mod animation;
+pub mod export;
 pub mod graphics;
 pub mod models;
 pub mod utils;
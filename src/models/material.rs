@@ -1,4 +1,66 @@
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Material {
     pub color: glam::Vec4,
+    /// Light given off, as a multiple of `color`
+    pub emission: f32,
+    /// From mirror-like at 0 to completely matte at 1
+    pub roughness: f32,
+    /// From 0 for plastic-like surfaces to 1 for metals, whose reflections take on their color
+    pub metalness: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::new(glam::Vec4::ZERO)
+    }
+}
+
+impl Material {
+    /// Matte material that doesn't give off light
+    pub const fn new(color: glam::Vec4) -> Self {
+        Self {
+            color,
+            emission: 0.0,
+            roughness: 1.0,
+            metalness: 0.0,
+        }
+    }
+
+    /// Glass, water and anything else that can be seen through, drawn after everything solid
+    pub fn is_translucent(&self) -> bool {
+        self.color.w < 1.0
+    }
+
+    pub fn lerp(&self, other: &Material, t: f32) -> Material {
+        // Return the endpoints exactly so keyframes survive a round trip
+        if t <= 0.0 {
+            return *self;
+        }
+        if t >= 1.0 {
+            return *other;
+        }
+
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Material {
+            color: self.color.lerp(other.color, t),
+            emission: mix(self.emission, other.emission),
+            roughness: mix(self.roughness, other.roughness),
+            metalness: mix(self.metalness, other.metalness),
+        }
+    }
+
+    /// Returns the palette entry with the closest color, or itself if the palette is empty
+    pub fn nearest(&self, palette: &[Material]) -> Material {
+        let mut best = *self;
+        let mut best_dist = f32::INFINITY;
+        for m in palette {
+            let dist = self.color.distance_squared(m.color);
+            if dist < best_dist {
+                best = *m;
+                best_dist = dist;
+            }
+        }
+
+        best
+    }
 }
@@ -1,16 +1,136 @@
 use super::{layer::Layer, material::Material, voxel::Voxel};
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Model {
     pub label: &'static str,
     pub value: Vec<Layer>,
 }
 
-const MAT: Material = Material {
-    color: glam::vec4(0.3, 0.3, 0.6, 1.0),
-};
-const MAT2: Material = Material {
-    color: glam::vec4(0.6, 0.3, 0.3, 1.0),
-};
+impl Model {
+    /// Creates a model of the given size where every voxel is empty
+    pub fn empty(label: &'static str, width: usize, height: usize, depth: usize) -> Self {
+        Self {
+            label,
+            value: (0..height)
+                .map(|_| Layer {
+                    label: "layer",
+                    value: vec![vec![Voxel::new(false, Material::default()); depth]; width],
+                })
+                .collect(),
+        }
+    }
+
+    /// Largest extents of the model as (x, layers, z), since layers may differ in size
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        let mut width = 0;
+        let mut depth = 0;
+        for layer in &self.value {
+            width = width.max(layer.value.len());
+            for row in &layer.value {
+                depth = depth.max(row.len());
+            }
+        }
+
+        (width, self.value.len(), depth)
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        self.value.get(y)?.value.get(x)?.get(z)
+    }
+
+    pub fn is_filled(&self, x: usize, y: usize, z: usize) -> bool {
+        match self.get(x, y, z) {
+            Some(v) => v.filled,
+            None => false,
+        }
+    }
+
+    /// Sets a voxel, returning false if the position is outside of the model
+    pub fn set(&mut self, x: usize, y: usize, z: usize, voxel: Voxel) -> bool {
+        match self
+            .value
+            .get_mut(y)
+            .and_then(|l| l.value.get_mut(x))
+            .and_then(|r| r.get_mut(z))
+        {
+            Some(v) => {
+                *v = voxel;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Scale `regen` gives voxels so the largest extent of what's filled spans -1 to 1, which is
+    /// also how big a voxel is in mesh space
+    pub fn mesh_scale(&self) -> f32 {
+        let largest = self
+            .filled_voxels()
+            .iter()
+            .map(|((x, y, z), _)| (x + 1).max(y + 1).max(z + 1))
+            .max()
+            .unwrap_or(0);
+        match largest {
+            0 => 1.0,
+            l => 2.0 / l as f32,
+        }
+    }
+
+    /// Voxel coordinates in the space meshes and cameras are in
+    pub fn to_mesh_space(&self, voxel: glam::Vec3) -> glam::Vec3 {
+        voxel * self.mesh_scale() - 1.0
+    }
+
+    /// Mesh space coordinates, such as a camera's target, in voxels
+    pub fn from_mesh_space(&self, mesh: glam::Vec3) -> glam::Vec3 {
+        (mesh + 1.0) / self.mesh_scale()
+    }
+
+    /// Box around the filled voxels on the layers `layers` accepts, in mesh space
+    pub fn mesh_bounds(&self, layers: impl Fn(usize) -> bool) -> Option<(glam::Vec3, glam::Vec3)> {
+        let (min, max) = self
+            .filled_voxels()
+            .into_iter()
+            .filter(|((_, y, _), _)| layers(*y))
+            .map(|((x, y, z), _)| glam::vec3(x as f32, y as f32, z as f32))
+            .fold(None, |bounds, p| match bounds {
+                None => Some((p, p + 1.0)),
+                Some((min, max)) => Some((p.min(min), (p + 1.0).max(max))),
+            })?;
+
+        let scale = self.mesh_scale();
+        Some((min * scale - 1.0, max * scale - 1.0))
+    }
+
+    /// Positions and voxels of every filled voxel, in layer/row/column order
+    pub fn filled_voxels(&self) -> Vec<((usize, usize, usize), Voxel)> {
+        let mut out = vec![];
+        for (y, layer) in self.value.iter().enumerate() {
+            for (x, row) in layer.value.iter().enumerate() {
+                for (z, voxel) in row.iter().enumerate() {
+                    if voxel.filled {
+                        out.push(((x, y, z), *voxel));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Unique materials of all filled voxels, in the order they first appear
+    pub fn palette(&self) -> Vec<Material> {
+        let mut out = vec![];
+        for (_, voxel) in self.filled_voxels() {
+            crate::utils::push_if_absent(&mut out, voxel.material);
+        }
+
+        out
+    }
+}
+
+const MAT: Material = Material::new(glam::vec4(0.3, 0.3, 0.6, 1.0));
+const MAT2: Material = Material::new(glam::vec4(0.6, 0.3, 0.3, 1.0));
 
 // Test model for now with hardcoded materials
 pub fn get_model() -> Model {
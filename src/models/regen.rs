@@ -2,26 +2,117 @@ use crate::{graphics::vertex::Vertex, models::regen_temp, utils, utils::*};
 
 use super::model::Model;
 
+/// Mesh of a model, with the indices of every opaque face before any translucent face's
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Indices drawn by the opaque pass, the rest are blended over them
+    pub opaque: usize,
+}
+
+impl Mesh {
+    /// Orders translucent faces furthest first along `depth_axis`, so blending them in order
+    /// layers them correctly
+    pub fn sort_translucent(&mut self, depth_axis: glam::Vec3) {
+        let vertices = &self.vertices;
+        let depth = |face: &[u32]| {
+            face.iter()
+                .map(|i| depth_axis.dot(glam::Vec3::from_array(vertices[*i as usize].pos)))
+                .sum::<f32>()
+        };
+
+        let mut faces = self.indices[self.opaque..]
+            .chunks(6)
+            .map(|face| (depth(face), face.to_vec()))
+            .collect::<Vec<_>>();
+        faces.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        self.indices.truncate(self.opaque);
+        self.indices
+            .extend(faces.into_iter().flat_map(|(_, face)| face));
+    }
+}
+
 pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
+    let mesh = gen_mesh(model);
+    (mesh.vertices, mesh.indices)
+}
+
+pub fn gen_mesh(model: &Model) -> Mesh {
     let pretime = std::time::Instant::now();
     let mut vertices = vec![];
     let mut indices = vec![];
-
-    let mut layer_num = 0;
     let mut offset = 0;
     let mut culled = vec![];
 
+    // Translucent voxels don't share vertices with solid ones, so colors don't bleed between them
+    push_voxels(
+        model,
+        false,
+        &mut vertices,
+        &mut indices,
+        &mut offset,
+        &mut culled,
+    );
+    let opaque = indices.len();
+    push_voxels(
+        model,
+        true,
+        &mut vertices,
+        &mut indices,
+        &mut offset,
+        &mut culled,
+    );
+
+    log::log(
+        format!(
+            "Mesh created and optimized in {:?}ms",
+            std::time::Instant::now()
+                .duration_since(pretime)
+                .as_secs_f32()
+                * 1000.0
+        ),
+        log::LogLevel::INFO,
+    );
+
+    Mesh {
+        vertices: utils::normalize_scale(&vertices, -1.0, 1.0),
+        indices,
+        opaque,
+    }
+}
+
+/// Adds the faces of every voxel that is or isn't translucent
+fn push_voxels(
+    model: &Model,
+    translucent: bool,
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    offset: &mut u32,
+    culled: &mut Vec<usize>,
+) {
+    let start = vertices.len();
+    let mut layer_num = 0;
+
     for layer in &model.value {
         // Sides
         for ux in 0..layer.value.len() {
             for uz in 0..layer.value[ux].len() {
-                // If voxel is empty
-                if !layer.value[ux][uz].filled {
+                // If voxel is empty, or drawn in the other pass
+                let voxel = &layer.value[ux][uz];
+                if !voxel.filled || voxel.material.is_translucent() != translucent {
                     continue;
                 }
 
-                let temp =
-                    regen_temp::ModelGenTemp::new(model, ux, uz, layer_num, layer, &vertices);
+                let temp = regen_temp::ModelGenTemp::new(
+                    model,
+                    ux,
+                    uz,
+                    layer_num,
+                    layer,
+                    &vertices[start..],
+                );
 
                 // Push vertices and duplicate list
                 // The value of the index it's REPLACING must be appeneded to 'culled'
@@ -29,9 +120,9 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     match temp.left_up_back_dup {
                         Some(v) => {
                             // Push vertex
-                            utils::push_if_absent(&mut culled, (0 + offset) as usize);
+                            utils::push_if_absent(culled, (0 + *offset) as usize);
                             // Push duplicate index
-                            v as i32
+                            (v + start) as i32
                         }
                         None => {
                             // Push vertex
@@ -42,8 +133,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.left_up_front_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (1 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (1 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.left_up_front);
@@ -52,8 +143,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.right_up_front_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (2 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (2 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.right_up_front);
@@ -62,8 +153,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.right_up_back_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (3 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (3 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.right_up_back);
@@ -72,8 +163,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.left_down_back_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (4 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (4 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.left_down_back);
@@ -82,8 +173,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.left_down_front_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (5 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (5 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.left_down_front);
@@ -92,8 +183,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.right_down_front_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (6 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (6 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.right_down_front);
@@ -102,8 +193,8 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                     },
                     match temp.right_down_back_dup {
                         Some(v) => {
-                            utils::push_if_absent(&mut culled, (7 + offset) as usize);
-                            v as i32
+                            utils::push_if_absent(culled, (7 + *offset) as usize);
+                            (v + start) as i32
                         }
                         None => {
                             vertices.push(temp.right_down_back);
@@ -116,55 +207,36 @@ pub fn gen_vert_idx(model: &Model) -> (Vec<Vertex>, Vec<u32>) {
                 use crate::utils::consts::*;
 
                 if temp.top_condition {
-                    push_indices(&mut indices, INDICES_TOP, offset, &culled, duplicate_list);
+                    push_indices(indices, INDICES_TOP, *offset, culled, duplicate_list);
                 }
 
                 if temp.bottom_condition {
-                    push_indices(
-                        &mut indices,
-                        INDICES_BOTTOM,
-                        offset,
-                        &culled,
-                        duplicate_list,
-                    );
+                    push_indices(indices, INDICES_BOTTOM, *offset, culled, duplicate_list);
                 }
 
                 if temp.right_condition {
-                    push_indices(&mut indices, INDICES_RIGHT, offset, &culled, duplicate_list);
+                    push_indices(indices, INDICES_RIGHT, *offset, culled, duplicate_list);
                 }
 
                 if temp.left_condition {
-                    push_indices(&mut indices, INDICES_LEFT, offset, &culled, duplicate_list);
+                    push_indices(indices, INDICES_LEFT, *offset, culled, duplicate_list);
                 }
 
                 if temp.front_condition {
-                    push_indices(&mut indices, INDICES_FRONT, offset, &culled, duplicate_list);
+                    push_indices(indices, INDICES_FRONT, *offset, culled, duplicate_list);
                 }
 
                 if temp.back_condition {
-                    push_indices(&mut indices, INDICES_BACK, offset, &culled, duplicate_list);
+                    push_indices(indices, INDICES_BACK, *offset, culled, duplicate_list);
                 }
 
                 // Lines up with the INITIAL indices, NOT the transformed ones
-                offset += 8;
+                *offset += 8;
             }
         }
 
         layer_num += 1;
     }
-
-    log::log(
-        format!(
-            "Mesh created and optimized in {:?}ms",
-            std::time::Instant::now()
-                .duration_since(pretime)
-                .as_secs_f32()
-                * 1000.0
-        ),
-        log::LogLevel::INFO,
-    );
-
-    (utils::normalize_scale(&vertices, -1.0, 1.0), indices)
 }
 
 fn push_indices(
@@ -1,6 +1,6 @@
 use crate::graphics::vertex::Vertex;
 
-use super::{layer::Layer, model::Model, normal::get_normal};
+use super::{layer::Layer, model::Model, normal::get_normal, voxel::Voxel};
 
 pub struct ModelGenTemp {
     pub top_condition: bool,
@@ -34,18 +34,29 @@ impl ModelGenTemp {
         uz: usize,
         layer_num: i32,
         layer: &Layer,
-        vertices: &Vec<Vertex>,
+        vertices: &[Vertex],
     ) -> Self {
         let x = ux as f32;
         let y = layer_num as f32;
         let z = uz as f32;
 
-        let top_condition = !is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, 1);
-        let bottom_condition = !is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, -1);
-        let right_condition = !is_filled_at_offset(&model.value, x, z, layer_num, 1, 0, 0);
-        let left_condition = !is_filled_at_offset(&model.value, x, z, layer_num, -1, 0, 0);
-        let front_condition = !is_filled_at_offset(&model.value, x, z, layer_num, 0, 1, 0);
-        let back_condition = !is_filled_at_offset(&model.value, x, z, layer_num, 0, -1, 0);
+        let voxel = &layer.value[ux][uz];
+        let hidden = |offset_x, offset_z, offset_layer| {
+            hides_face(
+                &model.value,
+                x,
+                z,
+                layer_num,
+                (offset_x, offset_z, offset_layer),
+                voxel,
+            )
+        };
+        let top_condition = !hidden(0, 0, 1);
+        let bottom_condition = !hidden(0, 0, -1);
+        let right_condition = !hidden(1, 0, 0);
+        let left_condition = !hidden(-1, 0, 0);
+        let front_condition = !hidden(0, 1, 0);
+        let back_condition = !hidden(0, -1, 0);
 
         let left_up_back = Vertex::new(
             [x, y + 1., z],
@@ -56,7 +67,8 @@ impl ModelGenTemp {
                 back_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, 1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let left_up_back_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == left_up_back.pos);
@@ -70,7 +82,8 @@ impl ModelGenTemp {
                 front_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, 1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let left_up_front_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == left_up_front.pos);
@@ -84,7 +97,8 @@ impl ModelGenTemp {
                 front_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, 1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let right_up_front_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == right_up_front.pos);
@@ -98,7 +112,8 @@ impl ModelGenTemp {
                 back_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, 1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let right_up_back_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == right_up_back.pos);
@@ -112,7 +127,8 @@ impl ModelGenTemp {
                 back_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, -1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let left_down_back_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == left_down_back.pos);
@@ -126,7 +142,8 @@ impl ModelGenTemp {
                 front_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, -1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let left_down_front_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == left_down_front.pos);
@@ -140,7 +157,8 @@ impl ModelGenTemp {
                 front_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, -1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let right_down_front_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == right_down_front.pos);
@@ -154,7 +172,8 @@ impl ModelGenTemp {
                 back_condition,
                 is_filled_at_offset(&model.value, x, z, layer_num, 0, 0, -1),
             ),
-        );
+        )
+        .with_emission(voxel.material.emission);
         let right_down_back_dup = vertices
             .iter()
             .position(|v: &Vertex| v.pos == right_down_back.pos);
@@ -186,32 +205,49 @@ impl ModelGenTemp {
     }
 }
 
-fn is_filled_at_offset(
-    tiles: &Vec<Layer>,
+fn voxel_at_offset(
+    tiles: &[Layer],
     x: f32,
     z: f32,
     layer: i32,
     offset_x: i32,
     offset_z: i32,
     offset_layer: i32,
-) -> bool {
+) -> Option<&Voxel> {
     let x = x as i32;
     let z = z as i32;
 
-    let a = match tiles.get((layer + offset_layer) as usize) {
-        Some(x) => x,
-        None => return false,
-    };
-
-    let b = match a.value.get((x + offset_x) as usize) {
-        Some(x) => x,
-        None => return false,
-    };
+    tiles
+        .get((layer + offset_layer) as usize)?
+        .value
+        .get((x + offset_x) as usize)?
+        .get((z + offset_z) as usize)
+}
 
-    let c = match b.get((z + offset_z) as usize) {
-        Some(x) => x,
-        None => return false,
-    };
+fn is_filled_at_offset(
+    tiles: &Vec<Layer>,
+    x: f32,
+    z: f32,
+    layer: i32,
+    offset_x: i32,
+    offset_z: i32,
+    offset_layer: i32,
+) -> bool {
+    voxel_at_offset(tiles, x, z, layer, offset_x, offset_z, offset_layer).is_some_and(|v| v.filled)
+}
 
-    c.filled
+/// Whether the neighbour at an offset covers the face of `voxel` towards it. Translucent
+/// neighbours only cover faces of the same material, so anything else shows through them.
+fn hides_face(
+    tiles: &[Layer],
+    x: f32,
+    z: f32,
+    layer: i32,
+    (offset_x, offset_z, offset_layer): (i32, i32, i32),
+    voxel: &Voxel,
+) -> bool {
+    match voxel_at_offset(tiles, x, z, layer, offset_x, offset_z, offset_layer) {
+        Some(n) if n.filled => !n.material.is_translucent() || n.material == voxel.material,
+        _ => false,
+    }
 }
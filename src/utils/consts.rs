@@ -1,29 +1,132 @@
-#[rustfmt::skip]
-pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
-    1.0, 0.0, 0.0, 0.0,
-    0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0,
-);
-
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
-pub const VBO_ATTRIBS: [wgpu::VertexAttribute; 3] =
-    wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4, 2 => Float32x3];
+pub const VBO_ATTRIBS: [wgpu::VertexAttribute; 4] =
+    wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4, 2 => Float32x3, 3 => Float32];
+
+pub const OVERLAY_VBO_ATTRIBS: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4];
 
 pub const ZOOM_SENS: f32 = 0.2;
+// Zoom steps dragging across the window's height makes
+pub const ZOOM_DRAG_SENS: f32 = 10.0;
 
 pub const ROT_SENS_X: f32 = 800.0;
 pub const ROT_SENS_Y: f32 = 1500.0;
 
 pub const ROT_SENS_THROTTLE: f32 = 0.2;
 
-pub const ROT_CLAMP: f32 = (std::f32::consts::PI / 2.0) * 0.999;
-
 pub const PAN_SENS: f32 = 100.0;
 
 pub const PAN_SENS_THROTTLE: f32 = 0.2;
 
+// Orbit camera, in mesh space where the model spans -1 to 1
+pub const CAM_ZNEAR: f32 = 0.01;
+pub const CAM_ZFAR: f32 = 100.0;
+pub const CAM_DISTANCE_MIN: f32 = 0.05;
+pub const CAM_DISTANCE_MAX: f32 = 50.0;
+
+// Fly mode, looking in radians per pixel the mouse moves and flying in mesh space per second.
+// Shift and Alt multiply the speed, and looking up or down stops just short of straight.
+pub const FLY_LOOK_SENS: f32 = 0.003;
+pub const FLY_SPEED: f32 = 1.0;
+pub const FLY_SPEED_FAST: f32 = 4.0;
+pub const FLY_SPEED_SLOW: f32 = 0.25;
+pub const FLY_PITCH_LIMIT: f32 = (std::f32::consts::PI / 2.0) * 0.99;
+
+// How much room framing leaves around what it fits into the view
+pub const FRAME_MARGIN: f32 = 1.1;
+
+// Seconds the view takes to ease to a preset or between projections
+pub const CAM_TRANSITION_TIME: f32 = 0.25;
+
+pub const DEFAULT_FPS: f32 = 12.0;
+
+pub const PLAYBACK_SPEED_MIN: f32 = 0.125;
+pub const PLAYBACK_SPEED_MAX: f32 = 8.0;
+
+// Timeline strip layout, in pixels
+pub const TIMELINE_HEIGHT: f32 = 40.0;
+pub const TIMELINE_MARGIN: f32 = 12.0;
+
+// Steps smoke lasts before fading and fire burns before turning into smoke
+pub const SIM_SMOKE_LIFE: u32 = 24;
+pub const SIM_FIRE_LIFE: u32 = 8;
+
+// Rigid-body bakes, in voxels and seconds
+pub const PHYSICS_GRAVITY: f32 = -40.0;
+pub const PHYSICS_RESTITUTION: f32 = 0.3;
+pub const PHYSICS_FRICTION: f32 = 0.05;
+pub const PHYSICS_SLEEP_SPEED: f32 = 0.2;
+pub const PHYSICS_SUBSTEPS: usize = 8;
+// Times contacts between fragments are solved each step, more settles stacks better
+pub const PHYSICS_CONTACT_ITERATIONS: usize = 4;
+
+// Project files, the version is bumped whenever older builds couldn't read a new file
+pub const PROJECT_VERSION: u32 = 1;
+pub const PROJECT_PATH: &str = "project.vxa.json";
+// Custom mouse bindings, read at startup when they exist
+pub const NAVIGATION_PATH: &str = "navigation.json";
+
+// Where exports started from the viewport are written, and how sprite sheets are rendered
+pub const EXPORT_DIR: &str = "export";
+pub const SPRITE_SIZE: u32 = 128;
+pub const SPRITE_DIRECTIONS: u32 = 8;
+
+// Sun lighting the viewport, exports and path traced stills, pointing towards the sun
+pub const SUN_DIRECTION: [f32; 3] = [0.5, 1.0, 0.3];
+// How much light shadowed faces still get, and the shadow map's size in texels
+pub const SHADOW_AMBIENT: f32 = 0.45;
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+pub const SHADOW_MAP_SIZE_MIN: u32 = 512;
+pub const SHADOW_MAP_SIZE_MAX: u32 = 8192;
+// Floor catching shadows, in mesh space where the model spans -1 to 1
+pub const GROUND_EXTENT: f32 = 4.0;
+pub const GROUND_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// Viewport post-processing, the SSAO radius is in mesh space where the model spans -1 to 1
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+pub const OCCLUSION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg8Unorm;
+pub const SSAO_RADIUS: f32 = 0.15;
+pub const SSAO_STRENGTH: f32 = 1.0;
+pub const BLOOM_THRESHOLD: f32 = 1.0;
+pub const BLOOM_STRENGTH: f32 = 0.8;
+// Exposure is multiplied or divided by the step with each key press
+pub const EXPOSURE_STEP: f32 = 1.25;
+pub const EXPOSURE_MIN: f32 = 0.125;
+pub const EXPOSURE_MAX: f32 = 8.0;
+
+// Ground grid, in voxels with a major line every so many. It reaches out a number of times the
+// camera's distance from its target, and minor lines are spread further apart wherever there would
+// be more than the most allowed from the middle out.
+pub const GRID_MAJOR_EVERY: u32 = 8;
+pub const GRID_REACH: f32 = 4.0;
+pub const GRID_LINES_MAX: u32 = 64;
+// Axis gizmo in the top right corner, in pixels
+pub const GIZMO_SIZE: f32 = 40.0;
+pub const GIZMO_MARGIN: f32 = 16.0;
+
+// Lines along voxel edges, in pixels. Colors are cycled through in order.
+pub const OUTLINE_THICKNESS: f32 = 1.0;
+pub const OUTLINE_THICKNESS_MAX: f32 = 4.0;
+pub const OUTLINE_COLORS: [[f32; 4]; 3] = [
+    [0.0, 0.0, 0.0, 0.6],
+    [1.0, 1.0, 1.0, 0.6],
+    [1.0, 0.6, 0.1, 0.9],
+];
+// Silhouettes are this many times thicker, and found where depth jumps by more than the gap in
+// mesh space
+pub const OUTLINE_SILHOUETTE: f32 = 2.0;
+pub const OUTLINE_SILHOUETTE_GAP: f32 = 0.05;
+
+// Path traced stills, per pixel
+pub const PATH_TRACE_SAMPLES: u32 = 64;
+pub const PATH_TRACE_BOUNCES: u32 = 4;
+// Samples traced between viewport frames while rendering a still
+pub const PATH_TRACE_SAMPLES_PER_FRAME: u32 = 1;
+
+// Frames between full copies in animation storage, bounding the diffs applied when seeking
+pub const KEYFRAME_INTERVAL: usize = 16;
+
 #[rustfmt::skip]
 pub const INDICES_TOP: &[u32] = &[
     0, 1, 3,
@@ -0,0 +1,98 @@
+/// 8-bit RGBA image, stored row by row from the top left
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, color: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; (width * height) as usize],
+        }
+    }
+
+    /// Wraps tightly packed RGBA bytes, returning `None` if there aren't exactly enough of them
+    pub fn from_rgba(width: u32, height: u32, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != (width * height * 4) as usize {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels: bytes
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+        })
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some(self.pixels[(y * self.width + x) as usize])
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        if x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = color;
+        }
+    }
+
+    /// Copy of a rectangle, clipped to the image
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Image {
+        let width = width.min(self.width.saturating_sub(x));
+        let height = height.min(self.height.saturating_sub(y));
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for row in y..y + height {
+            let start = (row * self.width + x) as usize;
+            pixels.extend_from_slice(&self.pixels[start..start + width as usize]);
+        }
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Copies another image over this one with its top left at `x`, `y`, without blending
+    pub fn blit(&mut self, other: &Image, x: u32, y: u32) {
+        for row in 0..other.height.min(self.height.saturating_sub(y)) {
+            let width = other.width.min(self.width.saturating_sub(x)) as usize;
+            let from = (row * other.width) as usize;
+            let to = ((y + row) * self.width + x) as usize;
+            self.pixels[to..to + width].copy_from_slice(&other.pixels[from..from + width]);
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.pixels)
+    }
+}
+
+/// Converts a color from 0 to 1 into bytes, without any color space conversion
+pub fn color_to_rgba8(color: [f32; 4]) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Encodes a linear color channel from 0 to 1 the way sRGB render targets store it
+pub fn linear_to_srgb(c: f32) -> f32 {
+    match c <= 0.0031308 {
+        true => c * 12.92,
+        false => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
@@ -1,7 +1,9 @@
 use crate::graphics::vertex::Vertex;
 
 pub mod consts;
+pub mod image;
 pub mod log;
+pub mod rng;
 pub mod tests;
 
 pub fn push_if_absent<T>(vector: &mut Vec<T>, element: T)
@@ -13,6 +15,12 @@ where
     }
 }
 
+/// Labels are `&'static str` throughout, so ones read from files are leaked. Projects only hold a
+/// handful of them.
+pub fn leak_str(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
 pub fn normalize_scale(vertices: &Vec<Vertex>, min: f32, max: f32) -> Vec<Vertex> {
     let mut max_x: f32 = 0.0;
     let mut min_x: f32 = 0.0;
@@ -118,3 +126,17 @@ pub fn empty_buffer(device: &wgpu::Device) -> wgpu::Buffer {
         mapped_at_creation: false,
     })
 }
+
+/// Stable pseudo-random value in [0, 1) for a grid position
+pub fn hash3(x: usize, y: usize, z: usize) -> f32 {
+    let mut h = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    h ^= h >> 33;
+
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
@@ -235,3 +235,1587 @@ fn rotmat_gen() {
         out.x_axis, out.y_axis, out.z_axis, out.w_axis
     )
 }
+
+#[test]
+fn tween_endpoints() {
+    use crate::animation::tween::{tween_at, MaterialMode, TweenStrategy};
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    let red = Material::new(glam::vec4(1.0, 0.0, 0.0, 1.0));
+    let blue = Material::new(glam::vec4(0.0, 0.0, 1.0, 1.0));
+
+    // 2x2x2 cube in one corner moving to a 3x1x2 bar in the other
+    let mut from = Model::empty("from", 5, 3, 5);
+    for (x, y, z) in [(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 1, 0)] {
+        from.set(x, y, z, Voxel::new(true, red));
+        from.set(x, y, z + 1, Voxel::new(true, red));
+    }
+    let mut to = Model::empty("to", 5, 3, 5);
+    for x in 2..5 {
+        to.set(x, 0, 3, Voxel::new(true, blue));
+        to.set(x, 0, 4, Voxel::new(true, blue));
+    }
+
+    for strategy in [
+        TweenStrategy::CrossDissolve,
+        TweenStrategy::CentroidMigration,
+        TweenStrategy::SignedDistance,
+    ] {
+        for mode in [MaterialMode::Interpolate, MaterialMode::SnapToPalette] {
+            assert_eq!(
+                tween_at(&from, &to, 0.0, strategy, mode).filled_voxels(),
+                from.filled_voxels()
+            );
+            assert_eq!(
+                tween_at(&from, &to, 1.0, strategy, mode).filled_voxels(),
+                to.filled_voxels()
+            );
+
+            let half = tween_at(&from, &to, 0.5, strategy, mode);
+            for m in half.palette() {
+                match mode {
+                    MaterialMode::Interpolate => assert!(m.color.x < 1.0 || m == red),
+                    MaterialMode::SnapToPalette => assert!(m == red || m == blue),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn frame_store_roundtrip() {
+    use crate::animation::storage::FrameStore;
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    // A single voxel walking along a 6x2x6 floor, one step per frame
+    let frames = (0..40)
+        .map(|i| {
+            let mut m = Model::empty("walk", 6, 2, 6);
+            m.set(i % 6, 0, (i / 6) % 6, Voxel::new(true, Material::default()));
+            m
+        })
+        .collect::<Vec<_>>();
+
+    let mut store = FrameStore::from_frames(frames.clone(), 8);
+    assert_eq!(store.len(), frames.len());
+    for (i, f) in frames.iter().enumerate() {
+        assert_eq!(store.get(i).as_ref(), Some(f));
+        assert_eq!(store.is_stored_keyframe(i), i % 8 == 0);
+    }
+    assert_eq!(store.iter().collect::<Vec<_>>(), frames);
+    assert!(store.stored_voxels() < frames.len() * 72 / 4);
+
+    // Edits in the middle of a run of diffs have to re-encode what follows
+    let mut edited = frames.clone();
+    edited.insert(11, Model::empty("walk", 6, 2, 6));
+    store.insert(11, Model::empty("walk", 6, 2, 6));
+    edited.remove(3);
+    assert_eq!(store.remove(3).as_ref(), Some(&frames[3]));
+    edited[20] = Model::empty("resized", 2, 2, 2);
+    store.set(20, Model::empty("resized", 2, 2, 2));
+
+    assert_eq!(store.iter().collect::<Vec<_>>(), edited);
+    assert!(store.is_stored_keyframe(20) && store.is_stored_keyframe(21));
+}
+
+#[test]
+fn playback() {
+    use crate::animation::{playback::Playback, Animation};
+    use crate::models::model::Model;
+
+    // Six frames a quarter of a second each, so steps land exactly
+    let mut anim = Animation::new("playback", vec![Model::empty("playback", 1, 1, 1); 6]);
+    anim.fps = 4.0;
+    let mut playback = Playback::default();
+    let play = |playback: &mut Playback, steps: usize| {
+        (0..steps)
+            .map(|_| {
+                playback.update(0.25, &anim);
+                playback.frame
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Paused playback stays put, then wraps around past the last frame
+    assert_eq!(play(&mut playback, 2), vec![0, 0]);
+    playback.toggle();
+    assert_eq!(play(&mut playback, 7), vec![1, 2, 3, 4, 5, 0, 1]);
+    playback.step(-3, &anim);
+    assert_eq!(playback.frame, 4);
+
+    // Long stalls skip whole loops rather than playing through them
+    playback.update(0.25 * 6.0 * 3.0 + 0.25, &anim);
+    assert_eq!(playback.frame, 5);
+
+    // Loop ranges are clamped to the animation and put in order, and frames outside one jump to
+    // its start
+    playback.set_loop_start(9, &anim);
+    assert_eq!(playback.loop_bounds(&anim), (5, 5));
+    playback.set_loop_start(2, &anim);
+    playback.set_loop_end(4, &anim);
+    assert_eq!(playback.loop_bounds(&anim), (2, 4));
+    playback.seek(0, &anim);
+    assert_eq!(play(&mut playback, 5), vec![2, 3, 4, 2, 3]);
+    playback.loop_range = Some((3, 20));
+    assert_eq!(playback.loop_bounds(&anim), (3, 5));
+
+    // Ping-pong turns around at both ends without repeating them
+    playback.set_loop_start(1, &anim);
+    playback.set_loop_end(4, &anim);
+    playback.seek(1, &anim);
+    playback.toggle_ping_pong();
+    assert_eq!(play(&mut playback, 8), vec![2, 3, 4, 3, 2, 1, 2, 3]);
+    playback.update(0.25 * 6.0 * 2.0, &anim);
+    assert_eq!(playback.frame, 3);
+    assert_eq!(play(&mut playback, 2), vec![4, 3]);
+
+    // Speed doubles and halves within its limits
+    for _ in 0..10 {
+        playback.faster();
+    }
+    assert_eq!(playback.speed, crate::utils::consts::PLAYBACK_SPEED_MAX);
+    playback.clear_loop();
+    playback.toggle_ping_pong();
+    playback.jump_to_end(&anim);
+    assert_eq!(playback.frame, 5);
+}
+
+#[test]
+fn timeline_scrubbing() {
+    use crate::graphics::timeline::Timeline;
+    use crate::utils::consts::{TIMELINE_HEIGHT, TIMELINE_MARGIN};
+    use winit::dpi::PhysicalSize;
+
+    // Ten frames across 224 pixels inside the margins, each 22.4 wide
+    let size = PhysicalSize::new(224 + TIMELINE_MARGIN as u32 * 2, 300);
+    let x = |frame: usize| Timeline::frame_x(frame, 10, size);
+    assert_eq!(Timeline::frame_x(0, 10, size), TIMELINE_MARGIN + 11.2);
+    for frame in 0..10 {
+        // Anywhere across a frame snaps to it
+        assert_eq!(Timeline::frame_at(x(frame), 10, size), frame);
+        assert_eq!(Timeline::frame_at(x(frame) - 11.0, 10, size), frame);
+        assert_eq!(Timeline::frame_at(x(frame) + 11.0, 10, size), frame);
+    }
+
+    // Past either end clamps to the first and last frame, and empty animations stay on 0
+    assert_eq!(Timeline::frame_at(-50.0, 10, size), 0);
+    assert_eq!(Timeline::frame_at(1000.0, 10, size), 9);
+    assert_eq!(Timeline::frame_at(100.0, 0, size), 0);
+
+    // Scrubbing only starts with a click on the strip
+    let strip_y = 300.0 - TIMELINE_HEIGHT / 2.0;
+    let mut timeline = Timeline::default();
+    let pos = |frame: usize, y: f32| glam::vec2(x(frame), y);
+    assert_eq!(timeline.scrub(pos(3, 100.0), true, true, 10, size), None);
+    assert_eq!(timeline.scrub(pos(3, strip_y), false, true, 10, size), None);
+    assert_eq!(
+        timeline.scrub(pos(3, strip_y), true, true, 10, size),
+        Some(3)
+    );
+    assert!(timeline.scrubbing);
+
+    // then follows the mouse off the strip, clamped, until the button is let go
+    assert_eq!(timeline.scrub(pos(7, 20.0), false, true, 10, size), Some(7));
+    let far = glam::vec2(5000.0, 20.0);
+    assert_eq!(timeline.scrub(far, false, true, 10, size), Some(9));
+    assert_eq!(
+        timeline.scrub(pos(5, strip_y), false, false, 10, size),
+        None
+    );
+    assert!(!timeline.scrubbing);
+}
+
+#[test]
+fn retime() {
+    use crate::animation::{interpolation::Interpolation, retime, track::Track, Animation};
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    let frames = (0..4)
+        .map(|i| {
+            let mut m = Model::empty("retime", 4, 1, 1);
+            m.set(i, 0, 0, Voxel::new(true, Material::default()));
+            m
+        })
+        .collect::<Vec<_>>();
+
+    // Upsampling keeps each frame at the same time
+    let mut anim = Animation::new("retime", frames.clone());
+    anim.tracks[0].durations = vec![1, 2, 1, 3];
+    retime::set_fps(&mut anim, 24.0);
+    assert_eq!(anim.tracks[0].durations, vec![2, 4, 2, 6]);
+    assert_eq!(anim.duration() as f32 / anim.fps, 7.0 / 12.0);
+
+    // Downsampling a stepped animation drops frames that would never be shown
+    let mut stepped = Animation::new("retime", frames.clone());
+    stepped.fps = 24.0;
+    retime::set_fps(&mut stepped, 12.0);
+    assert_eq!(stepped.tracks[0].durations, vec![1, 1]);
+    assert_eq!(stepped.tracks[0].frame(1).as_ref(), Some(&frames[2]));
+
+    // and so does an interpolated one, which would otherwise tween towards frames it skips
+    let mut interpolated = Animation::new("retime", frames.clone());
+    interpolated.fps = 24.0;
+    interpolated.tracks[0].interpolation = Interpolation::Linear;
+    retime::set_fps(&mut interpolated, 12.0);
+    assert_eq!(interpolated.tracks[0].durations, vec![1, 1]);
+    assert_eq!(interpolated.tracks[0].frame(1).as_ref(), Some(&frames[2]));
+
+    // Squashing the middle of an interpolated track to nothing tweens straight across the gap
+    let mut track = Track::new("retime", frames.clone());
+    track.interpolation = Interpolation::Linear;
+    track.durations = vec![2, 2, 2, 2];
+    retime::scale_range(&mut track, 1..3, 0.0);
+    assert_eq!(track.durations, vec![2, 2]);
+    let filled = |m: &Model| {
+        m.filled_voxels()
+            .into_iter()
+            .map(|((x, _, _), _)| x)
+            .collect::<Vec<_>>()
+    };
+    for step in 0..=16 {
+        let sampled = track.sample(step as f32 * 0.25).unwrap();
+        assert!(filled(&sampled).iter().all(|x| *x == 0 || *x == 3));
+    }
+    assert_eq!(track.sample(1.999).map(|m| filled(&m)), Some(vec![3]));
+    assert_eq!(track.sample(2.0).as_ref(), Some(&frames[3]));
+
+    let mut track = Track::new("retime", frames.clone());
+    track.durations = vec![1, 2, 3, 4];
+    retime::reverse_range(&mut track, 1..4);
+    assert_eq!(track.durations, vec![1, 4, 3, 2]);
+    assert_eq!(track.frame(1).as_ref(), Some(&frames[3]));
+    retime::scale_range(&mut track, 0..2, 1.5);
+    assert_eq!(track.durations, vec![2, 6, 3, 2]);
+    retime::hold_frame(&mut track, 3, 2);
+    retime::duplicate_frame(&mut track, 0, 2);
+    assert_eq!(track.durations, vec![2, 2, 2, 6, 3, 4]);
+    assert_eq!(track.frame(2).as_ref(), Some(&frames[0]));
+    assert_eq!(track.locate(6.0), Some((3, 0.0)));
+
+    // Ranges selected backwards cover the same frames
+    #[allow(clippy::reversed_empty_ranges)]
+    let backwards = (3..1, 4..2);
+    retime::scale_range(&mut track, backwards.0, 0.5);
+    assert_eq!(track.durations, vec![2, 1, 1, 6, 3, 4]);
+    let (third, fourth) = (track.frame(2), track.frame(3));
+    assert_ne!(third, fourth);
+    retime::reverse_range(&mut track, backwards.1);
+    assert_eq!((track.frame(2), track.frame(3)), (fourth, third));
+}
+
+#[test]
+fn track_blending() {
+    use crate::animation::{
+        track::{BlendMode, Track, TrackTarget},
+        Animation,
+    };
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    let legs = Material::new(glam::vec4(0.0, 1.0, 0.0, 1.0));
+    let arm = Material::new(glam::vec4(1.0, 0.0, 0.0, 1.0));
+
+    // Layer 0 is the legs and layer 2 the arm, each with a two frame cycle along x
+    let pose = |leg_x: usize, arm_x: usize| {
+        let mut m = Model::empty("character", 4, 3, 4);
+        m.set(leg_x, 0, 0, Voxel::new(true, legs));
+        m.set(1, 1, 1, Voxel::new(true, legs));
+        m.set(arm_x, 2, 3, Voxel::new(true, arm));
+        m
+    };
+
+    let mut walk = Track::new("walk", vec![pose(0, 0), pose(1, 0)]);
+    walk.target = TrackTarget::Layers(vec![0, 1]);
+    let mut wave = Track::new("wave", vec![pose(0, 2), pose(0, 3), pose(0, 2)]);
+    wave.target = TrackTarget::Layers(vec![2]);
+
+    let mut anim = Animation::new("character", vec![]);
+    anim.tracks = vec![walk, wave];
+    assert_eq!(anim.duration(), 3);
+
+    // Each track only drives its own layers, the shorter walk holds its last frame
+    assert_eq!(anim.sample(1.0), Some(pose(1, 3)));
+    assert_eq!(anim.sample(2.0), Some(pose(1, 2)));
+
+    // Additive tracks only ever add voxels
+    let mut extra = Model::empty("character", 4, 3, 4);
+    extra.set(3, 2, 3, Voxel::new(true, arm));
+    let mut add = Track::new("add", vec![extra]);
+    add.blend = BlendMode::Additive;
+    anim.tracks.push(add);
+    let mut expected = pose(0, 2);
+    expected.set(3, 2, 3, Voxel::new(true, arm));
+    assert_eq!(anim.sample(0.0), Some(expected));
+
+    // Partial weights dissolve between the layers below and the track, the same way every time
+    let full = (0..4).flat_map(|x| (0..4).map(move |z| (x, z))).fold(
+        Model::empty("character", 4, 3, 4),
+        |mut m, (x, z)| {
+            m.set(x, 1, z, Voxel::new(true, arm));
+            m
+        },
+    );
+    let mut fill = Track::new("fill", vec![full]);
+    fill.weight = 0.5;
+    anim.tracks.push(fill);
+
+    let a = anim.sample(0.0).unwrap();
+    assert_eq!(Some(&a), anim.sample(0.0).as_ref());
+    let filled = a.value[1]
+        .value
+        .iter()
+        .flatten()
+        .filter(|v| v.filled)
+        .count();
+    assert!(filled > 1 && filled < 16);
+}
+
+#[test]
+fn project_roundtrip() {
+    use crate::animation::{
+        interpolation::Interpolation,
+        marker::{self, Marker},
+        playback::Playback,
+        project, retime,
+        track::{Track, TrackTarget},
+        Animation,
+    };
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    // Surface settings other than color are saved per palette entry too
+    let mat = Material {
+        emission: 2.0,
+        metalness: 1.0,
+        ..Material::new(glam::vec4(0.2, 0.4, 0.6, 1.0))
+    };
+    let frames = (0..20)
+        .map(|i| {
+            let mut m = Model::empty("clip", 4, 2, 4);
+            m.set(i % 4, i % 2, 0, Voxel::new(true, mat));
+            m
+        })
+        .collect::<Vec<_>>();
+
+    let mut anim = Animation::new("clip", frames);
+    let mut overlay = Track::new("overlay", vec![Model::empty("clip", 4, 2, 4)]);
+    overlay.target = TrackTarget::Layers(vec![1]);
+    overlay.interpolation = Interpolation::Linear;
+    overlay.weight = 0.5;
+    anim.tracks.push(overlay);
+    anim.add_marker(Marker::new("footstep", 6, Some("left".to_string())));
+    anim.add_marker(Marker::new("start", 0, None));
+
+    // Markers stay sorted and round trip along with every track
+    assert_eq!(anim.markers[0].name, "start");
+    let loaded = project::from_str(&project::to_string(&anim).unwrap()).unwrap();
+    assert_eq!(loaded, anim);
+    assert_eq!(
+        loaded.tracks[0].frames.stored_voxels(),
+        anim.tracks[0].frames.stored_voxels()
+    );
+
+    // Files from newer versions are refused even when everything else in them is fine
+    let mut newer: serde_json::Value =
+        serde_json::from_str(&project::to_string(&anim).unwrap()).unwrap();
+    assert!(project::from_str(&newer.to_string()).is_ok());
+    newer["version"] = (crate::utils::consts::PROJECT_VERSION + 1).into();
+    let error = project::from_str(&newer.to_string()).unwrap_err();
+    assert!(error.to_string().contains("newer"));
+
+    // So are files with nothing to play, having no tracks or only frames lasting no time
+    let mut empty = newer.clone();
+    empty["version"] = crate::utils::consts::PROJECT_VERSION.into();
+    for track in empty["tracks"].as_array_mut().unwrap() {
+        for duration in track["durations"].as_array_mut().unwrap() {
+            *duration = 0.into();
+        }
+    }
+    assert!(project::from_str(&empty.to_string()).is_err());
+    empty["tracks"] = serde_json::json!([]);
+    assert!(project::from_str(&empty.to_string()).is_err());
+
+    // Events keep their time when the frame rate changes
+    assert_eq!(anim.markers_in(5..7).count(), 1);
+    retime::set_fps(&mut anim, 24.0);
+    assert_eq!(anim.markers[1].frame, 12);
+    let events = marker::events(&anim);
+    assert_eq!(events[1]["time"], 0.5);
+    assert_eq!(events[1]["payload"], "left");
+    let sidecar = marker::sidecar(&anim);
+    assert_eq!(sidecar["markers"], events);
+    assert_eq!(sidecar["fps"], 24.0);
+
+    // Playing several frames at once, or around the end of the loop, passes every marker between
+    let mut playback = Playback::default();
+    playback.toggle();
+    let fired = |playback: &Playback| {
+        playback
+            .played(&anim)
+            .into_iter()
+            .flat_map(|tick| anim.markers_in(tick as u32..tick as u32 + 1))
+            .map(|m| m.name.clone())
+            .collect::<Vec<_>>()
+    };
+    playback.seek(10, &anim);
+    playback.update(4.5 / anim.fps, &anim);
+    assert_eq!(playback.frame, 14);
+    assert_eq!(fired(&playback), vec!["footstep"]);
+    playback.seek(anim.duration() as usize - 2, &anim);
+    playback.update(3.5 / anim.fps, &anim);
+    assert_eq!(playback.frame, 1);
+    assert_eq!(fired(&playback), vec!["start"]);
+    playback.seek(5, &anim);
+    assert!(playback.played(&anim).is_empty());
+}
+
+#[test]
+fn cellular_automaton() {
+    use crate::animation::sim::{Element, Simulation};
+    use crate::models::model::Model;
+
+    let count = |sim: &Simulation, element: Element| {
+        let (w, h, d) = sim.size();
+        (0..w)
+            .flat_map(|x| (0..h).flat_map(move |y| (0..d).map(move |z| (x, y, z))))
+            .filter(|(x, y, z)| sim.get(*x, *y, *z) == Some(element))
+            .count()
+    };
+
+    // A column of sand slumps into a pile on the floor
+    let mut sand = Simulation::new(&Model::empty("sand", 7, 6, 7), 1);
+    for y in 0..6 {
+        sand.spawn(3, y, 3, Element::Sand);
+    }
+    let frames = sand.bake(4, 5);
+    assert_eq!(frames.len(), 4);
+    assert_eq!(count(&sand, Element::Sand), 6);
+    assert!((0..6).any(|y| sand.get(3, y, 3) != Some(Element::Sand)));
+    assert_eq!(sand.get(3, 0, 3), Some(Element::Sand));
+
+    // The same seed always bakes the same frames
+    let mut again = Simulation::new(&Model::empty("sand", 7, 6, 7), 1);
+    for y in 0..6 {
+        again.spawn(3, y, 3, Element::Sand);
+    }
+    assert_eq!(again.bake(4, 5), frames);
+
+    // Water levels out into a single layer
+    let mut water = Simulation::new(&Model::empty("water", 4, 3, 4), 2);
+    for y in 0..3 {
+        for x in 0..2 {
+            water.spawn(x, y, 0, Element::Water);
+        }
+    }
+    water.bake(2, 30);
+    assert_eq!(count(&water, Element::Water), 6);
+    assert!((0..4).all(|x| (0..4).all(|z| water.get(x, 2, z).is_none())));
+
+    // Smoke rises and fades, fire burns out into smoke
+    let mut smoke = Simulation::new(&Model::empty("smoke", 3, 8, 3), 3);
+    smoke.spawn(1, 0, 1, Element::Smoke);
+    smoke.spawn(1, 0, 0, Element::Fire);
+    smoke.step();
+    assert!(smoke.get(1, 0, 1).is_none());
+    for _ in 0..smoke.fire_life {
+        smoke.step();
+    }
+    assert_eq!(count(&smoke, Element::Fire), 0);
+    for _ in 0..smoke.smoke_life * 2 {
+        smoke.step();
+    }
+    assert_eq!(count(&smoke, Element::Smoke), 0);
+}
+
+#[test]
+fn destruction() {
+    use crate::animation::destruction::{fracture, FractureMode, RigidWorld};
+    use crate::models::{
+        material::Material,
+        model::{self, Model},
+        voxel::Voxel,
+    };
+
+    // The test model's top layer has two voxels that only touch diagonally
+    let parts = fracture(&model::get_model(), FractureMode::Layers);
+    assert_eq!(parts.len(), 4);
+    assert_eq!(parts.iter().map(|f| f.voxels.len()).sum::<usize>(), 15);
+
+    let mut cube = Model::empty("cube", 6, 6, 6);
+    for x in 0..6 {
+        for y in 0..6 {
+            for z in 0..6 {
+                cube.set(x, y, z, Voxel::new(true, Material::default()));
+            }
+        }
+    }
+    let shards = fracture(&cube, FractureMode::Voronoi { cells: 5, seed: 7 });
+    assert!(shards.len() >= 5);
+    assert_eq!(shards.iter().map(|f| f.voxels.len()).sum::<usize>(), 216);
+    assert_eq!(
+        shards,
+        fracture(&cube, FractureMode::Voronoi { cells: 5, seed: 7 })
+    );
+
+    // A raised block falls and comes to rest on the ground, in the padded frame
+    let mut raised = Model::empty("block", 2, 8, 2);
+    for x in 0..2 {
+        for z in 0..2 {
+            raised.set(x, 6, z, Voxel::new(true, Material::default()));
+            raised.set(x, 7, z, Voxel::new(true, Material::default()));
+        }
+    }
+    let mut world = RigidWorld::new(&raised, &fracture(&raised, FractureMode::Layers));
+    assert_eq!(world.bodies.len(), 2);
+    let frames = world.bake(48, 24.0, 2);
+    assert_eq!(frames[0].dimensions(), (6, 10, 6));
+    assert!(frames[0].is_filled(2, 7, 2) && !frames[0].is_filled(2, 0, 2));
+    let last = frames.last().unwrap();
+    assert_eq!(
+        last.filled_voxels()
+            .iter()
+            .filter(|(p, _)| p.1 == 0)
+            .count(),
+        4
+    );
+
+    // The top layer lands on the bottom one, and the two never share a voxel on the way
+    for frame in &frames {
+        assert_eq!(frame.filled_voxels().len(), 8);
+    }
+    assert_eq!(
+        last.filled_voxels()
+            .iter()
+            .filter(|(p, _)| p.1 == 1)
+            .count(),
+        4
+    );
+    assert!(world.bodies.iter().all(|b| b.velocity.length() < 1.0));
+}
+
+#[test]
+fn camera_track() {
+    use crate::animation::{
+        camera::{CameraKey, CameraTrack},
+        interpolation::Interpolation,
+        project, retime, Animation,
+    };
+    use crate::models::model;
+
+    let key = |frame, eye: glam::Vec3, fov| CameraKey {
+        frame,
+        eye,
+        target: glam::Vec3::ZERO,
+        fov,
+        zoom: -5.0,
+        orthographic: 0.0,
+    };
+
+    let mut track = CameraTrack {
+        interpolation: Interpolation::Linear,
+        ..Default::default()
+    };
+    track.set_key(key(10, glam::Vec3::Z * 2.0, 60.0));
+    track.set_key(key(0, glam::Vec3::X * 2.0, 40.0));
+    track.set_key(key(10, glam::Vec3::Z * 2.0, 50.0));
+    assert_eq!(track.keys.len(), 2);
+
+    // Holds outside the keys, swings around the target between them
+    assert_eq!(track.sample(-3.0).unwrap().fov, 40.0);
+    assert_eq!(track.sample(20.0).unwrap().fov, 50.0);
+    let mid = track.sample(5.0).unwrap();
+    assert!((mid.fov - 45.0).abs() < 1e-4);
+    assert!((mid.eye.length() - 2.0).abs() < 1e-4);
+    assert!((mid.eye.x - mid.eye.z).abs() < 1e-4);
+
+    track.interpolation = Interpolation::Step;
+    assert_eq!(track.sample(9.0).unwrap().fov, 40.0);
+
+    // Turntables come back around to where they started
+    let turntable =
+        CameraTrack::turntable(glam::vec3(0.0, 1.0, 3.0), glam::Vec3::ZERO, 45.0, -5.0, 48);
+    let (start, end) = (
+        turntable.sample(0.0).unwrap(),
+        turntable.sample(48.0).unwrap(),
+    );
+    assert!(start.eye.distance(end.eye) < 1e-4);
+    assert!(
+        turntable
+            .sample(24.0)
+            .unwrap()
+            .eye
+            .distance(glam::vec3(0.0, 1.0, -3.0))
+            < 1e-4
+    );
+
+    // Saved with the project and retimed along with everything else
+    let mut anim = Animation::new("camera", vec![model::get_model()]);
+    anim.camera = turntable;
+    assert_eq!(
+        project::from_str(&project::to_string(&anim).unwrap()).unwrap(),
+        anim
+    );
+    retime::set_fps(&mut anim, 24.0);
+    assert_eq!(anim.camera.keys.last().unwrap().frame, 96);
+
+    // Short orbits keep every key rather than stacking them up on the same tick
+    let short = CameraTrack::turntable(glam::Vec3::Z, glam::Vec3::ZERO, 45.0, -5.0, 4);
+    assert!(short.keys.windows(2).all(|k| k[0].frame < k[1].frame));
+
+    // Swinging to the opposite side goes around rather than through the target
+    let half = key(0, glam::Vec3::Z * 2.0, 45.0).lerp(&key(10, glam::Vec3::NEG_Z * 2.0, 45.0), 0.5);
+    assert!(half.eye.is_finite());
+    assert!((half.eye.length() - 2.0).abs() < 1e-4 && half.eye.y.abs() < 1e-4);
+    let over = key(0, glam::Vec3::Y, 45.0).lerp(&key(10, glam::Vec3::NEG_Y, 45.0), 0.5);
+    assert!(over.eye.is_finite() && (over.eye.length() - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn image_export() {
+    use crate::animation::{camera::CameraKey, Animation};
+    use crate::export::{self, ExportCamera, ExportOptions, FrameRenderer};
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+    use crate::utils::image::Image;
+
+    // Shades each frame by how many voxels it has, and remembers the cameras it was given
+    struct CountRenderer(Vec<CameraKey>);
+    impl FrameRenderer for CountRenderer {
+        fn render(
+            &mut self,
+            model: &Model,
+            camera: &CameraKey,
+            options: &ExportOptions,
+        ) -> anyhow::Result<Image> {
+            self.0.push(*camera);
+            let shade = model.filled_voxels().len() as u8 * 60;
+            Ok(Image::new(
+                options.width,
+                options.height,
+                [shade, shade, shade, 255],
+            ))
+        }
+    }
+
+    let frames = (0..4)
+        .map(|i| {
+            let mut m = Model::empty("clip", 4, 1, 1);
+            for x in 0..i {
+                m.set(x, 0, 0, Voxel::new(true, Material::default()));
+            }
+            m
+        })
+        .collect::<Vec<_>>();
+    let anim = Animation::new("clip", frames);
+    let fixed = CameraKey {
+        fov: 30.0,
+        ..Default::default()
+    };
+    let options = ExportOptions {
+        width: 3,
+        height: 3,
+        camera: ExportCamera::Fixed(fixed),
+        frames: Some(1..10),
+        ..Default::default()
+    };
+
+    let dir = std::env::temp_dir().join(format!("voxel_export_{}", std::process::id()));
+    let mut renderer = CountRenderer(vec![]);
+    let paths =
+        export::sequence::write_sequence(&anim, &mut renderer, &options, &dir, "clip").unwrap();
+    assert_eq!(paths.len(), 3);
+    assert!(paths[0].ends_with("clip0001.png"));
+    assert!(renderer.0.iter().all(|c| *c == fixed));
+
+    let decoder = png::Decoder::new(std::fs::File::open(&paths[2]).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut bytes = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut bytes).unwrap();
+    assert_eq!(&bytes[..4], &[180, 180, 180, 255]);
+
+    // Odd sizes round the chroma planes up, black and white map to the ends of limited range
+    let y4m = dir.join("clip.y4m");
+    export::y4m::write(&anim, &mut renderer, &options, &y4m).unwrap();
+    let data = std::fs::read(&y4m).unwrap();
+    let header = b"YUV4MPEG2 W3 H3 F12:1 Ip A1:1 C420jpeg\n";
+    assert!(data.starts_with(header));
+    let frame_size = b"FRAME\n".len() + 9 + 4 + 4;
+    assert_eq!(data.len(), header.len() + frame_size * 3);
+    assert_eq!(
+        data[header.len() + 6],
+        16 + ((219 * 60) as f32 / 255.0).round() as u8
+    );
+
+    let mut white = vec![];
+    export::y4m::write_frame(&mut white, &Image::new(2, 2, [255; 4])).unwrap();
+    assert_eq!(&white[6..], &[235, 235, 235, 235, 128, 128]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn animated_export() {
+    use crate::animation::{camera::CameraKey, Animation};
+    use crate::export::{self, ExportOptions, FrameRenderer, Repeat};
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+    use crate::utils::image::{color_to_rgba8, Image};
+
+    // Fills the frame with the first voxel's material, leaving empty frames see-through
+    struct FlatRenderer;
+    impl FrameRenderer for FlatRenderer {
+        fn render(
+            &mut self,
+            model: &Model,
+            _: &CameraKey,
+            options: &ExportOptions,
+        ) -> anyhow::Result<Image> {
+            let color = match model.filled_voxels().first() {
+                Some((_, v)) => self.material_color(&v.material),
+                None => [0, 0, 0, 0],
+            };
+            Ok(Image::new(options.width, options.height, color))
+        }
+    }
+
+    let red = Material::new(glam::vec4(1.0, 0.0, 0.0, 1.0));
+    let teal = Material::new(glam::vec4(0.0, 0.5, 0.5, 1.0));
+    let frames = [None, Some(red), Some(red), Some(teal)]
+        .into_iter()
+        .map(|m| {
+            let mut model = Model::empty("clip", 1, 1, 1);
+            if let Some(m) = m {
+                model.set(0, 0, 0, Voxel::new(true, m));
+            }
+            model
+        })
+        .collect::<Vec<_>>();
+    let anim = Animation::new("clip", frames);
+    let options = ExportOptions {
+        width: 2,
+        height: 2,
+        background: [0.0, 0.0, 0.0, 0.0],
+        repeat: Repeat::Times(3),
+        ..Default::default()
+    };
+
+    // The held frame is merged, and delays round where frames end
+    let timed = export::timed_frames(&anim, &mut FlatRenderer, &options).unwrap();
+    assert_eq!(timed.len(), 3);
+    assert_eq!(export::frame_delays(&timed, 100.0), vec![8, 17, 8]);
+
+    let materials = export::gif::materials(&anim, &FlatRenderer, &options);
+    assert_eq!(materials.len(), 2);
+    let mut gif_bytes = vec![];
+    export::gif::write_frames(&mut gif_bytes, &timed, &materials, &options).unwrap();
+
+    let mut decoder = gif::DecodeOptions::new();
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = decoder.read_info(gif_bytes.as_slice()).unwrap();
+    assert_eq!(decoder.repeat(), gif::Repeat::Finite(2));
+    let mut decoded = vec![];
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        decoded.push((frame.delay, frame.buffer[..4].to_vec()));
+    }
+    assert_eq!(
+        decoded,
+        vec![
+            (8, vec![0, 0, 0, 0]),
+            (17, color_to_rgba8([1.0, 0.0, 0.0, 1.0]).to_vec()),
+            (8, color_to_rgba8([0.0, 0.5, 0.5, 1.0]).to_vec()),
+        ]
+    );
+
+    // Colors outside of the palette go to the closest entry
+    let mut palette = export::gif::Palette::new(&timed, &materials, options.background);
+    assert_eq!(palette.transparent, Some(0));
+    let i = palette.index([250, 10, 5, 255]) as usize;
+    assert_eq!(palette.colors[i], [255, 0, 0]);
+
+    let forever = ExportOptions {
+        repeat: Repeat::Forever,
+        ..options
+    };
+    let mut apng_bytes = vec![];
+    export::apng::write_frames(&mut apng_bytes, &timed, &forever).unwrap();
+    let mut reader = png::Decoder::new(apng_bytes.as_slice())
+        .read_info()
+        .unwrap();
+    let control = reader.info().animation_control.unwrap();
+    assert_eq!((control.num_frames, control.num_plays), (3, 0));
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let mut delays = vec![];
+    for _ in 0..3 {
+        reader.next_frame(&mut buffer).unwrap();
+        let fc = reader.info().frame_control.unwrap();
+        delays.push((fc.delay_num, fc.delay_den));
+    }
+    assert_eq!(delays, vec![(83, 1000), (167, 1000), (83, 1000)]);
+    assert_eq!(&buffer[..4], &color_to_rgba8([0.0, 0.5, 0.5, 1.0]));
+}
+
+#[test]
+fn sprite_sheet() {
+    use crate::animation::{camera::CameraKey, marker::Marker, Animation};
+    use crate::export::{self, sprite_sheet::SheetOptions, ExportOptions, FrameRenderer};
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+    use crate::utils::image::Image;
+
+    // Draws a block as wide as the voxel count on a see-through background, off to the side
+    // the camera is looking from
+    struct BlockRenderer;
+    impl FrameRenderer for BlockRenderer {
+        fn render(
+            &mut self,
+            model: &Model,
+            camera: &CameraKey,
+            options: &ExportOptions,
+        ) -> anyhow::Result<Image> {
+            let mut image = Image::new(options.width, options.height, [0; 4]);
+            let x = match camera.eye.x > 0.5 {
+                true => 5,
+                false => 1,
+            };
+            for dx in 0..model.filled_voxels().len() as u32 {
+                image.set(x + dx, 2, [255, 0, 0, 255]);
+                image.set(x + dx, 3, [255, 0, 0, 255]);
+            }
+            Ok(image)
+        }
+    }
+
+    let frames = [1, 2, 2]
+        .into_iter()
+        .map(|n| {
+            let mut m = Model::empty("walk", 4, 1, 1);
+            for x in 0..n {
+                m.set(x, 0, 0, Voxel::new(true, Material::default()));
+            }
+            m
+        })
+        .collect::<Vec<_>>();
+    let mut anim = Animation::new("walk", frames);
+    anim.add_marker(Marker::new("step", 1, None));
+    let options = ExportOptions {
+        width: 8,
+        height: 8,
+        background: [0.0; 4],
+        ..Default::default()
+    };
+    let sheet = SheetOptions {
+        directions: 4,
+        padding: 1,
+        trim: true,
+    };
+
+    // A quarter turn puts the eye on the positive x side
+    let turned = export::sprite_sheet::direction_camera(&CameraKey::default(), 1, 4);
+    assert!((turned.eye - glam::Vec3::X).length() < 1e-5);
+
+    let (image, atlas) =
+        export::sprite_sheet::build(&anim, &mut BlockRenderer, &options, &sheet, "walk.png")
+            .unwrap();
+    assert_eq!(atlas.frames.len(), 12);
+    assert_eq!(atlas.frames[0].direction, 0);
+    assert_eq!(atlas.frames[3].direction, 1);
+    assert_eq!(
+        atlas.frames[..3].iter().map(|f| f.duration).sum::<u32>(),
+        (3000.0 / anim.fps).round() as u32
+    );
+    assert_eq!(atlas.markers[0]["name"], "step");
+
+    // Trimmed to the block, with the pivot still at the middle of the untrimmed frame
+    let first = atlas.frames[0];
+    assert_eq!((first.rect.w, first.rect.h), (1, 2));
+    assert_eq!(first.pivot, [4 - 1, 4 - 2]);
+    let turned = atlas.frames[3];
+    assert_eq!(turned.pivot, [4 - 5, 4 - 2]);
+    assert_eq!(
+        image.get(first.rect.x, first.rect.y + 1),
+        Some([255, 0, 0, 255])
+    );
+
+    // Only the one and two voxel blocks are different sprites
+    let mut rects = atlas.frames.iter().map(|f| f.rect).collect::<Vec<_>>();
+    rects.sort_by_key(|r| (r.x, r.y));
+    rects.dedup();
+    assert_eq!(rects.len(), 2);
+
+    let dir = std::env::temp_dir().join(format!("voxel_sheet_{}", std::process::id()));
+    let mut written = vec![];
+    for _ in 0..2 {
+        export::sprite_sheet::write(&anim, &mut BlockRenderer, &options, &sheet, &dir, "walk")
+            .unwrap();
+        written.push((
+            std::fs::read(dir.join("walk.png")).unwrap(),
+            std::fs::read(dir.join("walk.json")).unwrap(),
+        ));
+    }
+    assert_eq!(written[0], written[1]);
+    let parsed: export::sprite_sheet::Atlas = serde_json::from_slice(&written[0].1).unwrap();
+    assert_eq!(parsed, atlas);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sprite_stack() {
+    use crate::animation::Animation;
+    use crate::export::stack::{self, StackLayout};
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    let red = Material::new(glam::vec4(1.0, 0.0, 0.0, 1.0));
+    let glass = Material::new(glam::vec4(0.2, 0.4, 0.6, 0.5));
+    let mut model = Model::empty("tower", 3, 2, 2);
+    model.set(2, 0, 1, Voxel::new(true, red));
+    model.set(0, 1, 0, Voxel::new(true, glass));
+
+    // Colors come straight from the materials, empty voxels are see-through
+    let bottom = stack::slice(&model, 0);
+    assert_eq!((bottom.width, bottom.height), (3, 2));
+    assert_eq!(bottom.get(2, 1), Some([255, 0, 0, 255]));
+    assert_eq!(bottom.get(0, 0), Some([0, 0, 0, 0]));
+
+    let strip = stack::strip(&model);
+    assert_eq!((strip.width, strip.height), (6, 2));
+    assert_eq!(strip.get(2, 1), Some([255, 0, 0, 255]));
+    assert_eq!(strip.get(3, 0), Some([51, 102, 153, 128]));
+
+    let anim = Animation::new("tower", vec![model.clone(), model]);
+    let dir = std::env::temp_dir().join(format!("voxel_stack_{}", std::process::id()));
+    let slices = stack::write(&anim, 0..5, StackLayout::Slices, &dir, "tower").unwrap();
+    assert_eq!(slices.len(), 4);
+    assert!(slices[3].ends_with("tower0001_01.png"));
+    let strips = stack::write(&anim, 1..2, StackLayout::Strip, &dir, "tower").unwrap();
+    assert_eq!(strips.len(), 1);
+    assert!(strips[0].ends_with("tower0001.png"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn iso_renderer() {
+    use crate::animation::camera::CameraKey;
+    use crate::export::{
+        iso::{IsoProjection, IsoRenderer},
+        ExportOptions, FrameRenderer,
+    };
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+    use crate::utils::image::Image;
+
+    let white = Material::new(glam::vec4(1.0, 1.0, 1.0, 1.0));
+    let mut model = Model::empty("steps", 2, 2, 1);
+    model.set(0, 0, 0, Voxel::new(true, white));
+    model.set(1, 0, 0, Voxel::new(true, white));
+    model.set(0, 1, 0, Voxel::new(true, white));
+
+    let ascii = |image: &Image| {
+        image
+            .pixels
+            .chunks(image.width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|p| match p {
+                        [255, 255, 255, 255] => '#',
+                        [204, 204, 204, 255] => '+',
+                        [153, 153, 153, 255] => '-',
+                        [0, 0, 0, 255] => 'o',
+                        _ => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut renderer = IsoRenderer {
+        projection: IsoProjection::Dimetric,
+        scale: 1,
+        outline: Some([0, 0, 0, 255]),
+        ..Default::default()
+    };
+    let options = ExportOptions {
+        width: 14,
+        height: 12,
+        background: [0.0; 4],
+        ..Default::default()
+    };
+    let camera = CameraKey {
+        target: model.to_mesh_space(glam::vec3(1.0, 1.0, 0.5)),
+        ..Default::default()
+    };
+    // Two voxels side by side with one on top, outlined
+    #[rustfmt::skip]
+    let golden = [
+        "..............",
+        "..............",
+        ".....oo.......",
+        "....o##o......",
+        "...o+##-o.....",
+        "...o++--o.....",
+        "...o++-##o....",
+        "...o+++##-o...",
+        "....o+++--o...",
+        ".....oo+-o....",
+        ".......oo.....",
+        "..............",
+    ];
+    let image = renderer.render(&model, &camera, &options).unwrap();
+    assert_eq!(ascii(&image), golden);
+    assert_eq!(renderer.render(&model, &camera, &options).unwrap(), image);
+
+    // Looking from the other side of the model turns it a quarter at a time
+    let turned = CameraKey {
+        eye: camera.target + glam::Vec3::X,
+        ..camera
+    };
+    let flipped = CameraKey {
+        eye: camera.target - glam::Vec3::Z,
+        ..camera
+    };
+    assert_ne!(renderer.render(&model, &turned, &options).unwrap(), image);
+    assert_ne!(renderer.render(&model, &flipped, &options).unwrap(), image);
+}
+
+#[test]
+fn path_tracer() {
+    use crate::animation::camera::CameraKey;
+    use crate::export::{
+        path_tracer::{Accumulator, PathTracer, Progressive},
+        ExportOptions, FrameRenderer,
+    };
+    use crate::models::{material::Material, model::Model, voxel::Voxel};
+
+    let grey = Material::new(glam::vec4(0.5, 0.5, 0.5, 1.0));
+    let model = |material| {
+        let mut m = Model::empty("cube", 1, 1, 1);
+        m.set(0, 0, 0, Voxel::new(true, material));
+        m
+    };
+    let options = ExportOptions {
+        width: 16,
+        height: 12,
+        background: [0.0; 4],
+        ..Default::default()
+    };
+    let camera = CameraKey {
+        eye: glam::vec3(1.0, 1.0, 1.0),
+        zoom: -8.0,
+        ..Default::default()
+    };
+    let mut tracer = PathTracer {
+        samples: 2,
+        ..Default::default()
+    };
+
+    // The cube is in the middle, misses are see-through, and the same seed gives the same image
+    let image = tracer.render(&model(grey), &camera, &options).unwrap();
+    assert_eq!(image.get(8, 6).unwrap()[3], 255);
+    assert_eq!(image.get(0, 0), Some([0, 0, 0, 0]));
+    assert_eq!(
+        tracer.render(&model(grey), &camera, &options).unwrap(),
+        image
+    );
+
+    // Refining a render a pass at a time ends up the same as rendering it in one go
+    let mut accumulator = Accumulator::new(options.width, options.height);
+    tracer.accumulate(&model(grey), &camera, &options, &mut accumulator, 1);
+    tracer.accumulate(&model(grey), &camera, &options, &mut accumulator, 1);
+    assert_eq!(accumulator.samples(), 2);
+    assert_eq!(accumulator.image(), image);
+
+    // Stills in the viewport are rendered that way over a few frames, stopping at the samples
+    let mut still = Progressive::new(tracer, model(grey), camera, options.clone());
+    assert!(!still.step(1));
+    assert_eq!(still.progress(), (1, 2));
+    assert!(still.step(4));
+    assert_eq!(still.progress(), (2, 2));
+    assert_eq!(still.image(), image);
+
+    // A window minimized to nothing renders an empty image rather than panicking
+    let empty = ExportOptions {
+        width: 0,
+        ..options.clone()
+    };
+    let nothing = tracer.render(&model(grey), &camera, &empty).unwrap();
+    assert_eq!((nothing.width, nothing.height), (0, 12));
+    assert!(nothing.pixels.is_empty());
+
+    // Glowing voxels light themselves up
+    let glowing = Material {
+        emission: 4.0,
+        ..grey
+    };
+    let lit = tracer.render(&model(glowing), &camera, &options).unwrap();
+    let brightness = |p: Option<[u8; 4]>| p.unwrap()[..3].iter().map(|c| *c as u32).sum::<u32>();
+    assert!(brightness(lit.get(8, 6)) > brightness(image.get(8, 6)));
+}
+
+#[test]
+fn shadow_light() {
+    use crate::graphics::shadow;
+    use crate::utils::consts::GROUND_EXTENT;
+
+    // Every corner of the mesh's cube lands inside the shadow map. The floor reaches past its
+    // sides, where nothing casts shadows anyway, but is never cut off in depth.
+    let light = shadow::light_view_proj();
+    let (e, y) = (GROUND_EXTENT, -1.0);
+    let corners = (0..8).map(|i| {
+        let bit = |b: u32| if i & (1 << b) == 0 { -1.0 } else { 1.0 };
+        glam::vec3(bit(0), bit(1), bit(2))
+    });
+    for p in corners {
+        let clip = light * p.extend(1.0);
+        assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0, "{:?}", p);
+        assert!((0.0..=1.0).contains(&clip.z), "{:?}", p);
+    }
+    let floor = [[-e, y, -e], [-e, y, e], [e, y, e], [e, y, -e]]
+        .map(|p| light * glam::Vec3::from_array(p).extend(1.0));
+    assert!(floor.iter().all(|clip| (0.0..=1.0).contains(&clip.z)));
+    assert!(floor
+        .iter()
+        .any(|clip| clip.x.abs() > 1.0 || clip.y.abs() > 1.0));
+
+    // Higher up is nearer the sun
+    let depth = |y: f32| (light * glam::vec4(0.0, y, 0.0, 1.0)).z;
+    assert!(depth(1.0) < depth(-1.0));
+}
+
+#[test]
+fn translucent_mesh() {
+    use crate::models::{material::Material, model::Model, regen, voxel::Voxel};
+
+    let solid = Voxel::new(true, Material::new(glam::vec4(1.0, 1.0, 1.0, 1.0)));
+    let glass = Voxel::new(true, Material::new(glam::vec4(0.2, 0.5, 1.0, 0.4)));
+    let tinted = Voxel::new(true, Material::new(glam::vec4(1.0, 0.2, 0.2, 0.4)));
+    let pair = |a, b| {
+        let mut m = Model::empty("pair", 2, 1, 1);
+        m.set(0, 0, 0, a);
+        m.set(1, 0, 0, b);
+        regen::gen_mesh(&m)
+    };
+
+    // Solid faces stay drawn behind glass, glass doesn't draw the side resting on them
+    let mesh = pair(solid, glass);
+    assert_eq!(mesh.opaque, 6 * 6);
+    assert_eq!(mesh.indices.len() - mesh.opaque, 5 * 6);
+    assert!(mesh.indices[mesh.opaque..]
+        .iter()
+        .all(|i| mesh.vertices[*i as usize].color[3] < 1.0));
+
+    // Glass only merges with the same glass
+    assert_eq!(pair(glass, glass).indices.len(), 10 * 6);
+    assert_eq!(pair(glass, tinted).indices.len(), 12 * 6);
+    assert_eq!(pair(glass, tinted).opaque, 0);
+
+    // Sorted faces go from furthest to nearest
+    let mut mesh = pair(glass, tinted);
+    let axis = glam::vec3(1.0, 0.0, 0.0);
+    mesh.sort_translucent(axis);
+    let depths = mesh.indices[mesh.opaque..]
+        .chunks(6)
+        .map(|face| {
+            face.iter()
+                .map(|i| axis.dot(glam::Vec3::from_array(mesh.vertices[*i as usize].pos)))
+                .sum::<f32>()
+        })
+        .collect::<Vec<_>>();
+    assert!(depths.windows(2).all(|w| w[0] >= w[1]));
+}
+
+#[test]
+fn post_settings() {
+    use crate::graphics::{post, wgpu_object::WgpuObject};
+    use crate::utils::consts::HDR_FORMAT;
+
+    // FXAA stands in for multisampling
+    let settings = post::PostSettings::default();
+    assert_eq!(settings.sample_count(), WgpuObject::SAMPLE_COUNT);
+    let fxaa = post::PostSettings {
+        fxaa: true,
+        ..settings
+    };
+    assert_eq!(fxaa.sample_count(), 1);
+
+    // The scene is drawn at the surface's size, but in HDR
+    let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: 640,
+        height: 480,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: vec![format],
+        desired_maximum_frame_latency: 2,
+    };
+    let scene = post::scene_config(&config);
+    assert_eq!((scene.width, scene.height), (640, 480));
+    assert_eq!(scene.view_formats, vec![HDR_FORMAT]);
+}
+
+#[test]
+fn outline_grid() {
+    use crate::graphics::outline::OutlineSettings;
+    use crate::models::{model, regen};
+
+    // Outlines are off until asked for
+    assert!(!OutlineSettings::default().enabled);
+
+    // Edges are found from where faces sit in the voxel grid, so every corner has to land on it
+    let model = model::get_model();
+    let voxel_size = model.mesh_scale();
+    for vertex in regen::gen_mesh(&model).vertices {
+        let voxel = (glam::Vec3::from_array(vertex.pos) + 1.0) / voxel_size;
+        assert!((voxel - voxel.round()).abs().max_element() < 1e-4);
+    }
+    assert_eq!(
+        model.from_mesh_space(glam::Vec3::splat(-1.0 + voxel_size)),
+        glam::Vec3::ONE
+    );
+}
+
+#[test]
+fn orthographic_camera() {
+    use crate::animation::camera::CameraKey;
+    use crate::graphics::cam::{self, CamTransition, ViewPreset};
+    use crate::utils::consts::CAM_TRANSITION_TIME;
+    use std::f32::consts::PI;
+
+    let mut camera = cam::Camera::from_key(&CameraKey::default(), 1.5);
+    camera.look_from(ViewPreset::Right.direction());
+    let project = |camera: &cam::Camera, point: glam::Vec3| {
+        let clip = camera.build_view_projection_matrix() * point.extend(1.0);
+        clip.truncate() / clip.w
+    };
+
+    // Looking down -x, so -z is to the right and y is up
+    let perspective = project(&camera, glam::vec3(0.0, 0.2, -0.3));
+    assert!(perspective.x > 0.0 && perspective.y > 0.0);
+
+    // Switching keeps what's at the target where it was, and stops nearer things looking bigger
+    let ortho = cam::Camera {
+        orthographic: 1.0,
+        ..camera
+    };
+    let flat = project(&ortho, glam::vec3(0.0, 0.2, -0.3));
+    assert!((flat.truncate() - perspective.truncate()).length() < 1e-4);
+    let near = project(&ortho, glam::vec3(0.5, 0.2, -0.3));
+    assert!((near.truncate() - flat.truncate()).length() < 1e-4);
+    assert!(near.z < flat.z && (0.0..=1.0).contains(&near.z));
+    assert!((ortho.depth_axis() - glam::Vec3::NEG_X).length() < 1e-4);
+    assert!((camera.depth_axis() - ortho.depth_axis()).length() < 1e-4);
+
+    // Transitions turn the short way around and finish on the preset
+    let from = cam::Camera {
+        yaw: PI * 0.9,
+        ..camera
+    };
+    let to = cam::Camera {
+        yaw: -PI * 0.9,
+        pitch: 0.5,
+        orthographic: 1.0,
+        ..camera
+    };
+    let mut transition = CamTransition::new(from, to);
+    let (halfway, done) = transition.advance(CAM_TRANSITION_TIME / 2.0);
+    assert!(!done && (halfway.yaw - PI).abs() < 1e-4);
+    let (end, done) = transition.advance(CAM_TRANSITION_TIME);
+    assert!(done && end.orthographic == 1.0 && (end.pitch - 0.5).abs() < 1e-4);
+
+    // Keys remember the blend, so views taken from them render the same way
+    let key = ortho.to_key(0);
+    assert_eq!(key.orthographic, 1.0);
+    let back = cam::Camera::from_key(&key, 1.5);
+    assert_eq!(back.orthographic, 1.0);
+    let moved = project(&back, glam::vec3(0.5, 0.2, -0.3));
+    assert!((moved.truncate() - flat.truncate()).length() < 1e-4);
+    assert_eq!(CameraKey::default().lerp(&key, 0.5).orthographic, 0.5);
+
+    // Keys saved before the blend was stored load as perspective
+    let mut json = serde_json::to_value(key).unwrap();
+    json.as_object_mut().unwrap().remove("orthographic");
+    let old: CameraKey = serde_json::from_value(json).unwrap();
+    assert_eq!(old.orthographic, 0.0);
+}
+
+#[test]
+fn orbit_camera() {
+    use crate::animation::camera::CameraKey;
+    use crate::graphics::cam;
+    use std::f32::consts::FRAC_PI_2;
+
+    let key = CameraKey {
+        eye: glam::vec3(0.3, 1.0, 0.5),
+        target: glam::vec3(0.3, 0.2, -0.1),
+        ..Default::default()
+    };
+    let mut camera = cam::Camera::from_key(&key, 1.5);
+
+    // Keys come back as they went in, with the eye a unit from the target
+    let back = camera.to_key(key.frame);
+    assert!((back.target - key.target).length() < 1e-5);
+    assert!((back.eye - back.target - (key.eye - key.target).normalize()).length() < 1e-5);
+    assert!((back.zoom - key.zoom).abs() < 1e-4);
+
+    // The target sits straight ahead, as far away as the camera is, at every pitch including
+    // the poles and past them
+    for pitch in [0.0, 1.0, FRAC_PI_2, 2.0, -FRAC_PI_2, -3.0] {
+        camera.pitch = pitch;
+        let (direction, up, right) = (camera.direction(), camera.up(), camera.right());
+        assert!(direction.dot(up).abs() < 1e-5 && (up.length() - 1.0).abs() < 1e-5);
+        assert!((right.length() - 1.0).abs() < 1e-5);
+
+        let view = camera.build_view_matrix();
+        assert!(!view.is_nan());
+        let target = view.transform_point3(camera.target);
+        assert!((target - glam::vec3(0.0, 0.0, -camera.distance)).length() < 1e-4);
+        assert!((view.transform_vector3(up) - glam::Vec3::Y).length() < 1e-4);
+        assert!((view.transform_vector3(right) - glam::Vec3::X).length() < 1e-4);
+    }
+    assert!(camera.upside_down());
+
+    // Orbiting over a pole keeps going rather than snapping, and wraps around
+    camera.pitch = 1.5;
+    camera.orbit(0.0, 0.2);
+    assert!((camera.pitch - 1.7).abs() < 1e-5 && camera.upside_down());
+    camera.orbit(7.0, 0.0);
+    assert!(camera.yaw.abs() <= std::f32::consts::PI);
+
+    // Zooming moves the camera rather than scaling anything, and panning moves the target across
+    // the view
+    let distance = camera.distance;
+    camera.zoom(2.0);
+    assert!(camera.distance < distance);
+    camera.zoom(-2.0);
+    assert!((camera.distance - distance).abs() < 1e-4);
+    let (target, right) = (camera.target, camera.right());
+    camera.pan(0.5, 0.0);
+    assert!((camera.target - target - right * 0.5 * distance).length() < 1e-4);
+
+    // Framing puts every corner of a box inside the view
+    let (min, max) = (glam::vec3(-0.5, -1.0, 0.0), glam::vec3(1.5, 0.2, 0.4));
+    let framed = camera.framing(min, max);
+    assert_eq!(framed.target, (min + max) / 2.0);
+    let matrix = framed.build_view_projection_matrix();
+    for i in 0..8 {
+        let corner = glam::Vec3::select(
+            glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+            max,
+            min,
+        );
+        let clip = matrix * corner.extend(1.0);
+        assert!(clip.w > 0.0);
+        assert!((clip.truncate() / clip.w).truncate().abs().max_element() < 1.0);
+    }
+}
+
+#[test]
+fn fly_camera() {
+    use crate::animation::camera::CameraKey;
+    use crate::graphics::cam;
+    use crate::utils::consts::FLY_PITCH_LIMIT;
+
+    let mut camera = cam::Camera::from_key(&CameraKey::default(), 1.5);
+    let (eye, distance) = (camera.eye(), camera.distance);
+
+    // Looking around turns on the spot, and stops short of straight up
+    camera.look(0.7, -3.0);
+    assert!((camera.eye() - eye).length() < 1e-5);
+    assert_eq!(camera.distance, distance);
+    assert_eq!(camera.pitch, -FLY_PITCH_LIMIT);
+    assert!(!camera.upside_down() && !camera.build_view_matrix().is_nan());
+
+    // Flying forward heads where the view faces, and sideways along the view's right
+    camera.look(0.0, FLY_PITCH_LIMIT);
+    let eye = camera.eye();
+    camera.fly(glam::vec3(0.0, 0.0, 0.5));
+    assert!((camera.eye() - eye - camera.depth_axis() * 0.5).length() < 1e-5);
+    let eye = camera.eye();
+    camera.fly(glam::vec3(0.3, 0.2, 0.0));
+    assert!((camera.eye() - eye - camera.right() * 0.3 - glam::Vec3::Y * 0.2).length() < 1e-5);
+
+    // A view already past the poles isn't snapped back, only kept from going further
+    camera.pitch = 2.0;
+    camera.look(0.0, 0.5);
+    assert_eq!(camera.pitch, 2.0);
+    camera.look(0.0, -0.5);
+    assert!((camera.pitch - 1.5).abs() < 1e-5);
+}
+
+#[test]
+fn navigation_presets() {
+    use crate::graphics::input::{self, InputMouseButton};
+    use crate::graphics::navigation::{Modifiers, NavAction, NavigationBindings, NavigationPreset};
+
+    let only = |button| move |b| b == button;
+    let alt_shift = Modifiers {
+        shift: true,
+        alt: true,
+        ..Modifiers::NONE
+    };
+
+    // Blender pans with Shift and still orbits with the throttle held
+    let blender = NavigationPreset::Blender.bindings();
+    let middle = only(InputMouseButton::Middle);
+    assert_eq!(
+        blender.action(middle, Modifiers::NONE),
+        Some((NavAction::Orbit, InputMouseButton::Middle))
+    );
+    assert_eq!(
+        blender.action(middle, Modifiers::ALT).unwrap().0,
+        NavAction::Orbit
+    );
+    assert_eq!(blender.action(middle, alt_shift).unwrap().0, NavAction::Pan);
+    assert_eq!(
+        blender.action(middle, Modifiers::CTRL).unwrap().0,
+        NavAction::Zoom
+    );
+    assert!(blender.throttled(alt_shift) && !blender.throttled(Modifiers::SHIFT));
+    assert_eq!(
+        blender.action(only(InputMouseButton::Right), Modifiers::NONE),
+        None
+    );
+
+    // Maya needs Alt, MagicaVoxel works without a middle button
+    let maya = NavigationPreset::Maya.bindings();
+    assert_eq!(
+        maya.action(only(InputMouseButton::Left), Modifiers::NONE),
+        None
+    );
+    assert_eq!(
+        maya.action(only(InputMouseButton::Right), alt_shift)
+            .unwrap()
+            .0,
+        NavAction::Zoom
+    );
+    assert!(maya.throttled(alt_shift) && !maya.throttled(Modifiers::ALT));
+    let magica = NavigationPreset::MagicaVoxel.bindings();
+    let right = only(InputMouseButton::Right);
+    assert_eq!(
+        magica.action(right, Modifiers::NONE).unwrap().0,
+        NavAction::Orbit
+    );
+    assert_eq!(
+        magica.action(right, Modifiers::SHIFT).unwrap().0,
+        NavAction::Pan
+    );
+    assert_eq!(
+        NavigationPreset::MagicaVoxel.next(false),
+        NavigationPreset::Blender
+    );
+
+    // Loaded bindings are one more stop in the cycle rather than lost to it
+    let loaded = NavigationPreset::MagicaVoxel.next(true);
+    assert_eq!(loaded, NavigationPreset::Custom);
+    assert_eq!(loaded.next(true), NavigationPreset::Blender);
+    assert_eq!(loaded.next(false), NavigationPreset::Blender);
+
+    // Custom bindings only need what they change
+    let custom: NavigationBindings =
+        serde_json::from_str(r#"{"orbit": [{"button": "Back"}], "zoom_speed": -1.0}"#).unwrap();
+    assert_eq!(custom.zoom_speed, -1.0);
+    assert_eq!(custom.pan, blender.pan);
+    assert_eq!(
+        custom.action(only(InputMouseButton::Back), Modifiers::NONE),
+        Some((NavAction::Orbit, InputMouseButton::Back))
+    );
+    let text = serde_json::to_string(&maya).unwrap();
+    assert_eq!(
+        serde_json::from_str::<NavigationBindings>(&text).unwrap(),
+        maya
+    );
+
+    // The extra buttons are tracked like any other instead of panicking
+    use winit::event::{ElementState, MouseButton};
+    for (button, state) in [
+        (MouseButton::Back, ElementState::Pressed),
+        (MouseButton::Forward, ElementState::Pressed),
+        (MouseButton::Other(9), ElementState::Pressed),
+    ] {
+        input::poll_mousebutton_event(&button, &state);
+    }
+    assert!(input::is_mouse_button_down(InputMouseButton::Back));
+    assert!(input::is_mouse_button_down(InputMouseButton::Other(9)));
+    for button in [
+        MouseButton::Back,
+        MouseButton::Forward,
+        MouseButton::Other(9),
+    ] {
+        input::poll_mousebutton_event(&button, &ElementState::Released);
+    }
+    assert!(!input::is_mouse_button_down(InputMouseButton::Forward));
+}
+
+#[test]
+fn reference_guides() {
+    use crate::animation::camera::CameraKey;
+    use crate::graphics::{cam, guides};
+    use crate::utils::consts::*;
+
+    let key = CameraKey {
+        eye: glam::vec3(0.2, 1.0, 0.6),
+        target: glam::vec3(0.3, 0.0, -0.4),
+        zoom: -2.0,
+        ..Default::default()
+    };
+    let camera = cam::Camera::from_key(&key, 1.5);
+    let voxel = 0.25;
+
+    // Grid lines sit on voxel edges under the model, fade out before they reach as far as they go,
+    // and never get denser than allowed
+    let grid = guides::grid_lines(&camera, voxel);
+    assert!(!grid.is_empty());
+    let reach = camera.distance * GRID_REACH;
+    let middle = glam::vec2(camera.target.x, camera.target.z);
+    for line in &grid {
+        assert!(line.pos0.y < -1.0 && line.pos0.y > -1.0 - 1e-3);
+        let across = match line.pos0.x == line.pos1.x {
+            true => line.pos0.x,
+            false => line.pos0.z,
+        };
+        let steps = (across + 1.0) / voxel;
+        assert!((steps - steps.round()).abs() < 1e-3);
+        assert!(line.color[3] > 0.0);
+
+        let mid = (line.pos0 + line.pos1) / 2.0;
+        assert!(glam::vec2(mid.x, mid.z).distance(middle) < reach);
+    }
+    let far = cam::Camera {
+        distance: CAM_DISTANCE_MAX,
+        ..camera
+    };
+    let far_grid = guides::grid_lines(&far, voxel);
+    let lines_across = far_grid
+        .iter()
+        .filter(|l| l.pos0.x == l.pos1.x)
+        .map(|l| (l.pos0.x * 1e3).round() as i64)
+        .collect::<std::collections::BTreeSet<_>>();
+    assert!(lines_across.len() <= GRID_LINES_MAX as usize * 2 + 1);
+
+    // A box has twelve edges, each along one axis, and spans whole voxels
+    let (min, max) = (glam::vec3(-1.0, -1.0, -0.5), glam::vec3(0.5, 1.0, 0.0));
+    let edges = guides::box_lines(min, max);
+    assert_eq!(edges.len(), 12);
+    for line in &edges {
+        let d = (line.pos1 - line.pos0).abs();
+        assert_eq!(d.cmpgt(glam::Vec3::ZERO).bitmask().count_ones(), 1);
+    }
+    assert_eq!(guides::box_dimensions(min, max, voxel), [6, 8, 2]);
+
+    // The target lands in the middle of the window, and axes point the way the view sees them
+    let size = glam::vec2(800.0, 600.0);
+    let center = camera.project(camera.target, size).unwrap();
+    assert!((center - size / 2.0).length() < 1e-2);
+    assert!(camera
+        .project(camera.eye() + camera.direction(), size)
+        .is_none());
+
+    let front = cam::Camera::from_key(
+        &CameraKey {
+            eye: glam::Vec3::Z,
+            target: glam::Vec3::ZERO,
+            ..Default::default()
+        },
+        1.0,
+    );
+    let [x, y, z] = guides::gizmo_axes(&front);
+    assert!((x.0 - glam::Vec2::X).length() < 1e-5);
+    assert!((y.0 - glam::Vec2::NEG_Y).length() < 1e-5);
+    assert!(z.0.length() < 1e-5 && z.1 > 0.9);
+}